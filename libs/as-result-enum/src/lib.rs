@@ -47,7 +47,13 @@ pub fn as_result_enum(input: TokenStream) -> TokenStream {
     let variant_definitions = fields.iter().filter_map(|field| {
         let field_name = field.ident.as_ref().unwrap();
         let field_type = &field.ty;
-        let field_attrs: Vec<Attribute> = field.attrs.clone();
+        // `#[serde(default)]` is only valid on fields, it cannot be forwarded to the variant
+        let field_attrs: Vec<Attribute> = field
+            .attrs
+            .iter()
+            .filter(|attr| !is_serde_default(attr))
+            .cloned()
+            .collect();
 
         let stripped_type = strip_option_arc_rwlock(field_type);
 
@@ -158,6 +164,19 @@ fn strip_option_arc_rwlock(ty: &Type) -> Type {
     ty.clone()
 }
 
+/// Check whether the attribute is `#[serde(default)]` or `#[serde(default = "...")]`
+fn is_serde_default(attr: &Attribute) -> bool {
+    attr.path().is_ident("serde")
+        && attr
+            .parse_args::<Meta>()
+            .ok()
+            .is_some_and(|meta| match meta {
+                Meta::Path(path) => path.is_ident("default"),
+                Meta::NameValue(name_value) => name_value.path.is_ident("default"),
+                _ => false,
+            })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -199,6 +218,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_is_serde_default() {
+        let attr: Attribute = parse_quote!(#[serde(default)]);
+        assert!(is_serde_default(&attr));
+
+        let attr: Attribute = parse_quote!(#[serde(default = "default_value")]);
+        assert!(is_serde_default(&attr));
+
+        let attr: Attribute = parse_quote!(#[serde(skip)]);
+        assert!(!is_serde_default(&attr));
+
+        let attr: Attribute = parse_quote!(#[doc = "documentation"]);
+        assert!(!is_serde_default(&attr));
+    }
+
     #[test]
     fn test_to_pascal_case() {
         assert_eq!(to_pascal_case("test_case"), "TestCase");
@@ -0,0 +1,74 @@
+use crate::authorized_requests::constants::GOOGLE_ALLOWED_DOMAINS;
+use crate::state::storage_provider::StorageProvider;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// The handle of a storage provider account, used to look up its tokens
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Type)]
+pub struct ProviderAccount {
+    /// The stable identifier of the account
+    pub id: String,
+}
+
+/// Check whether the access token of an account can be sent to a url
+///
+/// Only https urls of the provider domains are allowed so that the token cannot be leaked to
+/// third parties.
+///
+/// # Arguments
+///
+/// * `provider` - The provider of the account
+/// * `url` - The url of the request
+///
+/// # Returns
+///
+/// Whether the access token can be sent to the url
+pub fn is_allowed_url(provider: &StorageProvider, url: &Url) -> bool {
+    let allowed_domains: &[&str] = match provider {
+        StorageProvider::Google => GOOGLE_ALLOWED_DOMAINS,
+        _ => &[],
+    };
+
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+
+    url.scheme() == "https"
+        && allowed_domains.iter().any(|domain| {
+            host == *domain
+                || host
+                    .strip_suffix(domain)
+                    .is_some_and(|subdomain| subdomain.ends_with('.'))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_allowed_url() {
+        let allowed =
+            |url: &str| is_allowed_url(&StorageProvider::Google, &Url::parse(url).unwrap());
+
+        assert!(allowed("https://www.googleapis.com/drive/v3/files"));
+        assert!(allowed("https://googleapis.com/drive/v3/files"));
+        assert!(allowed(
+            "https://doc-0s-1c-docs.googleusercontent.com/docs/file"
+        ));
+
+        assert!(!allowed("http://www.googleapis.com/drive/v3/files"));
+        assert!(!allowed("https://www.googleapis.com.example.com/drive"));
+        assert!(!allowed("https://evilgoogleapis.com/drive"));
+        assert!(!allowed("https://example.com/?www.googleapis.com"));
+    }
+
+    #[test]
+    fn test_is_allowed_url_unsupported_provider() {
+        assert!(!is_allowed_url(
+            &StorageProvider::Dropbox,
+            &Url::parse("https://www.googleapis.com").unwrap()
+        ));
+    }
+}
@@ -0,0 +1,355 @@
+use crate::authorized_requests::account::{ProviderAccount, is_allowed_url};
+use crate::authorized_requests::constants::HTTP_CLIENT;
+use crate::authorized_requests::token::{
+    find_provider_data, get_access_token, refresh_access_token,
+};
+use crate::state::state::AppState;
+use crate::state::storage_provider::StorageProvider;
+use futures_util::StreamExt;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, Url};
+use serde::{Deserialize, Serialize};
+use specta::{Type, specta};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, State, command};
+use tauri_plugin_fs::FsExt;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tracing::debug;
+
+/// A request to a storage provider API
+#[derive(Debug, Clone, Serialize, Deserialize, Default, Type)]
+pub struct AuthorizedRequest {
+    /// The http method, defaults to `GET`
+    pub method: Option<String>,
+    /// The request headers, the authorization header is set automatically
+    pub headers: HashMap<String, String>,
+    /// The request body
+    pub body: Option<Vec<u8>>,
+}
+
+/// The response of a storage provider API
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct AuthorizedResponse {
+    /// The http status code
+    pub status: u16,
+    /// The http status text
+    pub status_text: String,
+    /// The response headers
+    pub headers: HashMap<String, String>,
+    /// The response body
+    pub body: Vec<u8>,
+}
+
+/// The progress of a download
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Type)]
+pub struct DownloadProgress {
+    /// The bytes received since the last progress event
+    pub progress: u64,
+    /// The bytes received so far
+    pub progress_total: u64,
+    /// The size of the file, 0 if unknown
+    pub total: u64,
+}
+
+/// Send a request to a storage provider API using the access token of an account
+///
+/// The access token never leaves the backend, it is refreshed if expired and attached to the
+/// request only if the url belongs to the provider.
+///
+/// # Arguments
+///
+/// * `app` - The Tauri app handle.
+/// * `state` - The application state.
+/// * `account` - The account whose access token authorizes the request.
+/// * `url` - The url of the request.
+/// * `request` - The request.
+///
+/// # Returns
+///
+/// The response
+#[command]
+#[specta]
+pub async fn authorized_fetch(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    account: ProviderAccount,
+    url: String,
+    request: AuthorizedRequest,
+) -> Result<AuthorizedResponse, String> {
+    let provider_data = find_provider_data(&state, &account).await?;
+    let url = parse_allowed_url(&provider_data.provider, url.as_str())?;
+    let access_token = get_access_token(&app, &state, &account).await?;
+
+    let method = match request.method {
+        Some(method) => {
+            Method::from_str(method.to_uppercase().as_str()).map_err(|e| e.to_string())?
+        }
+        None => Method::GET,
+    };
+
+    debug!("Sending authorized {} request to {}", method, url);
+    let mut builder = HTTP_CLIENT
+        .request(method, url)
+        .headers(make_headers(&request.headers, access_token.as_str())?);
+    if let Some(body) = request.body {
+        builder = builder.body(body);
+    }
+
+    let response = builder.send().await.map_err(|e| e.to_string())?;
+
+    let status = response.status();
+    let headers = response
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| {
+            value
+                .to_str()
+                .ok()
+                .map(|value| (name.to_string(), value.to_owned()))
+        })
+        .collect();
+    let body = response.bytes().await.map_err(|e| e.to_string())?;
+
+    Ok(AuthorizedResponse {
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or_default().to_owned(),
+        headers,
+        body: body.to_vec(),
+    })
+}
+
+/// Download a file from a storage provider using the access token of an account
+///
+/// The file can only be saved where the frontend is allowed to write, the temporary directory or
+/// the paths granted to the file system scope.
+///
+/// # Arguments
+///
+/// * `app` - The Tauri app handle.
+/// * `state` - The application state.
+/// * `account` - The account whose access token authorizes the download.
+/// * `url` - The url of the file.
+/// * `file_path` - The path to save the file to.
+/// * `on_progress` - The channel receiving the download progress.
+///
+/// # Returns
+///
+/// Nothing
+#[command]
+#[specta]
+pub async fn authorized_download(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    account: ProviderAccount,
+    url: String,
+    file_path: String,
+    on_progress: Channel<DownloadProgress>,
+) -> Result<(), String> {
+    let provider_data = find_provider_data(&state, &account).await?;
+    let url = parse_allowed_url(&provider_data.provider, url.as_str())?;
+    let file_path = parse_allowed_destination(&app, file_path.as_str())?;
+    let access_token = get_access_token(&app, &state, &account).await?;
+
+    debug!("Downloading {} to {}", url, file_path.display());
+    let response = HTTP_CLIENT
+        .get(url)
+        .headers(make_headers(&HashMap::new(), access_token.as_str())?)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    let total = response.content_length().unwrap_or_default();
+    let mut progress_total = 0;
+    let mut file = File::create(file_path).await.map_err(|e| e.to_string())?;
+    let mut stream = response.bytes_stream();
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| e.to_string())?;
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+
+        progress_total += chunk.len() as u64;
+        on_progress
+            .send(DownloadProgress {
+                progress: chunk.len() as u64,
+                progress_total,
+                total,
+            })
+            .map_err(|e| e.to_string())?;
+    }
+
+    file.flush().await.map_err(|e| e.to_string())
+}
+
+/// Refresh the access token of an account
+///
+/// # Arguments
+///
+/// * `app` - The Tauri app handle.
+/// * `state` - The application state.
+/// * `account` - The account to refresh the access token of.
+///
+/// # Returns
+///
+/// Nothing
+#[command]
+#[specta]
+pub async fn refresh_provider(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    account: ProviderAccount,
+) -> Result<(), String> {
+    refresh_access_token(&app, &state, &account).await?;
+
+    Ok(())
+}
+
+/// Parse the url of a request refusing the urls the access token cannot be sent to
+///
+/// # Arguments
+///
+/// * `provider` - The provider of the account whose access token authorizes the request.
+/// * `url` - The url of the request.
+///
+/// # Returns
+///
+/// The parsed url
+fn parse_allowed_url(provider: &StorageProvider, url: &str) -> Result<Url, String> {
+    let url = Url::parse(url).map_err(|e| e.to_string())?;
+
+    if !is_allowed_url(provider, &url) {
+        return Err(format!("Requests to {} are not allowed", url));
+    }
+
+    Ok(url)
+}
+
+/// Parse the destination of a download refusing the paths outside of the file system scope, used
+/// as well before rewriting a downloaded file
+///
+/// # Arguments
+///
+/// * `app` - The Tauri app handle.
+/// * `file_path` - The path to save the file to.
+///
+/// # Returns
+///
+/// The parsed path
+pub(crate) fn parse_allowed_destination(
+    app: &AppHandle,
+    file_path: &str,
+) -> Result<PathBuf, String> {
+    let path = PathBuf::from(file_path);
+    let temp_dir = app.path().temp_dir().map_err(|e| e.to_string())?;
+    let scope = app.fs_scope();
+
+    // the temporary directory is granted recursively by the capabilities
+    let allowed = is_within_directory(&path, &temp_dir) || scope.is_allowed(&path);
+    if !allowed || scope.is_forbidden(&path) {
+        return Err(format!("Downloads to {} are not allowed", file_path));
+    }
+
+    Ok(path)
+}
+
+/// Check whether a path is inside a directory, the paths escaping it with `..` are refused
+///
+/// # Arguments
+///
+/// * `path` - The path.
+/// * `directory` - The directory.
+///
+/// # Returns
+///
+/// Whether the path is inside the directory
+fn is_within_directory(path: &Path, directory: &Path) -> bool {
+    path.is_absolute()
+        && !path
+            .components()
+            .any(|component| component == Component::ParentDir)
+        && path.starts_with(directory)
+        && path != directory
+}
+
+/// Build the headers of a request attaching the access token
+///
+/// # Arguments
+///
+/// * `headers` - The request headers.
+/// * `access_token` - The access token.
+///
+/// # Returns
+///
+/// The request headers
+fn make_headers(
+    headers: &HashMap<String, String>,
+    access_token: &str,
+) -> Result<HeaderMap, String> {
+    let mut header_map = HeaderMap::new();
+
+    for (name, value) in headers {
+        header_map.insert(
+            HeaderName::from_str(name.as_str()).map_err(|e| e.to_string())?,
+            HeaderValue::from_str(value.as_str()).map_err(|e| e.to_string())?,
+        );
+    }
+
+    let mut authorization = HeaderValue::from_str(format!("Bearer {}", access_token).as_str())
+        .map_err(|e| e.to_string())?;
+    authorization.set_sensitive(true);
+    header_map.insert(AUTHORIZATION, authorization);
+
+    Ok(header_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_make_headers() {
+        let headers = HashMap::from([
+            ("Content-Type".to_owned(), "application/json".to_owned()),
+            ("Authorization".to_owned(), "Bearer forged".to_owned()),
+        ]);
+
+        let header_map = make_headers(&headers, "token").unwrap();
+        assert_eq!(header_map.get("content-type").unwrap(), "application/json");
+        assert_eq!(header_map.get(AUTHORIZATION).unwrap(), "Bearer token");
+        assert!(header_map.get(AUTHORIZATION).unwrap().is_sensitive());
+    }
+
+    #[test]
+    fn test_is_within_directory() {
+        let directory = std::env::temp_dir();
+
+        assert!(is_within_directory(&directory.join("file.txt"), &directory));
+        assert!(is_within_directory(
+            &directory.join("nested").join("file.txt"),
+            &directory
+        ));
+        assert!(!is_within_directory(&directory, &directory));
+        assert!(!is_within_directory(
+            &directory.join("..").join("file.txt"),
+            &directory
+        ));
+        assert!(!is_within_directory(Path::new("file.txt"), &directory));
+        assert!(!is_within_directory(
+            &directory.parent().unwrap().join("file.txt"),
+            &directory
+        ));
+    }
+
+    #[test]
+    fn test_parse_allowed_url() {
+        let provider = StorageProvider::Google;
+
+        assert!(parse_allowed_url(&provider, "https://www.googleapis.com/drive/v3/about").is_ok());
+        assert!(parse_allowed_url(&provider, "https://example.com/").is_err());
+        assert!(parse_allowed_url(&provider, "not a url").is_err());
+    }
+}
@@ -0,0 +1,18 @@
+use once_cell::sync::Lazy;
+
+/// The http client shared by the requests to the storage providers so that the connections are
+/// reused
+pub static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+
+/// The endpoint used to refresh the Google OAuth access tokens
+pub static GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
+/// The Google OAuth client id
+pub static GOOGLE_CLIENT_ID: &str =
+    "243418232258-pi0e0sa9g3ol72c212hg7k496g51k765.apps.googleusercontent.com";
+
+/// The Google OAuth client secret
+pub static GOOGLE_CLIENT_SECRET: &str = "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";
+
+/// The domains (and their subdomains) the Google access tokens can be sent to
+pub static GOOGLE_ALLOWED_DOMAINS: &[&str] = &["googleapis.com", "googleusercontent.com"];
@@ -0,0 +1,6 @@
+mod account;
+mod commands;
+mod constants;
+mod token;
+
+pub use commands::*;
@@ -0,0 +1,194 @@
+use crate::authorized_requests::account::ProviderAccount;
+use crate::authorized_requests::constants::{
+    GOOGLE_CLIENT_ID, GOOGLE_CLIENT_SECRET, GOOGLE_TOKEN_URL, HTTP_CLIENT,
+};
+use crate::crypt::{CryptData, CryptDataMode, KdfAlgorithm};
+use crate::secret_manager::read_data_key;
+use crate::state::account_registry::find_account;
+use crate::state::emit_state_changed;
+use crate::state::provider_data::{AccountCredential, ProviderData};
+use crate::state::save;
+use crate::state::state::{AppState, AppStateDeepKeys};
+use crate::state::storage_provider::StorageProvider;
+use serde::Deserialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, State};
+use tracing::debug;
+
+/// The error returned when the account is not authorized by OAuth tokens
+static NOT_OAUTH_ERROR: &str = "The account is not authorized by OAuth tokens";
+
+/// The response of the OAuth token endpoint to a refresh request
+#[derive(Debug, Deserialize)]
+struct RefreshResponse {
+    /// The new access token
+    access_token: String,
+    /// The lifetime of the access token in seconds
+    expires_in: u64,
+    /// The type of the access token
+    token_type: Option<String>,
+    /// The scopes granted to the application, separated by spaces
+    scope: Option<String>,
+}
+
+/// Get the access token of an account, refreshing it if it is expired
+///
+/// # Arguments
+///
+/// * `app` - The Tauri app handle.
+/// * `state` - The application state.
+/// * `account` - The account to get the access token of.
+///
+/// # Returns
+///
+/// The decrypted access token
+pub async fn get_access_token(
+    app: &AppHandle,
+    state: &State<'_, AppState>,
+    account: &ProviderAccount,
+) -> Result<String, String> {
+    let provider_data = find_provider_data(state, account).await?;
+    let AccountCredential::OAuth {
+        access_token,
+        expiry,
+        ..
+    } = provider_data.credential
+    else {
+        return Err(NOT_OAUTH_ERROR.to_owned());
+    };
+
+    if expiry <= now() {
+        return refresh_access_token(app, state, account).await;
+    }
+
+    decrypt_token(access_token).await
+}
+
+/// Refresh the access token of an account and store it in the state
+///
+/// # Arguments
+///
+/// * `app` - The Tauri app handle.
+/// * `state` - The application state.
+/// * `account` - The account to refresh the access token of.
+///
+/// # Returns
+///
+/// The new decrypted access token
+pub async fn refresh_access_token(
+    app: &AppHandle,
+    state: &State<'_, AppState>,
+    account: &ProviderAccount,
+) -> Result<String, String> {
+    let provider_data = find_provider_data(state, account).await?;
+    let AccountCredential::OAuth { refresh_token, .. } = provider_data.credential else {
+        return Err(NOT_OAUTH_ERROR.to_owned());
+    };
+    let refresh_token = decrypt_token(refresh_token).await?;
+
+    let request = match provider_data.provider {
+        StorageProvider::Google => HTTP_CLIENT.post(GOOGLE_TOKEN_URL).form(&[
+            ("refresh_token", refresh_token.as_str()),
+            ("client_id", GOOGLE_CLIENT_ID),
+            ("client_secret", GOOGLE_CLIENT_SECRET),
+            ("grant_type", "refresh_token"),
+        ]),
+        _ => return Err("Provider not supported".to_owned()),
+    };
+
+    debug!("Refreshing the access token of {}", account.id);
+    let response = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Cannot refresh the access token: {}", e))?
+        .json::<RefreshResponse>()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // the data key has full entropy, the memory-hard key derivation is not needed
+    let data_key = read_data_key().await?;
+    let new_access_token = CryptData::new_with_kdf(
+        response.access_token.clone().into_bytes(),
+        CryptDataMode::to_u8(vec![CryptDataMode::Encrypt, CryptDataMode::Encode]),
+        Some(data_key.as_slice()),
+        None,
+        KdfAlgorithm::HkdfSha3_512,
+    );
+    drop(data_key);
+
+    let mut writable_state = state.write().await;
+    let stored_provider_data = writable_state
+        .providers
+        .iter_mut()
+        .find(|data| data.id == account.id)
+        .ok_or("Account not found")?;
+    let AccountCredential::OAuth {
+        access_token,
+        expiry,
+        token_type,
+        scopes,
+        ..
+    } = &mut stored_provider_data.credential
+    else {
+        return Err(NOT_OAUTH_ERROR.to_owned());
+    };
+    *access_token = new_access_token;
+    *expiry = now() + response.expires_in;
+    if let Some(new_token_type) = response.token_type {
+        *token_type = new_token_type;
+    }
+    if let Some(scope) = response.scope {
+        *scopes = scope.split_whitespace().map(str::to_owned).collect();
+    }
+    drop(writable_state);
+
+    save(app.clone(), state.clone()).await?;
+    emit_state_changed(app, state, AppStateDeepKeys::Providers).await;
+
+    Ok(response.access_token)
+}
+
+/// Find the data of an account
+///
+/// # Arguments
+///
+/// * `state` - The application state.
+/// * `account` - The account to look for.
+///
+/// # Returns
+///
+/// The data of the account
+pub async fn find_provider_data(
+    state: &State<'_, AppState>,
+    account: &ProviderAccount,
+) -> Result<ProviderData, String> {
+    find_account(&state.read().await.providers, account.id.as_str()).cloned()
+}
+
+/// Decrypt a token stored in the state
+///
+/// # Arguments
+///
+/// * `token` - The encrypted token.
+///
+/// # Returns
+///
+/// The decrypted token
+async fn decrypt_token(mut token: CryptData) -> Result<String, String> {
+    let data_key = read_data_key().await?;
+
+    token.get_raw_data_as_string(Some(data_key.as_slice()))
+}
+
+/// Get the current utc unix timestamp
+///
+/// # Returns
+///
+/// The current utc unix timestamp
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
@@ -1,8 +1,10 @@
 use crate::crypt::encoding::{decode, encode};
-use crate::crypt::encryption::{decrypt, encrypt};
-use crate::crypt::hash::hash;
-use crate::crypt::{CryptDataMode, DerivedKey, ENCRYPTION_KEY_LENGTH, hmac};
-use crate::state::PASSWORD;
+use crate::crypt::{
+    AeadAlgorithm, CRYPT_DATA_FORMAT_VERSION, CRYPT_DATA_LEGACY_FORMAT_VERSION, CryptDataMode,
+    DataEncoding, ENCRYPTION_KEY_LENGTH, KdfAlgorithm, KdfParams, NonceLayout, SerializedKdf, hash,
+    hash_password, hmac, verify, verify_password,
+};
+use crate::secret_manager::read_data_key;
 use base64ct::Encoding;
 use chacha20poly1305::KeyInit;
 use chacha20poly1305::aead::Aead;
@@ -27,6 +29,11 @@ pub struct CryptData {
     mode: u8,
     /// The salt applied when deriving the encryption key
     salt: Option<Vec<u8>>,
+    /// The key derivation function (and its cost parameters) used to derive the encryption key or
+    /// the password hash
+    kdf: KdfAlgorithm,
+    /// The authenticated encryption algorithm used to encrypt the data
+    aead: AeadAlgorithm,
     /// The list of related keys in the parent struct, this is used to understand which values are
     /// required to (re-)compute the hash
     pub related_keys: Vec<String>,
@@ -37,6 +44,8 @@ impl PartialEq<Self> for CryptData {
         self.data == other.data
             && self.mode == other.mode
             && self.salt == other.salt
+            && self.kdf == other.kdf
+            && self.aead == other.aead
             && self.related_keys == other.related_keys
     }
 }
@@ -47,7 +56,10 @@ impl Serialize for CryptData {
         S: Serializer,
     {
         let mut this = self.clone();
-        let mut state = serializer.serialize_struct("CryptData", 4)?;
+        let mut state = serializer.serialize_struct("CryptData", 9)?;
+
+        // Always write the newest envelope version
+        state.serialize_field("version", &CRYPT_DATA_FORMAT_VERSION)?;
 
         // If the data is already encoded or hashed, serialize it as a string
         if CryptDataMode::should_encode(this.mode)
@@ -79,6 +91,18 @@ impl Serialize for CryptData {
 
         state.serialize_field("related_keys", &this.related_keys)?;
 
+        // The cipher and the nonce layout are only meaningful for encrypted data
+        if CryptDataMode::should_encrypt(this.mode) {
+            state.serialize_field("aead", &Some(this.aead))?;
+            state.serialize_field("nonce", &Some(NonceLayout::of(this.aead)))?;
+        } else {
+            state.serialize_field("aead", &None::<AeadAlgorithm>)?;
+            state.serialize_field("nonce", &None::<NonceLayout>)?;
+        }
+
+        state.serialize_field("kdf", &this.kdf)?;
+        state.serialize_field("encoding", &DataEncoding::Base64Unpadded)?;
+
         state.end()
     }
 }
@@ -91,10 +115,17 @@ impl<'ext_de> Deserialize<'ext_de> for CryptData {
         #[derive(Deserialize)]
         #[serde(field_identifier, rename_all = "snake_case")]
         enum Field {
+            Version,
             Data,
             Mode,
             Salt,
             RelatedKeys,
+            Aead,
+            Nonce,
+            Kdf,
+            Encoding,
+            #[serde(other)]
+            Unknown,
         };
 
         struct CryptDataVisitor;
@@ -109,13 +140,20 @@ impl<'ext_de> Deserialize<'ext_de> for CryptData {
             where
                 A: serde::de::MapAccess<'de>,
             {
+                let mut version = None;
                 let mut data = None;
                 let mut mode = None;
                 let mut salt = None;
                 let mut related_keys = None;
+                let mut aead = None;
+                let mut nonce = None;
+                let mut kdf = None;
 
                 while let Some(key) = map.next_key()? {
                     match key {
+                        Field::Version => {
+                            version = Some(map.next_value::<u8>()?);
+                        }
                         Field::Data => {
                             data = Some(map.next_value::<String>()?);
                         }
@@ -128,6 +166,21 @@ impl<'ext_de> Deserialize<'ext_de> for CryptData {
                         Field::RelatedKeys => {
                             related_keys = Some(map.next_value::<Vec<String>>()?);
                         }
+                        Field::Aead => {
+                            aead = map.next_value::<Option<AeadAlgorithm>>()?;
+                        }
+                        Field::Nonce => {
+                            nonce = map.next_value::<Option<NonceLayout>>()?;
+                        }
+                        Field::Kdf => {
+                            kdf = map.next_value::<Option<SerializedKdf>>()?;
+                        }
+                        Field::Encoding => {
+                            map.next_value::<DataEncoding>()?;
+                        }
+                        Field::Unknown => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                        }
                     }
                 }
 
@@ -143,6 +196,25 @@ impl<'ext_de> Deserialize<'ext_de> for CryptData {
                     return Err(serde::de::Error::missing_field("related_keys"));
                 }
 
+                let version = version.unwrap_or(CRYPT_DATA_LEGACY_FORMAT_VERSION);
+                if version > CRYPT_DATA_FORMAT_VERSION {
+                    return Err(serde::de::Error::custom(format!(
+                        "Unsupported CryptData format version {}",
+                        version
+                    )));
+                }
+
+                // Version 1 envelopes imply the cipher, version 2 envelopes record it
+                let aead = aead.unwrap_or_default();
+                if let Some(nonce) = nonce
+                    && nonce != NonceLayout::of(aead)
+                {
+                    return Err(serde::de::Error::custom(format!(
+                        "Unsupported nonce layout {:?} for {:?}",
+                        nonce, aead
+                    )));
+                }
+
                 let data = data.unwrap();
                 let mode = mode.unwrap();
                 let related_keys = related_keys.unwrap();
@@ -151,6 +223,8 @@ impl<'ext_de> Deserialize<'ext_de> for CryptData {
                 crypt_data.data = data.as_bytes().to_vec();
                 crypt_data.mode = mode;
                 crypt_data.raw_data = None;
+                crypt_data.kdf = kdf.map(KdfAlgorithm::from).unwrap_or_default();
+                crypt_data.aead = aead;
                 crypt_data.related_keys = related_keys;
 
                 if salt.is_some() {
@@ -165,7 +239,17 @@ impl<'ext_de> Deserialize<'ext_de> for CryptData {
 
         deserializer.deserialize_struct(
             "CryptData",
-            &["mode", "data", "salt", "related_keys"],
+            &[
+                "version",
+                "data",
+                "mode",
+                "salt",
+                "related_keys",
+                "aead",
+                "nonce",
+                "kdf",
+                "encoding",
+            ],
             CryptDataVisitor,
         )
     }
@@ -184,6 +268,8 @@ impl Debug for CryptData {
         pending_output
             .field("mode", &self.mode)
             .field("salt", &self.salt)
+            .field("kdf", &self.kdf)
+            .field("aead", &self.aead)
             .field("related_keys", &self.related_keys)
             .finish()
     }
@@ -217,8 +303,42 @@ impl CryptData {
         CryptDataMode::from_u8(self.mode)
     }
 
+    /// Check whether the password hash was computed using the legacy single salted SHA3-512 pass
+    /// and should be re-created to benefit from the memory-hard key derivation
+    ///
+    /// Encrypted data is not considered as it is keyed by the high entropy data key wrapped in the
+    /// state key slots.
+    ///
+    /// # Returns
+    ///
+    /// Whether the data should be re-created
+    pub fn needs_rehash(&self) -> bool {
+        self.kdf == KdfAlgorithm::HkdfSha3_512 && CryptDataMode::is_password_hash(self.mode)
+    }
+
+    /// Verify that the provided data matches the hash stored in this instance
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to verify
+    ///
+    /// # Returns
+    ///
+    /// Whether the data matches the hash
+    pub fn verify(&self, data: &[u8]) -> bool {
+        let hash = self.get_data_as_string();
+
+        match &self.kdf {
+            KdfAlgorithm::Argon2id(params) => verify_password(data, hash.as_str(), params),
+            KdfAlgorithm::HkdfSha3_512 => verify(data, hash.as_str()),
+        }
+    }
+
     /// Create a new CryptData struct
     ///
+    /// The key is assumed to be a password: encryption keys and password hashes are derived using
+    /// the memory-hard key derivation.
+    ///
     /// # Arguments
     ///
     /// * `data` - The data to manage
@@ -233,12 +353,44 @@ impl CryptData {
         mode: u8,
         key: Option<&[u8]>,
         related_keys: Option<Vec<String>>,
+    ) -> Self {
+        // Keys derived from the password and password hashes use the memory-hard key derivation
+        let kdf = if CryptDataMode::should_encrypt(mode) || CryptDataMode::is_password_hash(mode) {
+            KdfAlgorithm::Argon2id(KdfParams::default())
+        } else {
+            KdfAlgorithm::HkdfSha3_512
+        };
+
+        Self::new_with_kdf(raw_data, mode, key, related_keys, kdf)
+    }
+
+    /// Create a new CryptData struct using a specific key derivation function
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to manage
+    /// * `mode` - The working mode of the data
+    /// * `key` - The key to use for encryption and decryption (optional)
+    /// * `related_keys` - The related keys in the parent struct (optional)
+    /// * `kdf` - The key derivation function used to derive the encryption key
+    ///
+    /// # Returns
+    ///
+    /// The CryptData struct
+    pub fn new_with_kdf(
+        raw_data: Vec<u8>,
+        mode: u8,
+        key: Option<&[u8]>,
+        related_keys: Option<Vec<String>>,
+        kdf: KdfAlgorithm,
     ) -> Self {
         let mut instance = Self {
             raw_data: Some(raw_data),
             data: Vec::new(),
             mode,
             salt: None,
+            kdf,
+            aead: AeadAlgorithm::default(),
             related_keys: related_keys.unwrap_or_default(),
         };
 
@@ -284,7 +436,14 @@ impl CryptData {
     /// Nothing
     fn hash(&mut self) {
         if CryptDataMode::should_hash(self.mode) {
-            self.data = hash(&self.raw_data.as_ref().unwrap(), None).into_bytes();
+            if let KdfAlgorithm::Argon2id(params) = &self.kdf {
+                match hash_password(self.raw_data.as_ref().unwrap(), None, params) {
+                    Ok(hash) => self.data = hash.into_bytes(),
+                    Err(e) => error!("Failed to hash password: {}", e),
+                }
+            } else {
+                self.data = hash(&self.raw_data.as_ref().unwrap(), None).into_bytes();
+            }
         }
     }
 
@@ -322,11 +481,9 @@ impl CryptData {
             debug!("Data is not encrypted, encrypting it");
 
             // Derive the key using the salt if it exists or a new one will be generated during the process
-            let derived_key = if let Some(salt) = &self.salt {
-                DerivedKey::from_vec(key.to_vec(), Some(salt), ENCRYPTION_KEY_LENGTH as u8)?
-            } else {
-                DerivedKey::from_vec(key.to_vec(), None, ENCRYPTION_KEY_LENGTH as u8)?
-            };
+            let derived_key =
+                self.kdf
+                    .derive(key, self.salt.as_deref(), ENCRYPTION_KEY_LENGTH as u8)?;
             debug!("Key derived successfully");
 
             // store the salt
@@ -334,7 +491,9 @@ impl CryptData {
             debug!("Salt stored");
 
             // finally perform the encryption
-            self.data = encrypt(&self.raw_data.as_ref().unwrap(), &derived_key.key)?;
+            self.data = self
+                .aead
+                .encrypt(&self.raw_data.as_ref().unwrap(), &derived_key.key)?;
             debug!("Data encrypted successfully");
         }
 
@@ -362,8 +521,9 @@ impl CryptData {
             debug!("Salt correctly retrieved");
 
             debug!("Deriving key from salt");
-            let derived_key =
-                DerivedKey::from_vec(key.to_vec(), Some(&salt), ENCRYPTION_KEY_LENGTH as u8)?;
+            let derived_key = self
+                .kdf
+                .derive(key, Some(&salt), ENCRYPTION_KEY_LENGTH as u8)?;
             debug!("Key derived successfully");
 
             let data = if CryptDataMode::should_encode(self.mode) {
@@ -373,7 +533,7 @@ impl CryptData {
                 &self.data
             };
 
-            self.raw_data = Some(decrypt(data, &derived_key.key)?);
+            self.raw_data = Some(self.aead.decrypt(data, &derived_key.key)?);
             debug!("Data decrypted successfully");
         }
 
@@ -429,6 +589,23 @@ impl CryptData {
         }
     }
 
+    /// Decrypt the stored data ignoring the cached raw data, this ensures the key is actually
+    /// checked against the stored data
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to use for decryption
+    ///
+    /// # Returns
+    ///
+    /// The raw data
+    pub fn decrypt_stored_data(&self, key: &[u8]) -> Result<Vec<u8>, String> {
+        let mut instance = self.clone();
+        instance.raw_data = None;
+
+        instance.get_raw_data(Some(key))
+    }
+
     /// Get the raw data as a string
     ///
     /// # Arguments
@@ -444,44 +621,6 @@ impl CryptData {
     }
 }
 
-/// Get the raw data as a string
-///
-/// # Arguments
-///
-/// * `state` - The state to get the data from
-/// * `data` - The data to get
-///
-/// # Returns
-///
-/// The raw data as a string
-#[command]
-#[specta]
-pub async fn crypt_data_get_raw_data_as_string(mut data: CryptData) -> Result<String, String> {
-    debug!("Getting raw data as string from {:?}", data);
-    let key = PASSWORD.get().ok_or("Password not set")?.read().await;
-
-    Ok(data.get_raw_data_as_string(Some(key.as_bytes()))?)
-}
-
-/// Get the raw data
-///
-/// # Arguments
-///
-/// * `state` - The state to get the data from
-/// * `data` - The data to get
-///
-/// # Returns
-///
-/// The raw data
-#[command]
-#[specta]
-pub async fn crypt_data_get_raw_data(mut data: CryptData) -> Result<Vec<u8>, String> {
-    debug!("Getting raw data from {:?}", data);
-    let key = PASSWORD.get().ok_or("Password not set")?.read().await;
-
-    Ok(data.get_raw_data(Some(key.as_bytes()))?)
-}
-
 /// Create a new CryptData struct using a fully qualified string
 ///
 /// # Arguments
@@ -516,16 +655,23 @@ pub async fn make_crypt_data_from_qualified_string(data: String) -> Result<Crypt
         return Err("No mode set".to_owned());
     }
 
-    let key = PASSWORD.get().ok_or("Password not set")?.read().await;
+    let key = read_data_key().await?;
 
-    Ok(CryptData::new(data, mode, Some(key.as_bytes()), None))
+    // the data key has full entropy, the memory-hard key derivation is not needed
+    Ok(CryptData::new_with_kdf(
+        data,
+        mode,
+        Some(key.as_slice()),
+        None,
+        KdfAlgorithm::HkdfSha3_512,
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::crypt::verify;
-    use tokio::sync::RwLock;
+    use crate::crypt::DerivedKey;
+    use crate::secret_manager::unlock_secret_store;
 
     #[test]
     fn test_new() {
@@ -636,42 +782,17 @@ mod tests {
     #[tokio::test]
     async fn test_make_crypt_data_from_qualified_string() {
         let key = b"supersecretkey";
-        let _ = PASSWORD.set(RwLock::new(String::from_utf8_lossy(key).to_string()));
+        unlock_secret_store(key.to_vec()).await;
 
         let qualified_data = "secret:test string".to_owned();
         let mut crypt_data = make_crypt_data_from_qualified_string(qualified_data)
             .await
             .unwrap();
+        assert_eq!(crypt_data.kdf, KdfAlgorithm::HkdfSha3_512);
         let raw_data_str = crypt_data.get_raw_data_as_string(Some(key)).unwrap();
         assert_eq!(raw_data_str, "test string");
     }
 
-    #[tokio::test]
-    async fn test_crypt_data_get_raw_data_as_string() {
-        let key = b"supersecretkey";
-        let _ = PASSWORD.set(RwLock::new(String::from_utf8_lossy(key).to_string()));
-
-        let qualified_data = "secret:test string".to_owned();
-        let crypt_data = make_crypt_data_from_qualified_string(qualified_data)
-            .await
-            .unwrap();
-        let raw_data_str = crypt_data_get_raw_data_as_string(crypt_data).await.unwrap();
-        assert_eq!(raw_data_str, "test string");
-    }
-
-    #[tokio::test]
-    async fn test_crypt_data_get_raw_data() {
-        let key = b"supersecretkey";
-        let _ = PASSWORD.set(RwLock::new(String::from_utf8_lossy(key).to_string()));
-
-        let qualified_data = "secret:test string".to_owned();
-        let crypt_data = make_crypt_data_from_qualified_string(qualified_data)
-            .await
-            .unwrap();
-        let raw_data = crypt_data_get_raw_data(crypt_data).await.unwrap();
-        assert_eq!(raw_data, "test string".as_bytes());
-    }
-
     #[test]
     fn test_serialize() {
         let key = b"supersecretkey";
@@ -731,7 +852,7 @@ mod tests {
     fn test_encrypt_with_salt() {
         let key = b"supersecretkey";
         let mut data = CryptData::new(vec![1, 2, 3], CryptDataMode::Encrypt as u8, Some(key), None);
-        data.salt = Some(vec![4, 5, 6]);
+        data.salt = Some(vec![4, 5, 6, 7, 8, 9, 10, 11]);
         data.encrypt(key).unwrap();
         assert!(!data.data.is_empty());
     }
@@ -766,4 +887,153 @@ mod tests {
         let raw_data_str = data.get_raw_data_as_string(None).unwrap();
         assert_eq!(raw_data_str.as_bytes(), &[1, 2, 3]);
     }
+
+    #[test]
+    fn test_decrypt_stored_data_ignores_cache() {
+        let key = b"supersecretkey";
+        let data = CryptData::new(
+            b"test string".to_vec(),
+            CryptDataMode::to_u8(vec![CryptDataMode::Encode, CryptDataMode::Encrypt]),
+            Some(key),
+            None,
+        );
+        assert_eq!(data.decrypt_stored_data(key).unwrap(), b"test string");
+        assert!(data.decrypt_stored_data(b"wrong key").is_err());
+    }
+
+    #[test]
+    fn test_password_hash_uses_argon2() {
+        let data = CryptData::new(
+            b"password".to_vec(),
+            CryptDataMode::to_u8(vec![CryptDataMode::Hash, CryptDataMode::PasswordHash]),
+            None,
+            None,
+        );
+        assert_eq!(data.kdf, KdfAlgorithm::Argon2id(KdfParams::default()));
+        assert!(!data.needs_rehash());
+        assert!(data.verify(b"password"));
+        assert!(!data.verify(b"wrong password"));
+    }
+
+    #[test]
+    fn test_verify_legacy_password_hash() {
+        let mut data = CryptData::new(
+            b"password".to_vec(),
+            CryptDataMode::to_u8(vec![CryptDataMode::Hash, CryptDataMode::PasswordHash]),
+            None,
+            None,
+        );
+        data.kdf = KdfAlgorithm::HkdfSha3_512;
+        data.data = hash(b"password", None).into_bytes();
+        assert!(data.needs_rehash());
+        assert!(data.verify(b"password"));
+        assert!(!data.verify(b"wrong password"));
+    }
+
+    #[test]
+    fn test_decrypt_legacy_hkdf_data() {
+        let key = b"supersecretkey";
+        let derived_key =
+            DerivedKey::from_vec(key.to_vec(), None, ENCRYPTION_KEY_LENGTH as u8).unwrap();
+        let mut data = CryptData::default();
+        data.mode = CryptDataMode::Encrypt as u8;
+        data.salt = Some(derived_key.salt);
+        data.data = AeadAlgorithm::XChaCha20Poly1305
+            .encrypt(&[1, 2, 3], &derived_key.key)
+            .unwrap();
+        assert!(!data.needs_rehash());
+        assert_eq!(data.get_raw_data(Some(key)).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_serialize_kdf_params() {
+        let key = b"supersecretkey";
+        let data = CryptData::new(
+            b"test string".to_vec(),
+            CryptDataMode::to_u8(vec![CryptDataMode::Encode, CryptDataMode::Encrypt]),
+            Some(key),
+            None,
+        );
+        let serialized = serde_json::to_string(&data).unwrap();
+        let mut deserialized = serde_json::from_str::<CryptData>(serialized.as_str()).unwrap();
+        assert_eq!(data.kdf, deserialized.kdf);
+        assert_eq!(
+            deserialized.get_raw_data_as_string(Some(key)).unwrap(),
+            "test string"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_without_kdf() {
+        let serialized = r#"{"data":"AQID","mode":2,"salt":null,"related_keys":[]}"#;
+        let mut deserialized = serde_json::from_str::<CryptData>(serialized).unwrap();
+        assert_eq!(deserialized.kdf, KdfAlgorithm::HkdfSha3_512);
+        assert_eq!(deserialized.get_raw_data(None).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_deserialize_first_argon2_format() {
+        let key = b"supersecretkey";
+        let data = CryptData::new(
+            b"test string".to_vec(),
+            CryptDataMode::to_u8(vec![CryptDataMode::Encode, CryptDataMode::Encrypt]),
+            Some(key),
+            None,
+        );
+        // Rewrite the envelope in the first Argon2id format: no version, bare kdf parameters
+        let mut value = serde_json::to_value(&data).unwrap();
+        let envelope = value.as_object_mut().unwrap();
+        for field in ["version", "aead", "nonce", "encoding"] {
+            envelope.remove(field);
+        }
+        envelope.insert(
+            "kdf".to_string(),
+            serde_json::to_value(KdfParams::default()).unwrap(),
+        );
+        let mut deserialized = serde_json::from_value::<CryptData>(value).unwrap();
+        assert_eq!(
+            deserialized.kdf,
+            KdfAlgorithm::Argon2id(KdfParams::default())
+        );
+        assert_eq!(deserialized.aead, AeadAlgorithm::XChaCha20Poly1305);
+        assert_eq!(
+            deserialized.get_raw_data_as_string(Some(key)).unwrap(),
+            "test string"
+        );
+    }
+
+    #[test]
+    fn test_serialize_writes_versioned_envelope() {
+        let data = CryptData::new(
+            b"test string".to_vec(),
+            CryptDataMode::to_u8(vec![CryptDataMode::Encode, CryptDataMode::Encrypt]),
+            Some(b"supersecretkey"),
+            None,
+        );
+        let value = serde_json::to_value(&data).unwrap();
+        assert_eq!(value["version"], CRYPT_DATA_FORMAT_VERSION);
+        assert_eq!(value["aead"], "xchacha20-poly1305");
+        assert_eq!(value["nonce"]["length"], 24);
+        assert_eq!(value["nonce"]["position"], "prefix");
+        assert_eq!(value["kdf"]["id"], "argon2id");
+        assert_eq!(value["encoding"], "base64-unpadded");
+
+        let data = CryptData::new(vec![1, 2, 3], CryptDataMode::Encode as u8, None, None);
+        let value = serde_json::to_value(&data).unwrap();
+        assert!(value["aead"].is_null());
+        assert!(value["nonce"].is_null());
+        assert_eq!(value["kdf"]["id"], "hkdf-sha3-512");
+    }
+
+    #[test]
+    fn test_deserialize_unsupported_version() {
+        let serialized = r#"{"version":255,"data":"AQID","mode":2,"salt":null,"related_keys":[]}"#;
+        assert!(serde_json::from_str::<CryptData>(serialized).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_mismatched_nonce_layout() {
+        let serialized = r#"{"version":2,"data":"AQID","mode":6,"salt":null,"related_keys":[],"aead":"xchacha20-poly1305","nonce":{"length":12,"position":"prefix"},"kdf":{"id":"hkdf-sha3-512"},"encoding":"base64-unpadded"}"#;
+        assert!(serde_json::from_str::<CryptData>(serialized).is_err());
+    }
 }
@@ -1,11 +1,67 @@
 use crate::crypt::salt::make_salt_with_length_if_missing;
 use chacha20poly1305::aead::{Aead, Nonce};
 use chacha20poly1305::{KeyInit, XChaCha20Poly1305};
+use serde::{Deserialize, Serialize};
+use specta::Type;
 use tracing::{debug, error};
 
 pub static ENCRYPTION_KEY_LENGTH: usize = 32;
 pub static ENCRYPTION_NONCE_LENGTH: usize = 24;
 
+/// The authenticated encryption algorithms that can be used to encrypt data
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Type, Default)]
+pub enum AeadAlgorithm {
+    /// XChaCha20-Poly1305 with a random 24 bytes nonce prepended to the ciphertext
+    #[default]
+    #[serde(rename = "xchacha20-poly1305")]
+    XChaCha20Poly1305,
+}
+
+impl AeadAlgorithm {
+    /// Get the length of the nonce used by the algorithm
+    ///
+    /// # Returns
+    ///
+    /// The length of the nonce in bytes
+    pub fn nonce_length(&self) -> usize {
+        match self {
+            AeadAlgorithm::XChaCha20Poly1305 => ENCRYPTION_NONCE_LENGTH,
+        }
+    }
+
+    /// Encrypts data using the algorithm.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to encrypt.
+    /// * `key` - The key to use for encryption.
+    ///
+    /// # Returns
+    ///
+    /// The encrypted data, prefixed by the nonce.
+    pub fn encrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            AeadAlgorithm::XChaCha20Poly1305 => encrypt(data, key),
+        }
+    }
+
+    /// Decrypts data using the algorithm.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to decrypt, prefixed by the nonce.
+    /// * `key` - The key to use for decryption.
+    ///
+    /// # Returns
+    ///
+    /// The decrypted data.
+    pub fn decrypt(&self, data: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            AeadAlgorithm::XChaCha20Poly1305 => decrypt(data, key),
+        }
+    }
+}
+
 /// Encrypts data using the XChaCha20-Poly1305 cipher.
 ///
 /// # Arguments
@@ -0,0 +1,80 @@
+use crate::crypt::{AeadAlgorithm, KdfAlgorithm, KdfParams};
+use serde::{Deserialize, Serialize};
+
+/// The version of the serialized CryptData envelope written by this build.
+///
+/// - `1`: `{data, mode, salt, related_keys}` (optionally with the bare Argon2id `kdf` parameters),
+///   the cipher, the key derivation and the encoding are implied.
+/// - `2`: self-describing envelope recording the AEAD, the key derivation, the nonce layout and
+///   the encoding.
+pub static CRYPT_DATA_FORMAT_VERSION: u8 = 2;
+
+/// The version assumed when the serialized CryptData does not record one.
+pub static CRYPT_DATA_LEGACY_FORMAT_VERSION: u8 = 1;
+
+/// Where the nonce is stored relative to the ciphertext
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NoncePosition {
+    /// The nonce is prepended to the ciphertext
+    #[default]
+    Prefix,
+}
+
+/// The layout of the nonce used to encrypt the data
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NonceLayout {
+    /// The length of the nonce in bytes
+    pub length: usize,
+    /// Where the nonce is stored relative to the ciphertext
+    pub position: NoncePosition,
+}
+
+impl NonceLayout {
+    /// Get the nonce layout used by an AEAD algorithm
+    ///
+    /// # Arguments
+    ///
+    /// * `algorithm` - The AEAD algorithm
+    ///
+    /// # Returns
+    ///
+    /// The nonce layout
+    pub fn of(algorithm: AeadAlgorithm) -> Self {
+        Self {
+            length: algorithm.nonce_length(),
+            position: NoncePosition::Prefix,
+        }
+    }
+}
+
+/// The textual encoding of the binary fields of the envelope
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum DataEncoding {
+    /// Base64 without padding
+    #[default]
+    #[serde(rename = "base64-unpadded")]
+    Base64Unpadded,
+}
+
+/// The key derivation as found in a serialized envelope.
+///
+/// Version 2 envelopes record the full algorithm descriptor while version 1 envelopes may contain
+/// the bare Argon2id parameters.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum SerializedKdf {
+    /// A version 2 algorithm descriptor
+    Descriptor(KdfAlgorithm),
+    /// The bare Argon2id parameters of a version 1 envelope
+    LegacyParams(KdfParams),
+}
+
+impl From<SerializedKdf> for KdfAlgorithm {
+    fn from(value: SerializedKdf) -> Self {
+        match value {
+            SerializedKdf::Descriptor(algorithm) => algorithm,
+            SerializedKdf::LegacyParams(params) => KdfAlgorithm::Argon2id(params),
+        }
+    }
+}
@@ -1,8 +1,11 @@
 use crate::crypt::salt::make_salt_if_missing;
-use crate::crypt::{decode, encode};
+use crate::crypt::{DerivedKey, KdfParams, decode, encode};
 use sha3::{Digest, Sha3_512};
 use tracing::error;
 
+/// The length of a password hash generated with Argon2id.
+pub static PASSWORD_HASH_LENGTH: usize = 64;
+
 /// Hashes data using the SHA-3 512-bit algorithm.
 ///
 /// # Arguments
@@ -57,6 +60,92 @@ pub fn verify(data: &[u8], hash: &str) -> bool {
     hash == hash2
 }
 
+/// Hashes a password using the Argon2id algorithm.
+///
+/// # Arguments
+///
+/// * `data` - The password to hash.
+/// * `salt` - The salt to use. If `None`, a random salt will be generated.
+/// * `params` - The Argon2id cost parameters.
+///
+/// # Returns
+///
+/// The hashed password, the salt is appended to the hash.
+pub fn hash_password(
+    data: &[u8],
+    salt: Option<&[u8]>,
+    params: &KdfParams,
+) -> Result<String, String> {
+    let derived = DerivedKey::from_password(data, salt, params, PASSWORD_HASH_LENGTH as u8)?;
+
+    let data = [derived.key, derived.salt].concat();
+
+    Ok(encode(&data))
+}
+
+/// Verifies a password hash generated with the Argon2id algorithm.
+///
+/// # Arguments
+///
+/// * `data` - The password to verify.
+/// * `hash` - The hash to verify against.
+/// * `params` - The Argon2id cost parameters used when the hash was generated.
+///
+/// # Returns
+///
+/// Whether the hash is valid.
+pub fn verify_password(data: &[u8], hash: &str, params: &KdfParams) -> bool {
+    let raw_hash = decode(hash);
+    if raw_hash.is_err() {
+        error!(
+            "Failed to verify password hash, hash cannot be decoded: {}",
+            raw_hash.unwrap_err()
+        );
+        return false;
+    }
+    let raw_hash = raw_hash.unwrap();
+
+    if raw_hash.len() <= PASSWORD_HASH_LENGTH {
+        error!("Failed to verify password hash, hash is too short");
+        return false;
+    }
+
+    let salt = &raw_hash[PASSWORD_HASH_LENGTH..];
+    let hash = &raw_hash[..PASSWORD_HASH_LENGTH];
+
+    let derived = DerivedKey::from_password(data, Some(salt), params, PASSWORD_HASH_LENGTH as u8);
+    if derived.is_err() {
+        error!(
+            "Failed to verify password hash, key derivation failed: {}",
+            derived.unwrap_err()
+        );
+        return false;
+    }
+
+    constant_time_eq(hash, &derived.unwrap().key)
+}
+
+/// Compares two byte slices in constant time (with respect to their content).
+///
+/// # Arguments
+///
+/// * `a` - The first slice.
+/// * `b` - The second slice.
+///
+/// # Returns
+///
+/// Whether the slices are equal.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+        == 0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,4 +172,43 @@ mod tests {
         let invalid_hash = "invalidhash";
         assert!(!verify(data.as_slice(), invalid_hash));
     }
+
+    #[test]
+    fn test_hash_password() {
+        let data = b"password";
+        let salt = vec![10, 11, 12, 13, 14, 15, 16, 17, 18, 19];
+        let params = KdfParams::default();
+        let hash = hash_password(data, Some(salt.as_slice()), &params).unwrap();
+        assert_eq!(
+            decode(&hash).unwrap().len(),
+            PASSWORD_HASH_LENGTH + salt.len()
+        );
+    }
+
+    #[test]
+    fn test_verify_password() {
+        let data = b"password";
+        let params = KdfParams::default();
+        let hash = hash_password(data, None, &params).unwrap();
+        assert!(verify_password(data, &hash, &params));
+        assert!(!verify_password(b"wrong password", &hash, &params));
+    }
+
+    #[test]
+    fn test_verify_password_with_different_params() {
+        let data = b"password";
+        let params = KdfParams::default();
+        let hash = hash_password(data, None, &params).unwrap();
+        let other_params = KdfParams {
+            iterations: params.iterations + 1,
+            ..params
+        };
+        assert!(!verify_password(data, &hash, &other_params));
+    }
+
+    #[test]
+    fn test_verify_password_invalid_hash() {
+        let data = b"password";
+        assert!(!verify_password(data, "invalidhash", &KdfParams::default()));
+    }
 }
@@ -1,7 +1,68 @@
 use crate::crypt::salt::make_salt_if_missing;
+use argon2::{Algorithm, Argon2, Params, Version};
+use educe::Educe;
 use hkdf::Hkdf;
 use serde::{Deserialize, Serialize};
 use sha3::Sha3_512;
+use specta::Type;
+
+/// The cost parameters of the Argon2id password key derivation function.
+///
+/// Defaults follow the OWASP recommendation for Argon2id (19 MiB of memory, 2 iterations, 1 degree
+/// of parallelism).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Type, Educe)]
+#[educe(Default)]
+pub struct KdfParams {
+    /// The memory cost in KiB
+    #[educe(Default = 19456)]
+    pub memory_kib: u32,
+    /// The number of iterations (time cost)
+    #[educe(Default = 2)]
+    pub iterations: u32,
+    /// The degree of parallelism
+    #[educe(Default = 1)]
+    pub parallelism: u32,
+}
+
+/// The key derivation functions that can be used to derive a key
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Type, Default)]
+#[serde(tag = "id", content = "params")]
+pub enum KdfAlgorithm {
+    /// A single HKDF-SHA3-512 expand, only suitable for high entropy keys
+    #[default]
+    #[serde(rename = "hkdf-sha3-512")]
+    HkdfSha3_512,
+    /// The memory-hard Argon2id derivation, used for keys derived from a password
+    #[serde(rename = "argon2id")]
+    Argon2id(KdfParams),
+}
+
+impl KdfAlgorithm {
+    /// Derives a key using the algorithm.
+    ///
+    /// # Arguments
+    ///
+    /// * `password` - The password to derive the key from.
+    /// * `salt` - The salt to use for the derivation. If `None`, a random salt will be generated.
+    /// * `key_length` - The length of the key to derive.
+    ///
+    /// # Returns
+    ///
+    /// The derived key.
+    pub fn derive(
+        &self,
+        password: &[u8],
+        salt: Option<&[u8]>,
+        key_length: u8,
+    ) -> Result<DerivedKey, String> {
+        match self {
+            KdfAlgorithm::HkdfSha3_512 => DerivedKey::from_byte_key(password, salt, key_length),
+            KdfAlgorithm::Argon2id(params) => {
+                DerivedKey::from_password(password, salt, params, key_length)
+            }
+        }
+    }
+}
 
 /// A derived key.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -45,6 +106,49 @@ impl DerivedKey {
         })
     }
 
+    /// Derives a key from a low entropy secret (such as a user password) using Argon2id.
+    ///
+    /// # Arguments
+    ///
+    /// * `password` - The password to derive the key from.
+    /// * `salt` - The salt to use for the derivation. If `None`, a random salt will be generated.
+    /// * `params` - The Argon2id cost parameters.
+    /// * `key_length` - The length of the key to derive.
+    ///
+    /// # Returns
+    ///
+    /// The derived key.
+    pub fn from_password(
+        password: &[u8],
+        salt: Option<&[u8]>,
+        params: &KdfParams,
+        key_length: u8,
+    ) -> Result<Self, String> {
+        if password.is_empty() {
+            return Err("Password cannot be empty".to_string());
+        }
+        if key_length == 0 {
+            return Err("Key length must be greater than 0".to_string());
+        }
+
+        let salt = make_salt_if_missing(salt);
+        let argon2_params = Params::new(
+            params.memory_kib,
+            params.iterations,
+            params.parallelism,
+            Some(key_length as usize),
+        )
+        .map_err(|err| err.to_string())?;
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params);
+
+        let mut okm = vec![0u8; key_length as usize];
+        argon2
+            .hash_password_into(password, &salt, &mut okm)
+            .map_err(|err| err.to_string())?;
+
+        Ok(DerivedKey { key: okm, salt })
+    }
+
     /// Derives a key from a password.
     ///
     /// # Arguments
@@ -157,6 +261,87 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_from_password_with_salt() {
+        let password = b"password";
+        let key_length = 32;
+        let salt = [0u8; 32];
+        let params = KdfParams::default();
+        let derived_key_1 =
+            DerivedKey::from_password(password, Some(&salt), &params, key_length).unwrap();
+        let derived_key_2 =
+            DerivedKey::from_password(password, Some(&salt), &params, key_length).unwrap();
+        assert_eq!(derived_key_1.key.len(), key_length as usize);
+        assert_eq!(derived_key_1.salt, salt);
+        assert_eq!(derived_key_1.key, derived_key_2.key);
+    }
+
+    #[test]
+    fn test_from_password_differs_from_hkdf() {
+        let password = b"password";
+        let key_length = 32;
+        let salt = [0u8; 32];
+        let argon2_key =
+            DerivedKey::from_password(password, Some(&salt), &KdfParams::default(), key_length)
+                .unwrap();
+        let hkdf_key = DerivedKey::from_byte_key(password, Some(&salt), key_length).unwrap();
+        assert_ne!(argon2_key.key, hkdf_key.key);
+    }
+
+    #[test]
+    fn test_from_password_with_invalid_params() {
+        let password = b"password";
+        let params = KdfParams {
+            memory_kib: 0,
+            ..Default::default()
+        };
+        let result = DerivedKey::from_password(password, None, &params, 32);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_kdf_algorithm_derive() {
+        let password = b"password";
+        let salt = [0u8; 32];
+        let params = KdfParams::default();
+
+        let hkdf_key = KdfAlgorithm::HkdfSha3_512
+            .derive(password, Some(&salt), 32)
+            .unwrap();
+        let argon2_key = KdfAlgorithm::Argon2id(params)
+            .derive(password, Some(&salt), 32)
+            .unwrap();
+
+        assert_eq!(
+            hkdf_key.key,
+            DerivedKey::from_byte_key(password, Some(&salt), 32)
+                .unwrap()
+                .key
+        );
+        assert_eq!(
+            argon2_key.key,
+            DerivedKey::from_password(password, Some(&salt), &params, 32)
+                .unwrap()
+                .key
+        );
+    }
+
+    #[test]
+    fn test_kdf_algorithm_serialization() {
+        let hkdf = serde_json::to_string(&KdfAlgorithm::HkdfSha3_512).unwrap();
+        assert_eq!(hkdf, r#"{"id":"hkdf-sha3-512"}"#);
+
+        let argon2 = serde_json::to_string(&KdfAlgorithm::Argon2id(KdfParams::default())).unwrap();
+        assert_eq!(
+            argon2,
+            r#"{"id":"argon2id","params":{"memory_kib":19456,"iterations":2,"parallelism":1}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<KdfAlgorithm>(argon2.as_str()).unwrap(),
+            KdfAlgorithm::Argon2id(KdfParams::default())
+        );
+    }
+
     #[test]
     fn test_multiple_derivation_with_different_salt() {
         let password = "password";
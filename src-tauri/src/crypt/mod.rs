@@ -2,6 +2,7 @@ mod crypt_data;
 mod crypt_data_mode;
 mod encoding;
 mod encryption;
+mod envelope;
 mod hash;
 mod hmac;
 mod key_derivation;
@@ -10,8 +11,9 @@ mod salt;
 pub use crypt_data::*;
 pub use crypt_data_mode::*;
 pub use encoding::*;
+pub use envelope::*;
 pub use hash::*;
 pub use hmac::*;
 pub use key_derivation::*;
 
-pub use encryption::ENCRYPTION_KEY_LENGTH;
+pub use encryption::{AeadAlgorithm, ENCRYPTION_KEY_LENGTH};
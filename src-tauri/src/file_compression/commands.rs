@@ -0,0 +1,160 @@
+use crate::authorized_requests::parse_allowed_destination;
+use crate::file_compression::compression::{compress_file, decompress_file, is_compressed_file};
+use crate::file_compression::constants::{COMPRESSED_FILE_EXTENSION, MAX_DECOMPRESSED_FILE_SIZE};
+use crate::state::state::AppState;
+use crate::state::storage_provider::StorageProvider;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use specta::{Type, specta};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State, command};
+use tracing::debug;
+
+/// The file to upload to a storage provider
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Type)]
+pub struct PreparedUpload {
+    /// The path of the file to upload
+    pub path: String,
+    /// Whether the file was compressed, if so it must be removed once uploaded
+    pub compressed: bool,
+}
+
+/// Prepare a file for the upload to a storage provider, compressing it if the provider has the
+/// file compression enabled
+///
+/// # Arguments
+///
+/// * `app` - The Tauri app handle.
+/// * `state` - The application state.
+/// * `provider` - The storage provider the file will be uploaded to.
+/// * `file_path` - The path of the file to upload.
+///
+/// # Returns
+///
+/// The file to upload
+#[command]
+#[specta]
+pub async fn prepare_upload(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    provider: StorageProvider,
+    file_path: String,
+) -> Result<PreparedUpload, String> {
+    let readable_state = state.read().await;
+    let compress = readable_state
+        .settings
+        .general_behaviour
+        .compress_files
+        .get(&provider)
+        .copied()
+        .unwrap_or(false);
+    drop(readable_state);
+
+    if !compress {
+        return Ok(PreparedUpload {
+            path: file_path,
+            compressed: false,
+        });
+    }
+
+    let source = PathBuf::from(&file_path);
+    let file_name = source
+        .file_name()
+        .ok_or("Invalid file path")?
+        .to_string_lossy()
+        .to_string();
+    let destination = app
+        .path()
+        .temp_dir()
+        .map_err(|e| e.to_string())?
+        .join(make_temp_file_name(&file_name));
+
+    debug!("Compressing {} before upload", file_path);
+    let compressed_destination = destination.clone();
+    tokio::task::spawn_blocking(move || compress_file(&source, &compressed_destination))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    Ok(PreparedUpload {
+        path: destination.to_string_lossy().to_string(),
+        compressed: true,
+    })
+}
+
+/// Make a unique name for the compressed copy of a file, concurrent uploads of files with the same
+/// name must not share their temporary file
+///
+/// # Arguments
+///
+/// * `file_name` - The name of the file to compress.
+///
+/// # Returns
+///
+/// The name of the temporary file
+fn make_temp_file_name(file_name: &str) -> String {
+    let mut suffix = [0u8; 8];
+    rand::rng().fill_bytes(&mut suffix);
+    let suffix = suffix
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    format!("{}.{}.{}", file_name, suffix, COMPRESSED_FILE_EXTENSION)
+}
+
+/// Decompress in place a downloaded file if it was compressed before the upload
+///
+/// Only the files the downloads can be saved to are rewritten, and the decompressed file is
+/// limited to `MAX_DECOMPRESSED_FILE_SIZE`.
+///
+/// # Arguments
+///
+/// * `app` - The Tauri app handle.
+/// * `file_path` - The path of the downloaded file.
+///
+/// # Returns
+///
+/// Whether the file was decompressed
+#[command]
+#[specta]
+pub async fn finalize_download(app: AppHandle, file_path: String) -> Result<bool, String> {
+    let source = parse_allowed_destination(&app, file_path.as_str())?;
+
+    tokio::task::spawn_blocking(move || {
+        if !is_compressed_file(&source)? {
+            return Ok(false);
+        }
+
+        debug!("Decompressing downloaded file {}", source.display());
+
+        // decompress next to the file and swap them only once the decompression succeeded
+        let mut destination = source.clone().into_os_string();
+        destination.push(".part");
+        let destination = PathBuf::from(destination);
+
+        if let Err(e) = decompress_file(&source, &destination, MAX_DECOMPRESSED_FILE_SIZE) {
+            let _ = std::fs::remove_file(&destination);
+            return Err(e);
+        }
+
+        std::fs::rename(&destination, &source).map_err(|e| e.to_string())?;
+        Ok(true)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_make_temp_file_name() {
+        let first = make_temp_file_name("report.pdf");
+        let second = make_temp_file_name("report.pdf");
+
+        assert_ne!(first, second);
+        assert!(first.starts_with("report.pdf."));
+        assert!(first.ends_with(&format!(".{}", COMPRESSED_FILE_EXTENSION)));
+    }
+}
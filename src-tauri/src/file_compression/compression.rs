@@ -0,0 +1,220 @@
+use crate::file_compression::constants::COMPRESSED_FILE_MAGIC;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Compress a stream prepending the compressed file marker
+///
+/// # Arguments
+///
+/// * `reader` - The stream to compress
+/// * `writer` - The stream to write the compressed data to
+///
+/// # Returns
+///
+/// Nothing
+pub fn compress_stream<R: Read, W: Write>(reader: R, mut writer: W) -> Result<(), String> {
+    writer
+        .write_all(COMPRESSED_FILE_MAGIC)
+        .map_err(|e| e.to_string())?;
+    zstd::stream::copy_encode(reader, &mut writer, zstd::DEFAULT_COMPRESSION_LEVEL)
+        .map_err(|e| e.to_string())?;
+    writer.flush().map_err(|e| e.to_string())
+}
+
+/// Decompress a stream previously compressed with [`compress_stream`]
+///
+/// # Arguments
+///
+/// * `reader` - The compressed stream, including the compressed file marker
+/// * `writer` - The stream to write the decompressed data to
+/// * `limit` - The largest size of the decompressed data, larger streams are refused
+///
+/// # Returns
+///
+/// Nothing
+pub fn decompress_stream<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    limit: u64,
+) -> Result<(), String> {
+    if !has_compressed_marker(&mut reader)? {
+        return Err("The stream is not compressed".to_owned());
+    }
+
+    // one byte past the limit is enough to tell that the stream is too large
+    let decoder = zstd::stream::read::Decoder::new(reader).map_err(|e| e.to_string())?;
+    let written = std::io::copy(&mut decoder.take(limit.saturating_add(1)), &mut writer)
+        .map_err(|e| e.to_string())?;
+    if written > limit {
+        return Err(format!(
+            "The decompressed data exceeds the limit of {} bytes",
+            limit
+        ));
+    }
+
+    writer.flush().map_err(|e| e.to_string())
+}
+
+/// Check whether a stream starts with the compressed file marker, the marker is consumed
+///
+/// # Arguments
+///
+/// * `reader` - The stream to check
+///
+/// # Returns
+///
+/// Whether the stream starts with the compressed file marker
+fn has_compressed_marker<R: Read>(reader: &mut R) -> Result<bool, String> {
+    let mut marker = Vec::with_capacity(COMPRESSED_FILE_MAGIC.len());
+    reader
+        .take(COMPRESSED_FILE_MAGIC.len() as u64)
+        .read_to_end(&mut marker)
+        .map_err(|e| e.to_string())?;
+
+    Ok(marker == COMPRESSED_FILE_MAGIC)
+}
+
+/// Check whether a file was compressed with [`compress_file`]
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to check
+///
+/// # Returns
+///
+/// Whether the file is compressed
+pub fn is_compressed_file(path: &Path) -> Result<bool, String> {
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    has_compressed_marker(&mut file)
+}
+
+/// Compress a file
+///
+/// # Arguments
+///
+/// * `source` - The path of the file to compress
+/// * `destination` - The path of the compressed file
+///
+/// # Returns
+///
+/// Nothing
+pub fn compress_file(source: &Path, destination: &Path) -> Result<(), String> {
+    let reader = BufReader::new(File::open(source).map_err(|e| e.to_string())?);
+    let writer = BufWriter::new(File::create(destination).map_err(|e| e.to_string())?);
+
+    compress_stream(reader, writer)
+}
+
+/// Decompress a file
+///
+/// # Arguments
+///
+/// * `source` - The path of the compressed file
+/// * `destination` - The path of the decompressed file
+/// * `limit` - The largest size of the decompressed file, larger files are refused
+///
+/// # Returns
+///
+/// Nothing
+pub fn decompress_file(source: &Path, destination: &Path, limit: u64) -> Result<(), String> {
+    let reader = BufReader::new(File::open(source).map_err(|e| e.to_string())?);
+    let writer = BufWriter::new(File::create(destination).map_err(|e| e.to_string())?);
+
+    decompress_stream(reader, writer, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+    use std::io::Cursor;
+
+    fn make_data() -> Vec<u8> {
+        // mix random and repeated data to exercise both compressible and incompressible blocks
+        let mut data = vec![0u8; 256 * 1024];
+        rand::rng().fill_bytes(&mut data[..128 * 1024]);
+        data.extend(b"storage orchestra ".repeat(16 * 1024));
+        data
+    }
+
+    #[test]
+    fn test_stream_round_trip() {
+        let data = make_data();
+
+        let mut compressed = Vec::new();
+        compress_stream(Cursor::new(&data), &mut compressed).unwrap();
+        assert!(compressed.starts_with(COMPRESSED_FILE_MAGIC));
+        assert!(compressed.len() < data.len());
+
+        let mut decompressed = Vec::new();
+        decompress_stream(Cursor::new(&compressed), &mut decompressed, u64::MAX).unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn test_stream_round_trip_empty() {
+        let mut compressed = Vec::new();
+        compress_stream(Cursor::new(Vec::new()), &mut compressed).unwrap();
+
+        let mut decompressed = Vec::new();
+        decompress_stream(Cursor::new(&compressed), &mut decompressed, u64::MAX).unwrap();
+        assert!(decompressed.is_empty());
+    }
+
+    #[test]
+    fn test_decompress_unmarked_stream() {
+        let mut decompressed = Vec::new();
+        assert!(
+            decompress_stream(Cursor::new(b"plain content"), &mut decompressed, u64::MAX).is_err()
+        );
+        assert!(decompress_stream(Cursor::new(b"SOZ"), &mut decompressed, u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_decompress_over_limit() {
+        // highly compressible data expands far beyond its compressed size
+        let data = vec![0u8; 1024 * 1024];
+        let mut compressed = Vec::new();
+        compress_stream(Cursor::new(&data), &mut compressed).unwrap();
+
+        let mut decompressed = Vec::new();
+        let limit = data.len() as u64 - 1;
+        assert!(decompress_stream(Cursor::new(&compressed), &mut decompressed, limit).is_err());
+        assert!(decompressed.len() as u64 <= limit + 1);
+
+        let mut decompressed = Vec::new();
+        decompress_stream(
+            Cursor::new(&compressed),
+            &mut decompressed,
+            data.len() as u64,
+        )
+        .unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let directory = std::env::temp_dir().join(format!(
+            "storage-orchestra-compression-{}",
+            rand::rng().next_u64()
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let source = directory.join("source.bin");
+        let compressed = directory.join("source.bin.zst");
+        let decompressed = directory.join("decompressed.bin");
+
+        let data = make_data();
+        std::fs::write(&source, &data).unwrap();
+        assert!(!is_compressed_file(&source).unwrap());
+
+        compress_file(&source, &compressed).unwrap();
+        assert!(is_compressed_file(&compressed).unwrap());
+
+        decompress_file(&compressed, &decompressed, u64::MAX).unwrap();
+        assert_eq!(std::fs::read(&decompressed).unwrap(), data);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,10 @@
+/// The marker prepended to the files compressed before being uploaded, this allows downloads to
+/// be recognized and decompressed automatically regardless of the current settings.
+pub const COMPRESSED_FILE_MAGIC: &[u8; 8] = b"SOZSTD01";
+
+/// The extension appended to the temporary compressed copy of a file
+pub const COMPRESSED_FILE_EXTENSION: &str = "zst";
+
+/// The largest size a downloaded file is decompressed to, a crafted file expanding to more is
+/// refused instead of filling the disk
+pub const MAX_DECOMPRESSED_FILE_SIZE: u64 = 16 * 1024 * 1024 * 1024;
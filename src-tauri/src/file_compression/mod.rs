@@ -0,0 +1,5 @@
+mod commands;
+mod compression;
+mod constants;
+
+pub use commands::*;
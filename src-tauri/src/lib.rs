@@ -1,20 +1,24 @@
 #![feature(let_chains)]
 
+mod authorized_requests;
 mod crypt;
+mod file_compression;
 mod native_apps;
+mod secret_manager;
 mod state;
 mod utility;
 
-use crate::state::state::{AppStateDeep, STATE_FILE};
+use crate::state::state::{AppState, AppStateDeep, STATE_FILE};
+use crate::state::state_generation::STATE_ROLLBACK_ERROR;
 use extensions_loader::load_extensions;
 use specta::specta;
 use specta_typescript::Typescript;
 use std::{fs, io};
 use tauri::async_runtime::RwLock;
-use tauri::{Emitter, Manager as _, Window, command};
+use tauri::{Emitter, Manager as _, RunEvent, Window, command};
 use tauri_plugin_oauth::start;
 use tauri_specta::{Builder, collect_commands, collect_events};
-use tracing::Level;
+use tracing::{Level, error};
 use tracing_subscriber::Registry;
 use tracing_subscriber::fmt::layer;
 use tracing_subscriber::fmt::writer::MakeWriterExt;
@@ -73,23 +77,63 @@ pub fn run() -> Result<(), String> {
         .commands(collect_commands![
             start_server,
             state::init_state,
+            state::recover_state,
             state::get_from_state,
             state::remove_from_state,
             state::insert_in_state,
+            state::patch_state,
+            state::reload_state_file,
+            state::overwrite_state_file,
             state::is_authenticated,
-            state::get_password,
+            state::report_activity,
+            state::lock_vault,
+            state::unlock_vault,
             state::load_settings,
             state::update_settings,
             state::check_password,
             state::update_password,
-            crypt::crypt_data_get_raw_data_as_string,
-            crypt::crypt_data_get_raw_data,
+            state::enroll_totp,
+            state::confirm_totp,
+            state::verify_totp,
+            state::regenerate_recovery_codes,
+            state::disable_totp,
+            state::generate_recovery_key,
+            state::revoke_recovery_key,
+            state::has_recovery_key,
+            state::get_unlock_attempts,
+            state::set_unlock_lockout_threshold,
+            state::list_vaults,
+            state::create_vault,
+            state::rename_vault,
+            state::delete_vault,
+            state::select_vault,
+            state::export_vault,
+            state::import_vault,
+            state::list_accounts,
+            state::get_account,
+            state::add_account,
+            state::update_account,
+            state::remove_account,
+            state::record_account_quota,
             crypt::make_crypt_data_from_qualified_string,
             native_apps::watch_native_open,
             native_apps::cancel_watch_native_open,
+            file_compression::prepare_upload,
+            file_compression::finalize_download,
+            authorized_requests::authorized_fetch,
+            authorized_requests::authorized_download,
+            authorized_requests::refresh_provider,
         ])
-        .events(collect_events![])
-        .constant("STATE_FILE", STATE_FILE);
+        .events(collect_events![
+            state::SettingsChanged,
+            state::ProvidersChanged,
+            state::CredentialsChanged,
+            state::VaultLocked,
+            state::StateSaveFailed,
+            state::StateFileChanged,
+        ])
+        .constant("STATE_FILE", STATE_FILE)
+        .constant("STATE_ROLLBACK_ERROR", STATE_ROLLBACK_ERROR);
 
     // Only export on non-release builds
     #[cfg(debug_assertions)]
@@ -116,15 +160,31 @@ pub fn run() -> Result<(), String> {
         .setup(move |app| {
             builder.mount_events(app);
 
-            app.manage(RwLock::new(AppStateDeep::default()));
-
+            let app_state = AppStateDeep::default();
             let handle = app.handle();
+            state::forward_save_failures(
+                handle.clone(),
+                app_state.debounced_saver.subscribe_failures(),
+            );
+            app.manage(RwLock::new(app_state));
+            state::watch_idle_lock(handle.clone());
+            state::watch_state_file(handle.clone());
+
             load_extensions(handle.clone()).unwrap();
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                // write the pending state changes before the process exits
+                let state = app.state::<AppState>();
+                if let Err(e) = tauri::async_runtime::block_on(state::flush_state(state)) {
+                    error!("Cannot save the state before exiting: {}", e);
+                }
+            }
+        });
 
     Ok(())
 }
@@ -0,0 +1,83 @@
+use region::LockGuard;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use tracing::warn;
+use zeroize::Zeroize;
+
+/// A secret kept in a buffer that is locked in memory and wiped when dropped.
+///
+/// Locking the buffer prevents it from being swapped to disk, it is best effort as the operating
+/// system may refuse to lock memory (e.g. when the locked memory limit is reached).
+pub struct LockedSecret {
+    /// The secret, never reallocated so that no copy is left behind
+    data: Box<[u8]>,
+    /// The guard keeping the buffer locked in memory, released after the buffer has been wiped
+    _lock: Option<LockGuard>,
+}
+
+impl LockedSecret {
+    /// Move a secret into a locked buffer
+    ///
+    /// # Arguments
+    ///
+    /// * `secret` - The secret, wiped once copied into the locked buffer
+    ///
+    /// # Returns
+    ///
+    /// The locked secret
+    pub fn new(mut secret: Vec<u8>) -> Self {
+        let data = secret.as_slice().to_vec().into_boxed_slice();
+        secret.zeroize();
+
+        let lock = if data.is_empty() {
+            None
+        } else {
+            region::lock(data.as_ptr(), data.len())
+                .inspect_err(|e| warn!("Cannot lock the secret in memory: {}", e))
+                .ok()
+        };
+
+        Self { data, _lock: lock }
+    }
+
+    /// Get the secret
+    ///
+    /// # Returns
+    ///
+    /// The secret
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Drop for LockedSecret {
+    fn drop(&mut self) {
+        self.data.zeroize();
+    }
+}
+
+impl Debug for LockedSecret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockedSecret")
+            .field("length", &self.data.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locked_secret() {
+        let secret = LockedSecret::new(b"supersecretkey".to_vec());
+        assert_eq!(secret.as_slice(), b"supersecretkey");
+        assert!(!format!("{:?}", secret).contains("supersecretkey"));
+    }
+
+    #[test]
+    fn test_empty_locked_secret() {
+        let secret = LockedSecret::new(Vec::new());
+        assert!(secret.as_slice().is_empty());
+    }
+}
@@ -0,0 +1,4 @@
+mod locked_secret;
+mod secret_store;
+
+pub use secret_store::*;
@@ -0,0 +1,208 @@
+use crate::secret_manager::locked_secret::LockedSecret;
+use once_cell::sync::Lazy;
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+/// The secrets of the unlocked vault, empty while the vault is locked
+pub static SECRET_STORE: Lazy<RwLock<SecretStore>> =
+    Lazy::new(|| RwLock::new(SecretStore::default()));
+
+/// How long after the password was last confirmed the sensitive commands can be run
+pub static REAUTHENTICATION_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// The key material needed while the vault is unlocked
+#[derive(Debug, Default)]
+pub struct SecretStore {
+    /// The data key used to encrypt all the secrets of the application secure storage
+    data_key: Option<LockedSecret>,
+    /// When the user was last active while the vault is unlocked
+    last_activity: Option<Instant>,
+    /// When the password was last confirmed while the vault is unlocked
+    authenticated_at: Option<Instant>,
+}
+
+impl SecretStore {
+    /// Store the data key of the unlocked vault, wiping the previous one
+    ///
+    /// # Arguments
+    ///
+    /// * `data_key` - The data key
+    pub fn unlock(&mut self, data_key: Vec<u8>) {
+        self.data_key = Some(LockedSecret::new(data_key));
+
+        // unlocking requires the password (or the recovery key)
+        self.last_activity = Some(Instant::now());
+        self.authenticated_at = Some(Instant::now());
+    }
+
+    /// Wipe the secrets of the vault
+    pub fn lock(&mut self) {
+        self.data_key = None;
+        self.last_activity = None;
+        self.authenticated_at = None;
+    }
+
+    /// Record that the user is active, ignored while the vault is locked
+    pub fn record_activity(&mut self) {
+        if self.is_unlocked() {
+            self.last_activity = Some(Instant::now());
+        }
+    }
+
+    /// Record that the user confirmed the password, ignored while the vault is locked
+    pub fn record_authentication(&mut self) {
+        if self.is_unlocked() {
+            self.last_activity = Some(Instant::now());
+            self.authenticated_at = Some(Instant::now());
+        }
+    }
+
+    /// Get for how long the user has been inactive
+    ///
+    /// # Returns
+    ///
+    /// The time since the last activity, `None` while the vault is locked
+    pub fn idle_duration(&self) -> Option<Duration> {
+        self.last_activity
+            .map(|last_activity| last_activity.elapsed())
+    }
+
+    /// Check whether the password was confirmed recently enough to run a sensitive command
+    ///
+    /// # Returns
+    ///
+    /// Whether the password was confirmed within the re-authentication window
+    pub fn is_recently_authenticated(&self) -> bool {
+        self.authenticated_at
+            .is_some_and(|authenticated_at| authenticated_at.elapsed() < REAUTHENTICATION_WINDOW)
+    }
+
+    /// Check whether the vault is unlocked
+    ///
+    /// # Returns
+    ///
+    /// Whether the vault is unlocked
+    pub fn is_unlocked(&self) -> bool {
+        self.data_key.is_some()
+    }
+}
+
+/// Store the data key of the unlocked vault
+///
+/// # Arguments
+///
+/// * `data_key` - The data key
+pub async fn unlock_secret_store(data_key: Vec<u8>) {
+    SECRET_STORE.write().await.unlock(data_key);
+}
+
+/// Wipe the secrets of the vault
+pub async fn lock_secret_store() {
+    SECRET_STORE.write().await.lock();
+}
+
+/// Check whether the vault is unlocked
+///
+/// # Returns
+///
+/// Whether the vault is unlocked
+pub async fn is_vault_unlocked() -> bool {
+    SECRET_STORE.read().await.is_unlocked()
+}
+
+/// Borrow the data key of the unlocked vault
+///
+/// The vault cannot be locked while the data key is borrowed, the guard should be dropped as
+/// soon as possible.
+///
+/// # Returns
+///
+/// The data key, an error if the vault is locked
+pub async fn read_data_key() -> Result<RwLockReadGuard<'static, LockedSecret>, String> {
+    RwLockReadGuard::try_map(SECRET_STORE.read().await, |store| store.data_key.as_ref())
+        .map_err(|_| "The vault is locked".to_owned())
+}
+
+/// Record that the user is active, this postpones the idle lock
+pub async fn record_activity() {
+    SECRET_STORE.write().await.record_activity();
+}
+
+/// Record that the user confirmed the password, this opens the re-authentication window
+pub async fn record_authentication() {
+    SECRET_STORE.write().await.record_authentication();
+}
+
+/// Get for how long the user has been inactive
+///
+/// # Returns
+///
+/// The time since the last activity, `None` while the vault is locked
+pub async fn idle_duration() -> Option<Duration> {
+    SECRET_STORE.read().await.idle_duration()
+}
+
+/// Check whether the password was confirmed recently enough to run a sensitive command
+///
+/// # Returns
+///
+/// Whether the password was confirmed within the re-authentication window
+pub async fn is_recently_authenticated() -> bool {
+    SECRET_STORE.read().await.is_recently_authenticated()
+}
+
+/// Check that the password was confirmed recently enough to run a sensitive command
+///
+/// # Returns
+///
+/// Nothing, an error asking to confirm the password otherwise
+pub async fn require_recent_authentication() -> Result<(), String> {
+    if is_recently_authenticated().await {
+        Ok(())
+    } else {
+        Err("Confirm your password to continue".to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_and_unlock() {
+        let mut store = SecretStore::default();
+        assert!(!store.is_unlocked());
+
+        store.unlock(b"data key".to_vec());
+        assert!(store.is_unlocked());
+        assert_eq!(store.data_key.as_ref().unwrap().as_slice(), b"data key");
+
+        store.unlock(b"new data key".to_vec());
+        assert_eq!(store.data_key.as_ref().unwrap().as_slice(), b"new data key");
+
+        store.lock();
+        assert!(!store.is_unlocked());
+    }
+
+    #[test]
+    fn test_session() {
+        let mut store = SecretStore::default();
+        store.record_activity();
+        store.record_authentication();
+        assert!(store.idle_duration().is_none());
+        assert!(!store.is_recently_authenticated());
+
+        store.unlock(b"data key".to_vec());
+        assert!(store.idle_duration().is_some());
+        assert!(store.is_recently_authenticated());
+
+        store.authenticated_at = Some(Instant::now() - REAUTHENTICATION_WINDOW);
+        assert!(!store.is_recently_authenticated());
+        store.record_authentication();
+        assert!(store.is_recently_authenticated());
+
+        store.lock();
+        assert!(store.idle_duration().is_none());
+        assert!(!store.is_recently_authenticated());
+    }
+}
@@ -0,0 +1,198 @@
+use crate::secret_manager::require_recent_authentication;
+use crate::state::account_registry::{
+    find_account, register_account, replace_account, reset_orphaned_default_page,
+    unregister_account,
+};
+use crate::state::provider_data::{AccountView, ProviderData, QuotaSnapshot};
+use crate::state::state::{AppState, AppStateDeepKeys};
+use crate::state::state_commands::save;
+use crate::state::state_events::emit_state_changed;
+use specta::specta;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, State, command};
+use tracing::debug;
+
+/// List the storage provider accounts, their credentials are not exposed
+///
+/// # Arguments
+///
+/// * `state` - The application state
+///
+/// # Returns
+///
+/// The accounts
+#[command]
+#[specta]
+pub async fn list_accounts(state: State<'_, AppState>) -> Result<Vec<AccountView>, String> {
+    Ok(state
+        .read()
+        .await
+        .providers
+        .iter()
+        .map(AccountView::from_account)
+        .collect())
+}
+
+/// Get a storage provider account, its credential is not exposed
+///
+/// # Arguments
+///
+/// * `state` - The application state
+/// * `id` - The id of the account
+///
+/// # Returns
+///
+/// The account
+#[command]
+#[specta]
+pub async fn get_account(state: State<'_, AppState>, id: String) -> Result<AccountView, String> {
+    find_account(&state.read().await.providers, id.as_str()).map(AccountView::from_account)
+}
+
+/// Add a storage provider account, an account can only be added once per provider
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `state` - The application state
+/// * `account` - The account to add, its id is assigned by the registry
+///
+/// # Returns
+///
+/// The added account, its credential is not exposed
+#[command]
+#[specta]
+pub async fn add_account(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    account: ProviderData,
+) -> Result<AccountView, String> {
+    let mut writable_state = state.write().await;
+    let account = register_account(&mut writable_state.providers, account)?;
+    drop(writable_state);
+
+    debug!("Account {} added", account.id);
+    save(app.clone(), state.clone()).await?;
+    emit_state_changed(&app, &state, AppStateDeepKeys::Providers).await;
+
+    Ok(AccountView::from_account(&account))
+}
+
+/// Update a storage provider account, used when the account is signed in again
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `state` - The application state
+/// * `id` - The id of the account
+/// * `account` - The new data of the account
+///
+/// # Returns
+///
+/// The updated account, its credential is not exposed
+#[command]
+#[specta]
+pub async fn update_account(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    account: ProviderData,
+) -> Result<AccountView, String> {
+    let mut writable_state = state.write().await;
+    let account = replace_account(&mut writable_state.providers, id.as_str(), account)?;
+
+    // the default page follows the account it shows
+    let default_page_reset = reset_orphaned_default_page(&mut writable_state);
+    drop(writable_state);
+
+    save(app.clone(), state.clone()).await?;
+    emit_state_changed(&app, &state, AppStateDeepKeys::Providers).await;
+    if default_page_reset {
+        emit_state_changed(&app, &state, AppStateDeepKeys::Settings).await;
+    }
+
+    Ok(AccountView::from_account(&account))
+}
+
+/// Remove a storage provider account, the default page is reset if it shows the account
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `state` - The application state
+/// * `id` - The id of the account
+///
+/// # Returns
+///
+/// Nothing
+#[command]
+#[specta]
+pub async fn remove_account(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    // removing accounts is a sensitive operation, checked before locking the state as the secret
+    // store is always locked before the state
+    require_recent_authentication().await?;
+
+    let mut writable_state = state.write().await;
+    let account = unregister_account(&mut writable_state.providers, id.as_str())?;
+    let default_page_reset = reset_orphaned_default_page(&mut writable_state);
+    drop(writable_state);
+
+    debug!("Account {} removed", account.id);
+    save(app.clone(), state.clone()).await?;
+    emit_state_changed(&app, &state, AppStateDeepKeys::Providers).await;
+    if default_page_reset {
+        emit_state_changed(&app, &state, AppStateDeepKeys::Settings).await;
+    }
+
+    Ok(())
+}
+
+/// Record the storage quota of a storage provider account
+///
+/// The snapshot is informational, no change event is emitted so that the views showing the quota
+/// are not refreshed by their own snapshot.
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `state` - The application state
+/// * `id` - The id of the account
+/// * `used` - The used storage in bytes
+/// * `total` - The total storage in bytes, `None` if the storage is unlimited
+///
+/// # Returns
+///
+/// Nothing
+#[command]
+#[specta]
+pub async fn record_account_quota(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    used: u64,
+    total: Option<u64>,
+) -> Result<(), String> {
+    let checked_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    let mut writable_state = state.write().await;
+    let account = writable_state
+        .providers
+        .iter_mut()
+        .find(|account| account.id == id)
+        .ok_or("Account not found")?;
+    account.quota = Some(QuotaSnapshot {
+        used,
+        total,
+        checked_at,
+    });
+    drop(writable_state);
+
+    save(app, state).await
+}
@@ -0,0 +1,369 @@
+use crate::state::provider_data::ProviderData;
+use crate::state::settings::general_behaviour::DefaultPageGroups;
+use crate::state::state::AppStateDeep;
+use rand::RngCore;
+use std::collections::HashSet;
+
+/// Generate a new account id
+///
+/// # Returns
+///
+/// The account id
+pub fn new_account_id() -> String {
+    let mut id = [0u8; 8];
+    rand::rng().fill_bytes(&mut id);
+
+    id.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Find an account by id
+///
+/// # Arguments
+///
+/// * `accounts` - The registered accounts
+/// * `id` - The id of the account
+///
+/// # Returns
+///
+/// The account, an error if it does not exist
+pub fn find_account<'a>(
+    accounts: &'a [ProviderData],
+    id: &str,
+) -> Result<&'a ProviderData, String> {
+    accounts
+        .iter()
+        .find(|account| account.id == id)
+        .ok_or("Account not found".to_owned())
+}
+
+/// Register a new account, an account is unique for its provider and owner
+///
+/// # Arguments
+///
+/// * `accounts` - The registered accounts
+/// * `account` - The account to register, its id is replaced by a new one
+///
+/// # Returns
+///
+/// The registered account
+pub fn register_account(
+    accounts: &mut Vec<ProviderData>,
+    mut account: ProviderData,
+) -> Result<ProviderData, String> {
+    ensure_unique_account(accounts, &account, None)?;
+
+    account.id = new_account_id();
+    accounts.push(account.clone());
+
+    Ok(account)
+}
+
+/// Replace the data of a registered account, the account keeps its id
+///
+/// # Arguments
+///
+/// * `accounts` - The registered accounts
+/// * `id` - The id of the account
+/// * `account` - The new data of the account
+///
+/// # Returns
+///
+/// The updated account
+pub fn replace_account(
+    accounts: &mut [ProviderData],
+    id: &str,
+    mut account: ProviderData,
+) -> Result<ProviderData, String> {
+    ensure_unique_account(accounts, &account, Some(id))?;
+
+    let existing = accounts
+        .iter_mut()
+        .find(|existing| existing.id == id)
+        .ok_or("Account not found")?;
+    account.id = id.to_owned();
+    *existing = account.clone();
+
+    Ok(account)
+}
+
+/// Unregister an account
+///
+/// # Arguments
+///
+/// * `accounts` - The registered accounts
+/// * `id` - The id of the account
+///
+/// # Returns
+///
+/// The removed account
+pub fn unregister_account(
+    accounts: &mut Vec<ProviderData>,
+    id: &str,
+) -> Result<ProviderData, String> {
+    let index = accounts
+        .iter()
+        .position(|account| account.id == id)
+        .ok_or("Account not found")?;
+
+    Ok(accounts.remove(index))
+}
+
+/// Validate a list of accounts replacing the registered ones
+///
+/// # Arguments
+///
+/// * `accounts` - The accounts
+///
+/// # Returns
+///
+/// Nothing if every account has a unique id and a unique provider and owner
+pub fn validate_accounts(accounts: &[ProviderData]) -> Result<(), String> {
+    let mut ids = HashSet::new();
+
+    for (index, account) in accounts.iter().enumerate() {
+        if account.id.is_empty() {
+            return Err(format!("The account {} has no id", account.owner));
+        }
+        if !ids.insert(account.id.as_str()) {
+            return Err(format!("The account id {} is used twice", account.id));
+        }
+
+        ensure_unique_account(&accounts[..index], account, None)?;
+    }
+
+    Ok(())
+}
+
+/// Ensure that a list of accounts replacing the registered ones keeps their ids, accounts can be
+/// updated or removed but only the registry assigns ids
+///
+/// # Arguments
+///
+/// * `accounts` - The registered accounts
+/// * `updated` - The accounts replacing them
+///
+/// # Returns
+///
+/// Nothing, an error if an account is added or an id is reassigned to another account
+pub fn ensure_stable_ids(
+    accounts: &[ProviderData],
+    updated: &[ProviderData],
+) -> Result<(), String> {
+    for account in updated {
+        let existing = find_account(accounts, account.id.as_str()).map_err(|_| {
+            format!(
+                "The account {} is not registered, use 'add_account' instead",
+                account.owner
+            )
+        })?;
+
+        if existing.provider != account.provider || existing.owner != account.owner {
+            return Err(format!(
+                "The account id {} cannot be reassigned",
+                account.id
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Reset the default page if it shows an account that is no longer registered
+///
+/// # Arguments
+///
+/// * `state` - The state
+///
+/// # Returns
+///
+/// Whether the default page was reset
+pub fn reset_orphaned_default_page(state: &mut AppStateDeep) -> bool {
+    let DefaultPageGroups::Providers(page) = &state.settings.general_behaviour.default_page else {
+        return false;
+    };
+
+    if find_account(&state.providers, page.account_id.as_str()).is_ok() {
+        return false;
+    }
+
+    state.settings.general_behaviour.default_page = DefaultPageGroups::default();
+    true
+}
+
+/// Ensure that no other account has the same provider and owner
+///
+/// # Arguments
+///
+/// * `accounts` - The registered accounts
+/// * `account` - The account to check
+/// * `id` - The id of the account being updated, if any
+///
+/// # Returns
+///
+/// Nothing, an error if the account is already registered
+fn ensure_unique_account(
+    accounts: &[ProviderData],
+    account: &ProviderData,
+    id: Option<&str>,
+) -> Result<(), String> {
+    if accounts.iter().any(|existing| {
+        Some(existing.id.as_str()) != id
+            && existing.provider == account.provider
+            && existing.owner == account.owner
+    }) {
+        return Err(format!(
+            "The account {} is already registered",
+            account.owner
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::settings::general_behaviour::ProviderPage;
+    use crate::state::storage_provider::StorageProvider;
+
+    fn make_account(owner: &str) -> ProviderData {
+        ProviderData {
+            owner: owner.to_owned(),
+            provider: StorageProvider::Google,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_account_registry() {
+        let mut accounts = Vec::new();
+
+        let first = register_account(&mut accounts, make_account("first")).unwrap();
+        let second = register_account(&mut accounts, make_account("second")).unwrap();
+        assert_ne!(first.id, second.id);
+        assert!(register_account(&mut accounts, make_account("first")).is_err());
+        assert!(validate_accounts(&accounts).is_ok());
+
+        // the same owner is another account on another provider
+        let dropbox = ProviderData {
+            provider: StorageProvider::Dropbox,
+            ..make_account("first")
+        };
+        assert!(register_account(&mut accounts, dropbox).is_ok());
+
+        let updated = ProviderData {
+            display_name: Some("First".to_owned()),
+            ..make_account("first")
+        };
+        let updated = replace_account(&mut accounts, &first.id, updated).unwrap();
+        assert_eq!(updated.id, first.id);
+        assert_eq!(
+            find_account(&accounts, &first.id).unwrap().display_name,
+            Some("First".to_owned())
+        );
+        assert!(replace_account(&mut accounts, &first.id, make_account("second")).is_err());
+        assert!(replace_account(&mut accounts, "missing", make_account("third")).is_err());
+
+        assert_eq!(
+            unregister_account(&mut accounts, &second.id).unwrap().owner,
+            "second"
+        );
+        assert!(find_account(&accounts, &second.id).is_err());
+        assert!(unregister_account(&mut accounts, &second.id).is_err());
+    }
+
+    #[test]
+    fn test_validate_accounts() {
+        let first = ProviderData {
+            id: "1".to_owned(),
+            ..make_account("first")
+        };
+        let second = ProviderData {
+            id: "2".to_owned(),
+            ..make_account("second")
+        };
+
+        assert!(validate_accounts(&[first.clone(), second.clone()]).is_ok());
+        assert!(validate_accounts(&[first.clone(), make_account("third")]).is_err());
+        assert!(
+            validate_accounts(&[
+                first.clone(),
+                ProviderData {
+                    id: "1".to_owned(),
+                    ..second.clone()
+                }
+            ])
+            .is_err()
+        );
+        assert!(
+            validate_accounts(&[
+                first.clone(),
+                ProviderData {
+                    id: "2".to_owned(),
+                    ..first.clone()
+                }
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_ensure_stable_ids() {
+        let mut accounts = Vec::new();
+        let first = register_account(&mut accounts, make_account("first")).unwrap();
+        let second = register_account(&mut accounts, make_account("second")).unwrap();
+
+        let updated = ProviderData {
+            display_name: Some("First".to_owned()),
+            ..first.clone()
+        };
+        assert!(ensure_stable_ids(&accounts, &[updated]).is_ok());
+        assert!(ensure_stable_ids(&accounts, &[]).is_ok());
+
+        // new accounts are only added by the registry
+        let added = ProviderData {
+            id: "added".to_owned(),
+            ..make_account("third")
+        };
+        assert!(ensure_stable_ids(&accounts, &[first.clone(), added]).is_err());
+
+        // ids cannot be swapped between accounts
+        let swapped = [
+            ProviderData {
+                id: second.id.clone(),
+                ..first.clone()
+            },
+            ProviderData {
+                id: first.id.clone(),
+                ..second.clone()
+            },
+        ];
+        assert!(ensure_stable_ids(&accounts, &swapped).is_err());
+    }
+
+    #[test]
+    fn test_reset_orphaned_default_page() {
+        let mut state = AppStateDeep {
+            providers: vec![ProviderData {
+                id: "1".to_owned(),
+                ..make_account("first")
+            }],
+            ..Default::default()
+        };
+        state.settings.general_behaviour.default_page =
+            DefaultPageGroups::Providers(ProviderPage {
+                account_id: "1".to_owned(),
+            });
+
+        assert!(!reset_orphaned_default_page(&mut state));
+
+        // the page follows the id, not the owner
+        state.providers = vec![make_account("first")];
+        assert!(reset_orphaned_default_page(&mut state));
+        assert_eq!(
+            state.settings.general_behaviour.default_page,
+            DefaultPageGroups::default()
+        );
+        assert!(!reset_orphaned_default_page(&mut state));
+    }
+}
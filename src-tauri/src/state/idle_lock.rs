@@ -0,0 +1,46 @@
+use crate::secret_manager::idle_duration;
+use crate::state::state::AppState;
+use crate::state::state_commands::clear_state;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::debug;
+
+/// How often the idle time of the unlocked vault is checked
+static IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Lock the vault once the user has been inactive for longer than the idle lock delay of the
+/// settings
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+///
+/// # Returns
+///
+/// Nothing
+pub fn watch_idle_lock(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            // the vault is locked
+            let Some(idle) = idle_duration().await else {
+                continue;
+            };
+
+            let state = app.state::<AppState>();
+            let idle_lock_minutes = state.read().await.settings.security.idle_lock_minutes;
+            if idle_lock_minutes == 0 || idle < Duration::from_secs(idle_lock_minutes as u64 * 60) {
+                continue;
+            }
+
+            debug!(
+                "Locking the vault after {} minutes of inactivity",
+                idle_lock_minutes
+            );
+            clear_state(&app, state).await;
+        }
+    });
+}
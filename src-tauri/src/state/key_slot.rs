@@ -0,0 +1,161 @@
+use crate::crypt::{CryptData, CryptDataMode, ENCRYPTION_KEY_LENGTH};
+use as_inner_serializable::AsInnerSerializable;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// The unlock methods that can wrap the data key
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, Type, Eq, PartialEq)]
+pub enum KeySlotKind {
+    /// The data key is wrapped by a key derived from the user password
+    #[serde(rename = "password")]
+    #[default]
+    Password,
+    /// The data key is wrapped by a key derived from the offline recovery key
+    #[serde(rename = "recovery")]
+    Recovery,
+}
+
+/// A copy of the data key wrapped by one of the unlock methods.
+///
+/// All the secrets in the state are encrypted using the random data key, the data key is stored
+/// encrypted once per unlock method so that changing the password only requires re-wrapping it.
+#[derive(Debug, Clone, Default, Type, AsInnerSerializable)]
+pub struct KeySlot {
+    /// The unlock method protecting the data key
+    pub kind: KeySlotKind,
+    /// The data key encrypted with the key derived from the unlock secret
+    pub wrapped_key: Arc<RwLock<CryptData>>,
+}
+
+impl KeySlot {
+    /// Wrap the data key using a secret
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The unlock method
+    /// * `data_key` - The data key to wrap
+    /// * `secret` - The secret used to derive the key encryption key
+    ///
+    /// # Returns
+    ///
+    /// The key slot
+    pub fn wrap(kind: KeySlotKind, data_key: &[u8], secret: &[u8]) -> Self {
+        Self {
+            kind,
+            wrapped_key: Arc::new(RwLock::new(CryptData::new(
+                data_key.to_vec(),
+                CryptDataMode::to_u8(vec![CryptDataMode::Encrypt, CryptDataMode::Encode]),
+                Some(secret),
+                None,
+            ))),
+        }
+    }
+
+    /// Unwrap the data key using a secret
+    ///
+    /// # Arguments
+    ///
+    /// * `secret` - The secret used to derive the key encryption key
+    ///
+    /// # Returns
+    ///
+    /// The data key, an error if the secret is wrong or the slot was tampered with
+    pub async fn unwrap_key(&self, secret: &[u8]) -> Result<Vec<u8>, String> {
+        self.wrapped_key
+            .read()
+            .await
+            .decrypt_stored_data(secret)
+            .map_err(|_| "Cannot unwrap the data key".to_owned())
+    }
+}
+
+/// Generate a new random data key
+///
+/// # Returns
+///
+/// The data key
+pub fn generate_data_key() -> Vec<u8> {
+    let mut data_key = vec![0u8; ENCRYPTION_KEY_LENGTH];
+    rand::rng().fill_bytes(&mut data_key);
+
+    data_key
+}
+
+/// Find the key slot of a given kind
+///
+/// # Arguments
+///
+/// * `key_slots` - The key slots of the state
+/// * `kind` - The unlock method to look for
+///
+/// # Returns
+///
+/// The key slot, if any
+pub fn find_key_slot(key_slots: &[KeySlot], kind: KeySlotKind) -> Option<&KeySlot> {
+    key_slots.iter().find(|slot| slot.kind == kind)
+}
+
+/// Add a key slot replacing any existing key slot of the same kind
+///
+/// # Arguments
+///
+/// * `key_slots` - The key slots of the state
+/// * `slot` - The key slot to add
+pub fn replace_key_slot(key_slots: &mut Vec<KeySlot>, slot: KeySlot) {
+    key_slots.retain(|existing| existing.kind != slot.kind);
+    key_slots.push(slot);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wrap_and_unwrap_key() {
+        let data_key = generate_data_key();
+        let slot = KeySlot::wrap(KeySlotKind::Password, &data_key, b"password");
+
+        assert_eq!(slot.unwrap_key(b"password").await.unwrap(), data_key);
+        assert!(slot.unwrap_key(b"wrong password").await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_serialize_key_slot() {
+        let data_key = generate_data_key();
+        let slot = KeySlot::wrap(KeySlotKind::Password, &data_key, b"password");
+        let serialized = serde_json::to_string(&slot).unwrap();
+
+        let slot = serde_json::from_str::<KeySlot>(serialized.as_str()).unwrap();
+        assert_eq!(slot.kind, KeySlotKind::Password);
+        assert_eq!(slot.unwrap_key(b"password").await.unwrap(), data_key);
+    }
+
+    #[test]
+    fn test_replace_key_slot() {
+        let data_key = generate_data_key();
+        let mut key_slots = vec![KeySlot::wrap(KeySlotKind::Password, &data_key, b"password")];
+
+        replace_key_slot(
+            &mut key_slots,
+            KeySlot::wrap(KeySlotKind::Recovery, &data_key, b"recovery"),
+        );
+        replace_key_slot(
+            &mut key_slots,
+            KeySlot::wrap(KeySlotKind::Recovery, &data_key, b"new recovery"),
+        );
+
+        assert_eq!(key_slots.len(), 2);
+        assert!(find_key_slot(&key_slots, KeySlotKind::Password).is_some());
+        assert!(find_key_slot(&key_slots, KeySlotKind::Recovery).is_some());
+    }
+
+    #[test]
+    fn test_generate_data_key() {
+        let data_key = generate_data_key();
+        assert_eq!(data_key.len(), ENCRYPTION_KEY_LENGTH);
+        assert_ne!(data_key, generate_data_key());
+    }
+}
@@ -1,8 +1,35 @@
-mod provider_data;
+mod account_commands;
+pub mod account_registry;
+mod idle_lock;
+mod key_slot;
+mod password_rotation;
+pub mod provider_data;
 mod settings;
 pub mod state;
+mod state_backup;
 mod state_commands;
-mod storage_provider;
+mod state_events;
+mod state_file;
+mod state_file_watcher;
+pub mod state_generation;
+mod state_migration;
+mod state_patch;
+mod state_signature;
+pub mod storage_provider;
+mod unlock_attempts;
+mod vault_bundle;
+mod vault_bundle_commands;
+mod vault_commands;
+mod vault_registry;
 
+pub use account_commands::*;
+pub use idle_lock::*;
+pub use settings::recovery_key_commands::*;
 pub use settings::security_commands::*;
+pub use settings::two_factor_commands::*;
+pub use settings::unlock_attempts_commands::*;
 pub use state_commands::*;
+pub use state_events::*;
+pub use state_file_watcher::watch_state_file;
+pub use vault_bundle_commands::*;
+pub use vault_commands::*;
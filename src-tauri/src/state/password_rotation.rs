@@ -0,0 +1,199 @@
+use crate::crypt::{CryptData, CryptDataMode};
+use crate::state::key_slot::{KeySlot, KeySlotKind, replace_key_slot};
+use crate::state::state::AppStateDeep;
+use crate::utility::atomic_file::write_atomically;
+use as_inner_serializable::AsInnerSerializable;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::warn;
+
+/// A password rotation built off to the side of the state.
+///
+/// The rotation is written to a journal next to the state file before it is applied, so that a
+/// rotation interrupted before the new state reached the disk can be resumed with the new password
+/// on the next unlock.
+#[derive(Debug, Clone, Default, AsInnerSerializable)]
+pub struct PasswordRotation {
+    /// The hash of the new password
+    pub password: Arc<RwLock<CryptData>>,
+    /// The data key wrapped by the new password
+    pub key_slot: KeySlot,
+}
+
+impl PasswordRotation {
+    /// Prepare the rotation to a new password, the state is not modified
+    ///
+    /// # Arguments
+    ///
+    /// * `data_key` - The data key
+    /// * `new_password` - The new password
+    ///
+    /// # Returns
+    ///
+    /// The rotation
+    pub fn prepare(data_key: &[u8], new_password: &str) -> Self {
+        Self {
+            password: Arc::new(RwLock::new(CryptData::new(
+                new_password.as_bytes().to_vec(),
+                CryptDataMode::to_u8(vec![CryptDataMode::Hash, CryptDataMode::PasswordHash]),
+                None,
+                None,
+            ))),
+            key_slot: KeySlot::wrap(KeySlotKind::Password, data_key, new_password.as_bytes()),
+        }
+    }
+
+    /// Check whether the rotation was made for a password
+    ///
+    /// # Arguments
+    ///
+    /// * `password` - The password to check
+    ///
+    /// # Returns
+    ///
+    /// Whether the password is the new password of the rotation
+    pub async fn verify(&self, password: &str) -> bool {
+        self.password.read().await.verify(password.as_bytes())
+    }
+
+    /// Apply the rotation, the password hash and the password key slot are replaced together
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The (writable) application state
+    pub fn apply(self, state: &mut AppStateDeep) {
+        state.password = self.password;
+        replace_key_slot(&mut state.key_slots, self.key_slot);
+    }
+}
+
+/// Get the path of the password rotation journal of a state file
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// The path of the journal
+pub fn rotation_journal_path(state_file: &Path) -> PathBuf {
+    let mut file_name = state_file.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".rotation");
+
+    state_file.with_file_name(file_name)
+}
+
+/// Write the password rotation journal of a state file
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+/// * `rotation` - The rotation about to be applied
+///
+/// # Returns
+///
+/// Nothing
+pub async fn write_rotation_journal(
+    state_file: &Path,
+    rotation: &PasswordRotation,
+) -> Result<(), String> {
+    let json = serde_json::to_vec(rotation).map_err(|e| e.to_string())?;
+
+    write_atomically(&rotation_journal_path(state_file), &json).await
+}
+
+/// Read the password rotation journal of a state file
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// The interrupted rotation, if any, an unreadable journal is ignored
+pub async fn read_rotation_journal(state_file: &Path) -> Option<PasswordRotation> {
+    let content = tokio::fs::read(rotation_journal_path(state_file))
+        .await
+        .ok()?;
+
+    serde_json::from_slice::<PasswordRotation>(&content)
+        .inspect_err(|e| warn!("Ignoring the unreadable password rotation journal: {}", e))
+        .ok()
+}
+
+/// Remove the password rotation journal of a state file
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// Nothing
+pub async fn remove_rotation_journal(state_file: &Path) -> Result<(), String> {
+    match tokio::fs::remove_file(rotation_journal_path(state_file)).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::key_slot::{find_key_slot, generate_data_key};
+    use rand::RngCore;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rotation_journal() {
+        let directory = std::env::temp_dir().join(format!(
+            "storage-orchestra-rotation-{}",
+            rand::rng().next_u64()
+        ));
+        tokio::fs::create_dir_all(&directory).await.unwrap();
+        let state_file = directory.join("state.json");
+
+        assert!(read_rotation_journal(&state_file).await.is_none());
+
+        let data_key = generate_data_key();
+        let rotation = PasswordRotation::prepare(&data_key, "new password");
+        write_rotation_journal(&state_file, &rotation)
+            .await
+            .unwrap();
+
+        let journal = read_rotation_journal(&state_file).await.unwrap();
+        assert!(journal.verify("new password").await);
+        assert!(!journal.verify("old password").await);
+        assert_eq!(
+            journal.key_slot.unwrap_key(b"new password").await.unwrap(),
+            data_key
+        );
+
+        remove_rotation_journal(&state_file).await.unwrap();
+        assert!(read_rotation_journal(&state_file).await.is_none());
+        assert!(remove_rotation_journal(&state_file).await.is_ok());
+
+        tokio::fs::remove_dir_all(&directory).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_apply_rotation() {
+        let data_key = generate_data_key();
+        let mut state = AppStateDeep {
+            key_slots: vec![
+                KeySlot::wrap(KeySlotKind::Password, &data_key, b"old password"),
+                KeySlot::wrap(KeySlotKind::Recovery, &data_key, b"recovery"),
+            ],
+            ..Default::default()
+        };
+
+        PasswordRotation::prepare(&data_key, "new password").apply(&mut state);
+
+        assert!(state.password.read().await.verify(b"new password"));
+        assert_eq!(state.key_slots.len(), 2);
+        let slot = find_key_slot(&state.key_slots, KeySlotKind::Password).unwrap();
+        assert_eq!(slot.unwrap_key(b"new password").await.unwrap(), data_key);
+        assert!(slot.unwrap_key(b"old password").await.is_err());
+    }
+}
@@ -1,22 +1,230 @@
 use crate::crypt::CryptData;
 use crate::state::storage_provider::StorageProvider;
-use as_inner_serializable::AsInnerSerializable;
 use serde::{Deserialize, Serialize};
 use specta::Type;
-use std::sync::Arc;
-use tokio::sync::RwLock;
 
-/// The data of a storage provider
-#[derive(Debug, Clone, Default, Type, AsInnerSerializable)]
+/// The data of a storage provider account
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Type)]
 pub struct ProviderData {
-    /// The access token
-    pub access_token: Arc<RwLock<CryptData>>,
-    /// The refresh token
-    pub refresh_token: Arc<RwLock<CryptData>>,
-    /// The expiry date of the token (utc unix timestamp)
-    pub expiry: u64,
-    /// The owner of the token (email)
+    /// The stable identifier of the account
+    pub id: String,
+    /// The credential used to access the account
+    pub credential: AccountCredential,
+    /// The owner of the account (email or username)
     pub owner: String,
-    /// The provider of the token
+    /// The provider of the account
     pub provider: StorageProvider,
+    /// The name of the account as shown by the provider
+    pub display_name: Option<String>,
+    /// The url of the avatar of the account
+    pub avatar_url: Option<String>,
+    /// The last known storage quota of the account
+    pub quota: Option<QuotaSnapshot>,
+}
+
+/// The credential of a storage provider account, the secrets are encrypted with the data key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum AccountCredential {
+    /// OAuth tokens
+    #[serde(rename = "oauth")]
+    OAuth {
+        /// The access token
+        access_token: CryptData,
+        /// The refresh token
+        refresh_token: CryptData,
+        /// The expiry date of the access token (utc unix timestamp)
+        expiry: u64,
+        /// The type of the access token (usually `Bearer`)
+        token_type: String,
+        /// The scopes granted to the application
+        scopes: Vec<String>,
+    },
+    /// A static key pair (S3 style access key and secret)
+    KeyPair {
+        /// The access key id
+        access_key_id: String,
+        /// The secret access key
+        secret_access_key: CryptData,
+    },
+    /// A username and a password (WebDAV or SFTP)
+    Password {
+        /// The username
+        username: String,
+        /// The password
+        password: CryptData,
+        /// Whether the password is an application specific password
+        app_password: bool,
+    },
+}
+
+impl Default for AccountCredential {
+    fn default() -> Self {
+        AccountCredential::OAuth {
+            access_token: CryptData::default(),
+            refresh_token: CryptData::default(),
+            expiry: 0,
+            token_type: String::new(),
+            scopes: Vec::new(),
+        }
+    }
+}
+
+impl AccountCredential {
+    /// Get the expiry date of the credential
+    ///
+    /// # Returns
+    ///
+    /// The expiry date (utc unix timestamp), `None` if the credential does not expire
+    pub fn expiry(&self) -> Option<u64> {
+        match self {
+            AccountCredential::OAuth { expiry, .. } => Some(*expiry),
+            AccountCredential::KeyPair { .. } | AccountCredential::Password { .. } => None,
+        }
+    }
+
+    /// Get the secrets of the credential
+    ///
+    /// # Returns
+    ///
+    /// The name and the value of each secret
+    pub fn secrets_mut(&mut self) -> Vec<(&'static str, &mut CryptData)> {
+        match self {
+            AccountCredential::OAuth {
+                access_token,
+                refresh_token,
+                ..
+            } => vec![
+                ("access_token", access_token),
+                ("refresh_token", refresh_token),
+            ],
+            AccountCredential::KeyPair {
+                secret_access_key, ..
+            } => vec![("secret_access_key", secret_access_key)],
+            AccountCredential::Password { password, .. } => vec![("password", password)],
+        }
+    }
+}
+
+/// A snapshot of the storage quota of an account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct QuotaSnapshot {
+    /// The used storage in bytes
+    pub used: u64,
+    /// The total storage in bytes, `None` if the storage is unlimited
+    pub total: Option<u64>,
+    /// The date of the snapshot (utc unix timestamp)
+    pub checked_at: u64,
+}
+
+/// The kind of the credential of an account
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialKind {
+    /// OAuth tokens
+    #[serde(rename = "oauth")]
+    OAuth,
+    /// A static key pair (S3 style access key and secret)
+    KeyPair,
+    /// A username and a password (WebDAV or SFTP)
+    Password,
+}
+
+/// A storage provider account as shown to the webview, the credential never leaves the backend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct AccountView {
+    /// The stable identifier of the account
+    pub id: String,
+    /// The provider of the account
+    pub provider: StorageProvider,
+    /// The owner of the account (email or username)
+    pub owner: String,
+    /// The kind of the credential of the account
+    pub kind: CredentialKind,
+    /// The name of the account as shown by the provider
+    pub display_name: Option<String>,
+    /// The url of the avatar of the account
+    pub avatar_url: Option<String>,
+    /// The scopes granted to the application, empty if the credential has no scopes
+    pub scopes: Vec<String>,
+    /// When the access token expires (utc unix timestamp), `None` if the credential does not
+    /// expire
+    pub expiry: Option<u64>,
+    /// The last known storage quota of the account
+    pub quota: Option<QuotaSnapshot>,
+}
+
+impl AccountView {
+    /// Get the public view of an account
+    ///
+    /// # Arguments
+    ///
+    /// * `account` - The account
+    ///
+    /// # Returns
+    ///
+    /// The view of the account
+    pub fn from_account(account: &ProviderData) -> Self {
+        let (kind, scopes) = match &account.credential {
+            AccountCredential::OAuth { scopes, .. } => (CredentialKind::OAuth, scopes.clone()),
+            AccountCredential::KeyPair { .. } => (CredentialKind::KeyPair, Vec::new()),
+            AccountCredential::Password { .. } => (CredentialKind::Password, Vec::new()),
+        };
+
+        Self {
+            id: account.id.clone(),
+            provider: account.provider.clone(),
+            owner: account.owner.clone(),
+            kind,
+            display_name: account.display_name.clone(),
+            avatar_url: account.avatar_url.clone(),
+            scopes,
+            expiry: account.credential.expiry(),
+            quota: account.quota.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_view() {
+        let account = ProviderData {
+            id: "1".to_owned(),
+            credential: AccountCredential::OAuth {
+                access_token: CryptData::default(),
+                refresh_token: CryptData::default(),
+                expiry: 10,
+                token_type: "Bearer".to_owned(),
+                scopes: vec!["email".to_owned()],
+            },
+            owner: "user@example.com".to_owned(),
+            provider: StorageProvider::Google,
+            ..Default::default()
+        };
+
+        let view = AccountView::from_account(&account);
+        assert_eq!(view.id, "1");
+        assert_eq!(view.kind, CredentialKind::OAuth);
+        assert_eq!(view.scopes, vec!["email".to_owned()]);
+        assert_eq!(view.expiry, Some(10));
+
+        let serialized = serde_json::to_value(&view).unwrap();
+        assert_eq!(serialized["kind"], "oauth");
+        assert!(serialized.get("credential").is_none());
+
+        let account = ProviderData {
+            credential: AccountCredential::KeyPair {
+                access_key_id: "key".to_owned(),
+                secret_access_key: CryptData::default(),
+            },
+            ..account
+        };
+        let view = AccountView::from_account(&account);
+        assert_eq!(view.kind, CredentialKind::KeyPair);
+        assert!(view.scopes.is_empty());
+        assert_eq!(view.expiry, None);
+    }
 }
@@ -59,8 +59,6 @@ pub enum DefaultPageGeneralGroup {
 /// A provider page
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Type, Default)]
 pub struct ProviderPage {
-    /// The storage provider
-    pub provider: StorageProvider,
-    /// The owner of the provider
-    pub owner: String,
+    /// The id of the account shown by the page
+    pub account_id: String,
 }
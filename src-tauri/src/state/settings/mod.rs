@@ -6,10 +6,13 @@ use serde::{Deserialize, Serialize};
 use specta::Type;
 
 pub mod general_behaviour;
-mod security;
+pub mod recovery_key_commands;
+pub mod security;
 pub mod security_commands;
 mod state_cryptdata_instances;
 pub mod theme;
+pub mod two_factor_commands;
+pub mod unlock_attempts_commands;
 
 /// The settings of the application
 #[derive(Debug, Clone, Serialize, Deserialize, Type, Default, AsResultEnum)]
@@ -0,0 +1,186 @@
+use crate::secret_manager::read_data_key;
+use crate::state::emit_state_changed;
+use crate::state::key_slot::{KeySlot, KeySlotKind, find_key_slot, replace_key_slot};
+use crate::state::save;
+use crate::state::settings::security_commands::check_password;
+use crate::state::state::{AppState, AppStateDeepKeys};
+use bip39::Mnemonic;
+use rand::RngCore;
+use specta::specta;
+use tauri::{AppHandle, State, command};
+use tracing::debug;
+
+/// The entropy of the recovery key in bytes, encoded as a 24 words mnemonic
+pub static RECOVERY_KEY_ENTROPY_LENGTH: usize = 32;
+
+/// Generate a new recovery key, replacing the previous one if any
+///
+/// The recovery key wraps the data key in its own key slot, it is never stored and is shown
+/// only once.
+///
+/// # Arguments
+///
+/// - `state` - The application state
+/// - `password` - The password
+///
+/// # Returns
+///
+/// Returns the recovery key as a word-list mnemonic
+#[command]
+#[specta]
+pub async fn generate_recovery_key(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    password: String,
+) -> Result<String, String> {
+    check_password(app.clone(), state.clone(), password).await?;
+
+    let (recovery_key, secret) = make_recovery_key()?;
+
+    let data_key = read_data_key().await?;
+    let slot = KeySlot::wrap(
+        KeySlotKind::Recovery,
+        data_key.as_slice(),
+        secret.as_slice(),
+    );
+    drop(data_key);
+
+    let mut writable_state = state.write().await;
+    replace_key_slot(&mut writable_state.key_slots, slot);
+    drop(writable_state);
+
+    debug!("Recovery key generated");
+    save(app.clone(), state.clone()).await?;
+    emit_state_changed(&app, &state, AppStateDeepKeys::KeySlots).await;
+
+    Ok(recovery_key)
+}
+
+/// Revoke the recovery key
+///
+/// # Arguments
+///
+/// - `state` - The application state
+/// - `password` - The password
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the recovery key was revoked, otherwise returns `Err(String)`
+#[command]
+#[specta]
+pub async fn revoke_recovery_key(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    password: String,
+) -> Result<(), String> {
+    check_password(app.clone(), state.clone(), password).await?;
+
+    let mut writable_state = state.write().await;
+    if find_key_slot(&writable_state.key_slots, KeySlotKind::Recovery).is_none() {
+        return Err("No recovery key configured".to_owned());
+    }
+    writable_state
+        .key_slots
+        .retain(|slot| slot.kind != KeySlotKind::Recovery);
+    drop(writable_state);
+
+    debug!("Recovery key revoked");
+    save(app.clone(), state.clone()).await?;
+    emit_state_changed(&app, &state, AppStateDeepKeys::KeySlots).await;
+
+    Ok(())
+}
+
+/// Check whether a recovery key is configured
+///
+/// # Arguments
+///
+/// - `state` - The application state
+///
+/// # Returns
+///
+/// Returns `true` if a recovery key is configured
+#[command]
+#[specta]
+pub async fn has_recovery_key(state: State<'_, AppState>) -> Result<bool, String> {
+    let readable_state = state.read().await;
+
+    Ok(find_key_slot(&readable_state.key_slots, KeySlotKind::Recovery).is_some())
+}
+
+/// Parse a recovery key into the secret wrapping the data key
+///
+/// The words are matched case-insensitively and regardless of the whitespaces between them.
+///
+/// # Arguments
+///
+/// - `recovery_key` - The recovery key as a word-list mnemonic
+///
+/// # Returns
+///
+/// Returns the secret, an error if the mnemonic is malformed
+pub fn parse_recovery_key(recovery_key: &str) -> Result<Vec<u8>, String> {
+    let normalized = recovery_key
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Mnemonic::parse_normalized(normalized.as_str())
+        .map(|mnemonic| mnemonic.to_entropy())
+        .map_err(|_| "Invalid recovery key".to_owned())
+}
+
+/// Generate a random recovery key
+///
+/// # Returns
+///
+/// Returns the recovery key as a word-list mnemonic along with the secret it encodes
+fn make_recovery_key() -> Result<(String, Vec<u8>), String> {
+    let mut secret = vec![0u8; RECOVERY_KEY_ENTROPY_LENGTH];
+    rand::rng().fill_bytes(&mut secret);
+
+    let mnemonic = Mnemonic::from_entropy(secret.as_slice()).map_err(|e| e.to_string())?;
+
+    Ok((mnemonic.to_string(), secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::key_slot::generate_data_key;
+
+    #[test]
+    fn test_recovery_key_round_trip() {
+        let (recovery_key, secret) = make_recovery_key().unwrap();
+        assert_eq!(recovery_key.split(' ').count(), 24);
+        assert_eq!(parse_recovery_key(recovery_key.as_str()).unwrap(), secret);
+
+        // the words are matched regardless of the case and of the whitespaces
+        let sloppy = format!("  {}\n", recovery_key.to_uppercase().replace(' ', "   "));
+        assert_eq!(parse_recovery_key(sloppy.as_str()).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_parse_invalid_recovery_key() {
+        let valid = format!("{} art", "abandon ".repeat(23).trim_end());
+        assert_eq!(parse_recovery_key(valid.as_str()).unwrap(), vec![0u8; 32]);
+
+        // the last word does not match the checksum
+        let wrong_checksum = "abandon ".repeat(24);
+        assert!(parse_recovery_key(wrong_checksum.as_str()).is_err());
+
+        assert!(parse_recovery_key("not a recovery key").is_err());
+        assert!(parse_recovery_key("").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_recovery_key_unwraps_data_key() {
+        let data_key = generate_data_key();
+        let (recovery_key, secret) = make_recovery_key().unwrap();
+        let slot = KeySlot::wrap(KeySlotKind::Recovery, &data_key, &secret);
+
+        let parsed = parse_recovery_key(recovery_key.as_str()).unwrap();
+        assert_eq!(slot.unwrap_key(&parsed).await.unwrap(), data_key);
+    }
+}
@@ -6,7 +6,10 @@ use specta::Type;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-#[derive(Debug, Clone, Type, Default, AsInnerSerializable)]
+/// The idle time after which the vault is locked by default, in minutes
+pub static DEFAULT_IDLE_LOCK_MINUTES: u32 = 15;
+
+#[derive(Debug, Clone, Type, AsInnerSerializable)]
 pub struct Security {
     /// The encryption settings
     pub encryption: EncryptionSettings,
@@ -14,6 +17,29 @@ pub struct Security {
     pub two_factor_authentication: TwoFactorAuthentication,
     /// The state file signature, this is used to verify the integrity of the state file.
     pub signature: Arc<RwLock<CryptData>>,
+    /// The idle time after which the vault is locked, in minutes, 0 to never lock it
+    #[serde(default = "default_idle_lock_minutes")]
+    pub idle_lock_minutes: u32,
+}
+
+impl Default for Security {
+    fn default() -> Self {
+        Self {
+            encryption: EncryptionSettings::default(),
+            two_factor_authentication: TwoFactorAuthentication::default(),
+            signature: Arc::default(),
+            idle_lock_minutes: DEFAULT_IDLE_LOCK_MINUTES,
+        }
+    }
+}
+
+/// Get the idle lock delay of the states saved before it was configurable
+///
+/// # Returns
+///
+/// The default idle lock delay, in minutes
+fn default_idle_lock_minutes() -> u32 {
+    DEFAULT_IDLE_LOCK_MINUTES
 }
 
 /// The encryption settings
@@ -33,4 +59,7 @@ pub struct TwoFactorAuthentication {
     pub enabled: bool,
     /// The two factor authentication secret
     pub secret: Option<Arc<RwLock<CryptData>>>,
+    /// The hashes of the single-use recovery codes
+    #[serde(default)]
+    pub recovery_codes: Vec<CryptData>,
 }
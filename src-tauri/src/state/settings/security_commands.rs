@@ -1,20 +1,22 @@
-use crate::crypt;
-use crate::crypt::{CryptData, CryptDataMode};
-use crate::state::settings::state_cryptdata_instances::{
-    count_states_cryptdata_instances, cryptdatas_of_state, visit_states_cryptdata_instances,
+use crate::crypt::{CryptData, CryptDataMode, KdfAlgorithm};
+use crate::secret_manager::record_authentication;
+use crate::state::emit_state_changed;
+use crate::state::key_slot::{KeySlot, KeySlotKind, find_key_slot, generate_data_key};
+use crate::state::password_rotation::{
+    PasswordRotation, remove_rotation_journal, write_rotation_journal,
 };
-use crate::state::state::AppState;
-use crate::state::{PASSWORD, save};
-use crate::utility::get_json_value::get_json_value;
+use crate::state::settings::state_cryptdata_instances::visit_states_cryptdata_instances;
+use crate::state::state::{AppState, AppStateDeep, AppStateDeepKeys};
+use crate::state::state_file::state_file_path;
+use crate::state::unlock_attempts::guard_unlock_attempt;
+use crate::state::{flush_state, save};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use specta::{Type, specta};
 use std::sync::Arc;
-use std::time::Duration;
 use tauri::ipc::Channel;
 use tauri::{AppHandle, State, command};
 use tokio::sync::RwLock;
-use tracing::{error, warn};
+use tracing::debug;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Type)]
 #[serde(rename_all = "snake_case", tag = "event", content = "data")]
@@ -26,8 +28,12 @@ pub enum PasswordUpdateEvent {
 
 /// Check that the password is correct
 ///
+/// The failed checks delay the next ones and count towards the lockout threshold of the vault, a
+/// successful check allows the sensitive commands to run for a short time.
+///
 /// # Arguments
 ///
+/// - `app` - The application handle
 /// - `state` - The application state
 /// - `password` - The password to check
 ///
@@ -36,26 +42,38 @@ pub enum PasswordUpdateEvent {
 /// Returns `Ok(())` if the password is correct, otherwise returns `Err("Invalid password")`
 #[command]
 #[specta]
-pub async fn check_password(state: State<'_, AppState>, password: String) -> Result<(), String> {
-    let readable_state = state.read().await;
-    let stored_password = readable_state.password.clone();
-    drop(readable_state);
+pub async fn check_password(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    password: String,
+) -> Result<(), String> {
+    let state_file = state_file_path(&app)?;
 
-    let stored_password = stored_password.read().await;
+    guard_unlock_attempt(&state_file, || async {
+        let readable_state = state.read().await;
+        let stored_password = readable_state.password.clone();
+        drop(readable_state);
 
-    if crypt::verify(
-        password.as_str().as_bytes(),
-        stored_password.get_data_as_string().as_str(),
-    ) {
-        Ok(())
-    } else {
-        Err("Invalid password".to_string())
-    }
+        let stored_password = stored_password.read().await;
+
+        if stored_password.verify(password.as_str().as_bytes()) {
+            Ok(())
+        } else {
+            Err("Invalid password".to_string())
+        }
+    })
+    .await?;
+
+    record_authentication().await;
+
+    Ok(())
 }
 
 /// Update the password
 ///
-/// Note that this triggers the update of all encrypted data too as the password is used to encrypt them.
+/// Only the password hash and the password key slot are updated, the secrets are encrypted with
+/// the data key that the password key slot wraps. The rotation is built off to the side and
+/// journaled before being applied, the journal is removed once the rotated state is on disk.
 ///
 /// # Arguments
 ///
@@ -76,243 +94,185 @@ pub async fn update_password(
     new_password: String,
     ev: Channel<PasswordUpdateEvent>,
 ) -> Result<(), String> {
-    check_password(state.clone(), current_password.clone()).await?;
-
-    let async_state = Arc::new(state);
-    let async_ev = Arc::new(ev);
+    check_password(app.clone(), state.clone(), current_password.clone()).await?;
 
-    // send the initialization event with the count of how many cryptdata should be updated
-    async_ev
-        .send(PasswordUpdateEvent::Initialized {
-            steps: count_states_cryptdata_instances(async_state.clone()).await,
-        })
+    // building the rotation, journaling it and committing it to disk
+    ev.send(PasswordUpdateEvent::Initialized { steps: 3 })
         .map_err(|e| e.to_string())?;
 
-    let delayed_items = Arc::new(RwLock::new(Vec::new()));
-    let delayed_items_visit_clone = delayed_items.clone();
-
-    let passed_keys = Arc::new(RwLock::new(Vec::new()));
-    let passed_keys_visit_clone = passed_keys.clone();
-
-    let ev_visit_clone = async_ev.clone();
-
-    let new_psw_visit_clone = new_password.clone();
-
-    // update the password for all the crypt data instances
-    visit_states_cryptdata_instances(
-        async_state.clone(),
-        Box::new(move |key: String, crypt_data: Arc<RwLock<CryptData>>| {
-            let current_password = current_password.clone();
-            let new_password = new_psw_visit_clone.clone();
-            let delayed_items = delayed_items_visit_clone.clone();
-            let passed_keys = passed_keys_visit_clone.clone();
-            let ev = ev_visit_clone.clone();
-
-            Box::pin(async move {
-                tokio::time::sleep(Duration::from_millis(500)).await;
-
-                let readable_crypt_data = crypt_data.read().await;
-                let modes = readable_crypt_data.get_modes();
-                let related_keys = if !readable_crypt_data.related_keys.is_empty() {
-                    Some(readable_crypt_data.related_keys.clone())
-                } else {
-                    None
-                };
-                drop(readable_crypt_data);
-
-                let mut writable_crypt_data = crypt_data.write().await;
-
-                let new_psw = new_password.clone();
-                let current_password = current_password.clone();
-
-                if modes.contains(&CryptDataMode::PasswordHash) {
-                    *writable_crypt_data = CryptData::new(
-                        new_psw.into_bytes(),
-                        CryptDataMode::to_u8(modes),
-                        None,
-                        None,
-                    );
-                } else if modes.contains(&CryptDataMode::SignatureHash) {
-                    // skip signature as it is automatically updated on state save
-                } else if modes.contains(&CryptDataMode::Hash) {
-                    // delay all the hashed items
-                    let mut delayed_items = delayed_items.write().await;
-                    delayed_items.push(crypt_data.clone());
-
-                    // skip the current crypt data instance
-                    return Ok(());
-                } else {
-                    // handle other modes
-                    *writable_crypt_data = CryptData::new(
-                        (writable_crypt_data).get_raw_data(Some(current_password.as_bytes()))?,
-                        CryptDataMode::to_u8(modes),
-                        Some(new_psw.as_bytes()),
-                        related_keys,
-                    );
-                }
-
-                ev.send(PasswordUpdateEvent::StepCompleted)
-                    .map_err(|e| e.to_string())?;
-
-                let mut passed_keys = passed_keys.write().await;
-                passed_keys.push(key);
-                Ok(())
-            })
-        }),
-    )
-    .await?;
-
-    // sort the delayed items, ensure SignatureHash is last
-    let delayed_items = delayed_items.write().await;
-
-    // update the delayed items
-    for crypt_data in delayed_items.iter() {
-        tokio::time::sleep(Duration::from_millis(500)).await;
-
-        let readable_crypt_data = crypt_data.read().await;
-
-        let modes = readable_crypt_data.get_modes();
-        let new_psw = new_password.clone();
-
-        let state_value = async_state.read().await;
-        let json_state = serde_json::to_value(&*state_value).map_err(|e| e.to_string())?;
+    let readable_state = state.read().await;
+    let data_key = find_key_slot(&readable_state.key_slots, KeySlotKind::Password)
+        .ok_or("Password key slot not found")?
+        .unwrap_key(current_password.as_bytes())
+        .await?;
+    drop(readable_state);
 
-        // get the related keys as a string
-        let dataset = get_related_hash_data(crypt_data.clone(), &json_state).await;
+    let rotation = PasswordRotation::prepare(data_key.as_slice(), &new_password);
+    drop(data_key);
+    ev.send(PasswordUpdateEvent::StepCompleted)
+        .map_err(|e| e.to_string())?;
 
-        // ensure that all the keys the dataset depends on are already passed from the iterator
-        // if this is not the case maybe a deadlock has been reached
-        if should_delay_hashing(async_state.clone(), crypt_data.clone(), passed_keys.clone()).await
-        {
-            error!(
-                "Failed to update the password for crypt data instance: {:?}",
-                crypt_data
-            );
-            continue;
-        }
+    let state_file = state_file_path(&app)?;
+    write_rotation_journal(&state_file, &rotation).await?;
+    ev.send(PasswordUpdateEvent::StepCompleted)
+        .map_err(|e| e.to_string())?;
 
-        make_dynamic_hash(crypt_data.clone(), dataset, new_psw.clone(), modes).await;
+    let mut writable_state = state.write().await;
+    rotation.apply(&mut writable_state);
+    drop(writable_state);
 
-        async_ev
-            .send(PasswordUpdateEvent::StepCompleted)
-            .map_err(|e| e.to_string())?;
-    }
-
-    async_ev
-        .send(PasswordUpdateEvent::Completed)
+    // the journal is only removed once the rotated state is on disk, an interrupted rotation is
+    // resumed on the next unlock with the new password
+    save(app.clone(), state.clone()).await?;
+    flush_state(state.clone()).await?;
+    remove_rotation_journal(&state_file).await?;
+    ev.send(PasswordUpdateEvent::StepCompleted)
         .map_err(|e| e.to_string())?;
 
-    // Update the static password in memory
-    let mut static_psw = PASSWORD.get().unwrap().write().await;
-    *static_psw = new_password;
-    drop(static_psw);
+    ev.send(PasswordUpdateEvent::Completed)
+        .map_err(|e| e.to_string())?;
 
-    save(
-        app,
-        Arc::into_inner(async_state).ok_or("Cannot take state ownership")?,
-    )
-    .await?;
+    emit_state_changed(&app, &state, AppStateDeepKeys::Password).await;
 
     Ok(())
 }
 
-/// Get the related hash data
+/// Replace the password hash and re-wrap the data key with the new password
 ///
 /// # Arguments
 ///
-/// - `crypt_data` - The crypt data instance
-/// - `state` - The application state as a JSON value
+/// - `state` - The (writable) application state
+/// - `data_key` - The data key
+/// - `new_password` - The new password
+/// - `ev` - The event channel
 ///
 /// # Returns
 ///
-/// Returns the related hash data
-async fn get_related_hash_data(
-    crypt_data: Arc<RwLock<CryptData>>,
-    state: &Value,
-) -> Vec<(String, String)> {
-    let mut dataset = Vec::new();
-
-    let crypt_data = crypt_data.read().await;
-
-    for key in crypt_data.related_keys.iter() {
-        let value = get_json_value(state, key.as_str());
-
-        if value.is_none() {
-            warn!("Key '{}' not found in state", key);
-            continue;
-        }
+/// Returns `Ok(())` if the password was replaced, otherwise returns `Err(String)`
+pub fn replace_password(
+    state: &mut AppStateDeep,
+    data_key: &[u8],
+    new_password: &str,
+    ev: &Channel<PasswordUpdateEvent>,
+) -> Result<(), String> {
+    // building the rotation and applying it
+    ev.send(PasswordUpdateEvent::Initialized { steps: 2 })
+        .map_err(|e| e.to_string())?;
 
-        dataset.push((key.clone(), value.unwrap().to_string()));
-    }
+    let rotation = PasswordRotation::prepare(data_key, new_password);
+    ev.send(PasswordUpdateEvent::StepCompleted)
+        .map_err(|e| e.to_string())?;
 
-    dataset
+    rotation.apply(state);
+    ev.send(PasswordUpdateEvent::StepCompleted)
+        .map_err(|e| e.to_string())
 }
 
-/// Check if hashing should be delayed
+/// Re-create the password hash if it is still computed using the legacy key derivation so that it
+/// uses the memory-hard key derivation.
+///
+/// This is run once the password has been verified, making the upgrade transparent to the user.
 ///
 /// # Arguments
 ///
 /// - `state` - The application state
-/// - `crypt_data` - The crypt data instance
-/// - `iterator` - The application state iterator
+/// - `password` - The (verified) password
 ///
 /// # Returns
 ///
-/// Returns `true` if hashing should be delayed, otherwise returns `false`
-async fn should_delay_hashing(
+/// Returns `Ok(true)` if the password hash was upgraded and the state should be saved
+pub async fn upgrade_legacy_cryptdata(
     state: Arc<State<'_, AppState>>,
-    crypt_data: Arc<RwLock<CryptData>>,
-    passed_keys: Arc<RwLock<Vec<String>>>,
-) -> bool {
-    let crypt_data = crypt_data.read().await;
-    let passed_keys = passed_keys.read().await;
-    let valid_keys = cryptdatas_of_state(state).await;
-
-    for key in crypt_data.related_keys.iter() {
-        if valid_keys.contains(key) && !passed_keys.contains(key) {
-            return true;
-        }
+    password: String,
+) -> Result<bool, String> {
+    let readable_state = state.read().await;
+    let mut writable_password = readable_state.password.write().await;
+    let modes = writable_password.get_modes();
+
+    // older versions stored the password hash without the `PasswordHash` marker
+    let is_unmarked_password = !modes.contains(&CryptDataMode::PasswordHash);
+
+    if !writable_password.needs_rehash() && !is_unmarked_password {
+        return Ok(false);
     }
 
-    false
+    debug!("Upgrading legacy key derivation of the password hash");
+
+    *writable_password = CryptData::new(
+        password.into_bytes(),
+        CryptDataMode::to_u8(vec![CryptDataMode::Hash, CryptDataMode::PasswordHash]),
+        None,
+        None,
+    );
+
+    Ok(true)
 }
 
-/// Make dynamic hash
+/// Move the secrets encrypted with the password to a new random data key wrapped by the password.
+///
+/// States created before the key hierarchy have no key slots and encrypt every secret using the
+/// password, this is run once the password has been verified.
 ///
 /// # Arguments
 ///
-/// - `crypt_data` - The crypt data instance
-/// - `dataset` - The related hash data
-/// - `new_psw` - The new password
-/// - `modes` - The crypt data modes
+/// - `state` - The application state
+/// - `password` - The (verified) password
 ///
 /// # Returns
 ///
-/// Updates in-place the crypt data instance
-async fn make_dynamic_hash(
-    crypt_data: Arc<RwLock<CryptData>>,
-    dataset: Vec<(String, String)>,
-    new_psw: String,
-    modes: Vec<CryptDataMode>,
-) {
-    let raw_data = dataset
-        .into_iter()
-        .map(|item| item.1)
-        .collect::<Vec<String>>()
-        .join("\n");
-
-    let mut writable_crypt_data = crypt_data.write().await;
-    let crypt_data = crypt_data.read().await;
-
-    // handle dynamic hashing based on the related fields
-    *writable_crypt_data = CryptData::new(
-        raw_data.into_bytes(),
-        CryptDataMode::to_u8(modes),
-        Some(new_psw.as_bytes()),
-        if !crypt_data.related_keys.is_empty() {
-            Some(crypt_data.related_keys.clone())
-        } else {
-            None
-        },
-    );
+/// Returns the new data key
+pub async fn migrate_to_data_key(
+    state: Arc<State<'_, AppState>>,
+    password: String,
+) -> Result<Vec<u8>, String> {
+    let data_key = generate_data_key();
+    let data_key_visit_clone = data_key.clone();
+    let password_visit_clone = password.clone();
+
+    visit_states_cryptdata_instances(
+        state.clone(),
+        Box::new(move |key: String, crypt_data: Arc<RwLock<CryptData>>| {
+            let password = password_visit_clone.clone();
+            let data_key = data_key_visit_clone.clone();
+
+            Box::pin(async move {
+                let mut writable_crypt_data = crypt_data.write().await;
+                let modes = writable_crypt_data.get_modes();
+
+                // the password hash is not encrypted and the signature is recomputed on save
+                if !modes.contains(&CryptDataMode::Encrypt) {
+                    return Ok(());
+                }
+
+                debug!("Moving '{}' to the data key", key);
+
+                let related_keys = if !writable_crypt_data.related_keys.is_empty() {
+                    Some(writable_crypt_data.related_keys.clone())
+                } else {
+                    None
+                };
+
+                *writable_crypt_data = CryptData::new_with_kdf(
+                    writable_crypt_data.get_raw_data(Some(password.as_bytes()))?,
+                    CryptDataMode::to_u8(modes),
+                    Some(data_key.as_slice()),
+                    related_keys,
+                    KdfAlgorithm::HkdfSha3_512,
+                );
+
+                Ok(())
+            })
+        }),
+    )
+    .await?;
+
+    let mut writable_state = state.write().await;
+    writable_state.key_slots = vec![KeySlot::wrap(
+        KeySlotKind::Password,
+        data_key.as_slice(),
+        password.as_bytes(),
+    )];
+    drop(writable_state);
+
+    Ok(data_key)
 }
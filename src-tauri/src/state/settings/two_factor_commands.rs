@@ -0,0 +1,505 @@
+use crate::crypt::{CryptData, CryptDataMode, KdfAlgorithm};
+use crate::secret_manager::read_data_key;
+use crate::state::emit_state_changed;
+use crate::state::save;
+use crate::state::settings::security::TwoFactorAuthentication;
+use crate::state::settings::security_commands::check_password;
+use crate::state::state::{AppState, AppStateDeepKeys};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use specta::{Type, specta};
+use std::sync::Arc;
+use tauri::{AppHandle, State, command};
+use tokio::sync::RwLock;
+use totp_rs::{Algorithm, Secret, TOTP};
+use tracing::{debug, warn};
+
+/// The issuer displayed by the authenticator applications
+pub static TOTP_ISSUER: &str = "Storage Orchestra";
+/// The account name displayed by the authenticator applications
+pub static TOTP_ACCOUNT_NAME: &str = "vault";
+/// The number of recovery codes generated when two factor authentication is enabled
+pub static RECOVERY_CODES_COUNT: usize = 10;
+/// The alphabet used to generate the recovery codes (ambiguous characters are excluded)
+static RECOVERY_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// The data needed to register the TOTP secret in an authenticator application
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Type)]
+pub struct TotpEnrollment {
+    /// The `otpauth://` URI of the secret
+    pub uri: String,
+    /// The QR code of the URI as a base64 encoded PNG image
+    pub qr_code: String,
+}
+
+/// Start the enrollment of a TOTP secret
+///
+/// The secret is stored but two factor authentication is only enabled once a code generated by
+/// the authenticator application is confirmed with `confirm_totp`.
+///
+/// # Arguments
+///
+/// - `state` - The application state
+/// - `password` - The password
+///
+/// # Returns
+///
+/// Returns the data needed to register the secret in an authenticator application
+#[command]
+#[specta]
+pub async fn enroll_totp(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    password: String,
+) -> Result<TotpEnrollment, String> {
+    check_password(app.clone(), state.clone(), password).await?;
+
+    let mut writable_state = state.write().await;
+    if writable_state
+        .settings
+        .security
+        .two_factor_authentication
+        .enabled
+    {
+        return Err("Two factor authentication is already enabled".to_owned());
+    }
+
+    let secret = Secret::generate_secret()
+        .to_bytes()
+        .map_err(|e| e.to_string())?;
+    let totp = make_totp(secret.clone())?;
+
+    // the data key has full entropy, the memory-hard key derivation is not needed
+    let data_key = read_data_key().await?;
+    writable_state
+        .settings
+        .security
+        .two_factor_authentication
+        .secret = Some(Arc::new(RwLock::new(CryptData::new_with_kdf(
+        secret,
+        CryptDataMode::to_u8(vec![CryptDataMode::Encrypt, CryptDataMode::Encode]),
+        Some(data_key.as_slice()),
+        None,
+        KdfAlgorithm::HkdfSha3_512,
+    ))));
+    drop(data_key);
+    drop(writable_state);
+
+    save(app.clone(), state.clone()).await?;
+    emit_state_changed(&app, &state, AppStateDeepKeys::Settings).await;
+
+    Ok(TotpEnrollment {
+        uri: totp.get_url(),
+        qr_code: totp.get_qr_base64()?,
+    })
+}
+
+/// Confirm the enrollment of the TOTP secret and enable two factor authentication
+///
+/// # Arguments
+///
+/// - `state` - The application state
+/// - `code` - A code generated by the authenticator application
+///
+/// # Returns
+///
+/// Returns the single-use recovery codes, they are shown only once
+#[command]
+#[specta]
+pub async fn confirm_totp(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    code: String,
+) -> Result<Vec<String>, String> {
+    let mut writable_state = state.write().await;
+    let two_factor = &mut writable_state.settings.security.two_factor_authentication;
+
+    if two_factor.enabled {
+        return Err("Two factor authentication is already enabled".to_owned());
+    }
+
+    let data_key = read_data_key().await?;
+    if !verify_totp_code(two_factor, data_key.as_slice(), code.as_str()).await? {
+        return Err("Invalid two factor authentication code".to_owned());
+    }
+    drop(data_key);
+
+    let recovery_codes = generate_recovery_codes();
+    two_factor.recovery_codes = hash_recovery_codes(&recovery_codes);
+    two_factor.enabled = true;
+    drop(writable_state);
+
+    save(app.clone(), state.clone()).await?;
+    emit_state_changed(&app, &state, AppStateDeepKeys::Settings).await;
+
+    Ok(recovery_codes)
+}
+
+/// Verify a code generated by the authenticator application
+///
+/// # Arguments
+///
+/// - `state` - The application state
+/// - `code` - The code to verify
+///
+/// # Returns
+///
+/// Returns `Ok(())` if the code is valid, otherwise returns `Err(String)`
+#[command]
+#[specta]
+pub async fn verify_totp(state: State<'_, AppState>, code: String) -> Result<(), String> {
+    let readable_state = state.read().await;
+    let data_key = read_data_key().await?;
+
+    if verify_totp_code(
+        &readable_state.settings.security.two_factor_authentication,
+        data_key.as_slice(),
+        code.as_str(),
+    )
+    .await?
+    {
+        Ok(())
+    } else {
+        Err("Invalid two factor authentication code".to_owned())
+    }
+}
+
+/// Regenerate the recovery codes, invalidating the previous ones
+///
+/// # Arguments
+///
+/// - `state` - The application state
+/// - `code` - A code generated by the authenticator application
+///
+/// # Returns
+///
+/// Returns the new single-use recovery codes, they are shown only once
+#[command]
+#[specta]
+pub async fn regenerate_recovery_codes(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    code: String,
+) -> Result<Vec<String>, String> {
+    let mut writable_state = state.write().await;
+    let two_factor = &mut writable_state.settings.security.two_factor_authentication;
+
+    if !two_factor.enabled {
+        return Err("Two factor authentication is not enabled".to_owned());
+    }
+
+    let data_key = read_data_key().await?;
+    if !verify_totp_code(two_factor, data_key.as_slice(), code.as_str()).await? {
+        return Err("Invalid two factor authentication code".to_owned());
+    }
+    drop(data_key);
+
+    let recovery_codes = generate_recovery_codes();
+    two_factor.recovery_codes = hash_recovery_codes(&recovery_codes);
+    drop(writable_state);
+
+    save(app.clone(), state.clone()).await?;
+    emit_state_changed(&app, &state, AppStateDeepKeys::Settings).await;
+
+    Ok(recovery_codes)
+}
+
+/// Disable two factor authentication removing the secret and the recovery codes
+///
+/// # Arguments
+///
+/// - `state` - The application state
+/// - `password` - The password
+/// - `code` - A code generated by the authenticator application or a recovery code
+///
+/// # Returns
+///
+/// Returns `Ok(())` if two factor authentication was disabled, otherwise returns `Err(String)`
+#[command]
+#[specta]
+pub async fn disable_totp(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    password: String,
+    code: String,
+) -> Result<(), String> {
+    check_password(app.clone(), state.clone(), password).await?;
+
+    let mut writable_state = state.write().await;
+    let two_factor = &mut writable_state.settings.security.two_factor_authentication;
+
+    if two_factor.enabled {
+        let data_key = read_data_key().await?;
+        check_second_factor(two_factor, data_key.as_slice(), Some(code.as_str())).await?;
+    }
+
+    *two_factor = TwoFactorAuthentication::default();
+    drop(writable_state);
+
+    save(app.clone(), state.clone()).await?;
+    emit_state_changed(&app, &state, AppStateDeepKeys::Settings).await;
+
+    Ok(())
+}
+
+/// Check the second factor required to unlock the state, if two factor authentication is enabled
+///
+/// A valid recovery code is consumed and removed from the state.
+///
+/// # Arguments
+///
+/// - `two_factor` - The two factor authentication settings
+/// - `key` - The key protecting the TOTP secret
+/// - `code` - A code generated by the authenticator application or a recovery code
+///
+/// # Returns
+///
+/// Returns `Ok(true)` if a recovery code was consumed and the state should be saved
+pub async fn check_second_factor(
+    two_factor: &mut TwoFactorAuthentication,
+    key: &[u8],
+    code: Option<&str>,
+) -> Result<bool, String> {
+    if !two_factor.enabled {
+        return Ok(false);
+    }
+
+    let code = code.ok_or("Two factor authentication code required")?;
+
+    if verify_totp_code(two_factor, key, code).await? {
+        return Ok(false);
+    }
+
+    // fallback to the single-use recovery codes
+    let normalized_code = normalize_recovery_code(code);
+    let position = two_factor
+        .recovery_codes
+        .iter()
+        .position(|hash| hash.verify(normalized_code.as_bytes()));
+
+    match position {
+        Some(position) => {
+            debug!(
+                "Recovery code used, {} left",
+                two_factor.recovery_codes.len() - 1
+            );
+            two_factor.recovery_codes.remove(position);
+            Ok(true)
+        }
+        None => {
+            warn!("Invalid two factor authentication code");
+            Err("Invalid two factor authentication code".to_owned())
+        }
+    }
+}
+
+/// Verify a code against the stored TOTP secret
+///
+/// # Arguments
+///
+/// - `two_factor` - The two factor authentication settings
+/// - `key` - The key protecting the TOTP secret
+/// - `code` - The code to verify
+///
+/// # Returns
+///
+/// Whether the code is valid
+async fn verify_totp_code(
+    two_factor: &TwoFactorAuthentication,
+    key: &[u8],
+    code: &str,
+) -> Result<bool, String> {
+    let secret = two_factor
+        .secret
+        .as_ref()
+        .ok_or("Two factor authentication is not enrolled")?
+        .read()
+        .await
+        .decrypt_stored_data(key)?;
+
+    make_totp(secret)?
+        .check_current(code.trim())
+        .map_err(|e| e.to_string())
+}
+
+/// Create the TOTP instance for a secret (RFC 6238, SHA-1, 6 digits, 30 seconds step)
+///
+/// # Arguments
+///
+/// - `secret` - The raw secret
+///
+/// # Returns
+///
+/// The TOTP instance
+pub fn make_totp(secret: Vec<u8>) -> Result<TOTP, String> {
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        secret,
+        Some(TOTP_ISSUER.to_owned()),
+        TOTP_ACCOUNT_NAME.to_owned(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Generate a new set of recovery codes formatted as `XXXX-XXXX-XXXX-XXXX`
+///
+/// # Returns
+///
+/// The recovery codes
+fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::rng();
+
+    (0..RECOVERY_CODES_COUNT)
+        .map(|_| {
+            (0..4)
+                .map(|_| {
+                    (0..4)
+                        .map(|_| {
+                            let index = rng.random_range(0..RECOVERY_CODE_ALPHABET.len());
+                            RECOVERY_CODE_ALPHABET[index] as char
+                        })
+                        .collect::<String>()
+                })
+                .collect::<Vec<String>>()
+                .join("-")
+        })
+        .collect()
+}
+
+/// Hash the recovery codes so that they are never stored in plain text
+///
+/// # Arguments
+///
+/// - `recovery_codes` - The recovery codes
+///
+/// # Returns
+///
+/// The hashed recovery codes
+fn hash_recovery_codes(recovery_codes: &[String]) -> Vec<CryptData> {
+    recovery_codes
+        .iter()
+        .map(|code| {
+            CryptData::new(
+                normalize_recovery_code(code).into_bytes(),
+                CryptDataMode::Hash as u8,
+                None,
+                None,
+            )
+        })
+        .collect()
+}
+
+/// Normalize a recovery code ignoring the separators and the case
+///
+/// # Arguments
+///
+/// - `code` - The recovery code
+///
+/// # Returns
+///
+/// The normalized recovery code
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::key_slot::generate_data_key;
+
+    fn make_two_factor(data_key: &[u8], secret: &[u8]) -> TwoFactorAuthentication {
+        TwoFactorAuthentication {
+            enabled: true,
+            secret: Some(Arc::new(RwLock::new(CryptData::new_with_kdf(
+                secret.to_vec(),
+                CryptDataMode::to_u8(vec![CryptDataMode::Encrypt, CryptDataMode::Encode]),
+                Some(data_key),
+                None,
+                KdfAlgorithm::HkdfSha3_512,
+            )))),
+            recovery_codes: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_second_factor_with_totp() {
+        let data_key = generate_data_key();
+        let secret = Secret::generate_secret().to_bytes().unwrap();
+        let mut two_factor = make_two_factor(&data_key, &secret);
+
+        let code = make_totp(secret).unwrap().generate_current().unwrap();
+        assert!(
+            !check_second_factor(&mut two_factor, &data_key, Some(code.as_str()))
+                .await
+                .unwrap()
+        );
+        assert!(
+            check_second_factor(&mut two_factor, &data_key, Some("000000x"))
+                .await
+                .is_err()
+        );
+        assert!(
+            check_second_factor(&mut two_factor, &data_key, None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recovery_codes_are_single_use() {
+        let data_key = generate_data_key();
+        let secret = Secret::generate_secret().to_bytes().unwrap();
+        let mut two_factor = make_two_factor(&data_key, &secret);
+
+        let recovery_codes = generate_recovery_codes();
+        two_factor.recovery_codes = hash_recovery_codes(&recovery_codes);
+        assert_eq!(two_factor.recovery_codes.len(), RECOVERY_CODES_COUNT);
+
+        let code = recovery_codes[3].to_lowercase();
+        assert!(
+            check_second_factor(&mut two_factor, &data_key, Some(code.as_str()))
+                .await
+                .unwrap()
+        );
+        assert_eq!(two_factor.recovery_codes.len(), RECOVERY_CODES_COUNT - 1);
+        assert!(
+            check_second_factor(&mut two_factor, &data_key, Some(code.as_str()))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_second_factor_disabled() {
+        let mut two_factor = TwoFactorAuthentication::default();
+        assert!(
+            !check_second_factor(&mut two_factor, b"key", None)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_generate_recovery_codes() {
+        let recovery_codes = generate_recovery_codes();
+        assert_eq!(recovery_codes.len(), RECOVERY_CODES_COUNT);
+
+        for code in recovery_codes.iter() {
+            assert_eq!(code.len(), 19);
+            assert_eq!(normalize_recovery_code(code).len(), 16);
+        }
+    }
+
+    #[test]
+    fn test_make_totp_uri() {
+        let secret = Secret::generate_secret().to_bytes().unwrap();
+        let uri = make_totp(secret).unwrap().get_url();
+        assert!(uri.starts_with("otpauth://totp/Storage%20Orchestra:vault?secret="));
+    }
+}
@@ -0,0 +1,66 @@
+use crate::secret_manager::is_vault_unlocked;
+use crate::state::settings::security_commands::check_password;
+use crate::state::state::AppState;
+use crate::state::state_file::state_file_path;
+use crate::state::unlock_attempts::{UnlockAttempts, load_unlock_attempts, update_unlock_attempts};
+use specta::specta;
+use tauri::{AppHandle, State, command};
+use tracing::debug;
+
+/// Get the password checks of the vault
+///
+/// # Arguments
+///
+/// - `app` - The application handle
+///
+/// # Returns
+///
+/// Returns the failed attempts counter, the lockout threshold and the latest attempts
+#[command]
+#[specta]
+pub async fn get_unlock_attempts(app: AppHandle) -> Result<UnlockAttempts, String> {
+    if !is_vault_unlocked().await {
+        return Err("The vault is locked".to_owned());
+    }
+
+    let state_file = state_file_path(&app)?;
+
+    Ok(load_unlock_attempts(&state_file).await)
+}
+
+/// Set the number of consecutive failed attempts after which only the recovery key can unlock the
+/// vault
+///
+/// # Arguments
+///
+/// - `app` - The application handle
+/// - `state` - The application state
+/// - `password` - The password
+/// - `threshold` - The lockout threshold, `None` to never lock the vault out
+///
+/// # Returns
+///
+/// Returns the updated unlock attempts
+#[command]
+#[specta]
+pub async fn set_unlock_lockout_threshold(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    password: String,
+    threshold: Option<u32>,
+) -> Result<UnlockAttempts, String> {
+    if threshold == Some(0) {
+        return Err("The lockout threshold must be at least 1".to_owned());
+    }
+
+    check_password(app.clone(), state, password).await?;
+
+    let state_file = state_file_path(&app)?;
+    let attempts = update_unlock_attempts(&state_file, |attempts| {
+        attempts.lockout_threshold = threshold;
+    })
+    .await?;
+
+    debug!("Lockout threshold set to {:?}", threshold);
+    Ok(attempts)
+}
@@ -1,6 +1,8 @@
 use crate::crypt::CryptData;
+use crate::state::key_slot::KeySlot;
 use crate::state::provider_data::ProviderData;
 use crate::state::settings::Settings;
+use crate::state::state_migration::STATE_SCHEMA_VERSION;
 use crate::utility::debounced_saver::DebouncedSaver;
 use as_inner_serializable::AsInnerSerializable;
 use as_result_enum::AsResultEnum;
@@ -15,7 +17,7 @@ use tokio_util::sync::CancellationToken;
 
 pub static STATE_FILE: &str = "state.json";
 
-#[derive(Debug, Default, Clone, AsInnerSerializable, KeysAsEnum, AsResultEnum)]
+#[derive(Debug, Clone, AsInnerSerializable, KeysAsEnum, AsResultEnum)]
 #[derive_extra(Type)]
 pub struct AppStateDeep {
     /// The debounced saver
@@ -24,14 +26,38 @@ pub struct AppStateDeep {
     /// The cancellation tokens used by the frontend to override automatic actions
     #[serde(skip)]
     pub cancellation_tokens: CancellationTokens,
+    /// The version of the state schema, used to migrate the states written by older releases
+    pub schema_version: u32,
+    /// The number of times the state was saved, mirrored outside of the vault directory so that
+    /// an older copy of the state file cannot be restored unnoticed
+    #[serde(default)]
+    pub generation: u64,
     /// The password to access the secure storage
     pub password: Arc<RwLock<CryptData>>,
+    /// The data key wrapped by each of the available unlock methods
+    #[serde(default)]
+    pub key_slots: Vec<KeySlot>,
     /// The list of providers
     pub providers: Vec<ProviderData>,
     /// The settings of the application
     pub settings: Settings,
 }
 
+impl Default for AppStateDeep {
+    fn default() -> Self {
+        Self {
+            debounced_saver: DebouncedSaver::default(),
+            cancellation_tokens: CancellationTokens::default(),
+            schema_version: STATE_SCHEMA_VERSION,
+            generation: 0,
+            password: Arc::default(),
+            key_slots: Vec::new(),
+            providers: Vec::new(),
+            settings: Settings::default(),
+        }
+    }
+}
+
 pub type AppState = RwLock<AppStateDeep>;
 
 #[derive(Debug, Clone, Educe)]
@@ -0,0 +1,324 @@
+use crate::state::state_file::{StateFile, decode_state_file};
+use crate::state::state_migration::parse_state;
+use crate::utility::atomic_file::write_atomically;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// The number of previous good states kept as backups of the state file
+pub static STATE_BACKUP_COUNT: usize = 3;
+
+/// Get the path of a backup of the state file
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+/// * `index` - The index of the backup, 1 is the newest
+///
+/// # Returns
+///
+/// The path of the backup
+pub fn backup_path(state_file: &Path, index: usize) -> PathBuf {
+    let mut file_name = state_file.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".bak.{}", index));
+
+    state_file.with_file_name(file_name)
+}
+
+/// Get the paths the state can be loaded from, the state file first and then its backups from the
+/// newest to the oldest
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// The existing paths the state can be loaded from
+pub fn state_file_candidates(state_file: &Path) -> Vec<PathBuf> {
+    std::iter::once(state_file.to_path_buf())
+        .chain((1..=STATE_BACKUP_COUNT).map(|index| backup_path(state_file, index)))
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// Check whether a state exists, either in the state file or in one of its backups
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// Whether a state exists
+pub fn has_state_file(state_file: &Path) -> bool {
+    !state_file_candidates(state_file).is_empty()
+}
+
+/// Write the state file, keeping the current state file as the newest backup if it is readable
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+/// * `content` - The new content of the state file
+///
+/// # Returns
+///
+/// Nothing
+pub async fn write_state_file(state_file: &Path, content: &[u8]) -> Result<(), String> {
+    rotate_backups(state_file).await?;
+
+    write_atomically(state_file, content).await
+}
+
+/// Load the state from the state file, falling back to the newest backup if it cannot be read
+///
+/// Only the files that cannot be decoded or parsed are skipped, a file failing to open (e.g. a
+/// wrong password or an invalid signature) is never replaced by a backup as the backups may still
+/// be protected by a previous password or a previous second factor.
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+/// * `open` - The function opening a decoded state file, it should fail if the state cannot be
+///   unlocked or its signature is invalid
+///
+/// # Returns
+///
+/// The opened state and the index of the backup it was restored from, 0 if it was loaded from the
+/// state file
+pub async fn load_state_file<T, F, Fut>(state_file: &Path, open: F) -> Result<(T, usize), String>
+where
+    F: Fn(StateFile) -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let mut decoding_error = None;
+
+    for backup in 0..=STATE_BACKUP_COUNT {
+        let path = match backup {
+            0 => state_file.to_path_buf(),
+            index => backup_path(state_file, index),
+        };
+        if !path.exists() {
+            continue;
+        }
+
+        let decoded = tokio::fs::read(&path)
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|content| decode_state_file(&content))
+            .and_then(|decoded| check_parsable(&decoded).map(|_| decoded));
+
+        let decoded = match decoded {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                warn!("Cannot read the state from {}: {}", path.display(), e);
+                decoding_error.get_or_insert(e);
+                continue;
+            }
+        };
+
+        if backup > 0 {
+            warn!("Restoring the state from the backup {}", path.display());
+        }
+
+        let opened = open(decoded).await;
+        if let Err(e) = &opened {
+            debug!("Cannot open the state from {}: {}", path.display(), e);
+        }
+
+        return opened.map(|state| (state, backup));
+    }
+
+    Err(decoding_error.unwrap_or("State file not found".to_owned()))
+}
+
+/// Check that a decoded state file holds a state that can be parsed
+///
+/// # Arguments
+///
+/// * `state_file` - The decoded state file
+///
+/// # Returns
+///
+/// Nothing if the state can be parsed, the encrypted states are only parsed once unlocked
+fn check_parsable(state_file: &StateFile) -> Result<(), String> {
+    let state = match state_file {
+        StateFile::Plain(state) => state.clone(),
+        StateFile::Container(container) if container.is_encrypted() => return Ok(()),
+        StateFile::Container(container) => container.open(None)?,
+    };
+
+    parse_state(state).map(|_| ())
+}
+
+/// Shift the backups by one, keeping the current state file as the newest backup
+///
+/// The current state file is kept only if it can be decoded and parsed so that a damaged state
+/// never replaces a good backup.
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// Nothing
+async fn rotate_backups(state_file: &Path) -> Result<(), String> {
+    let Ok(content) = tokio::fs::read(state_file).await else {
+        return Ok(());
+    };
+
+    if let Err(e) = decode_state_file(&content).and_then(|decoded| check_parsable(&decoded)) {
+        warn!("The state file is damaged, skipping its backup: {}", e);
+        return Ok(());
+    }
+
+    for index in (1..STATE_BACKUP_COUNT).rev() {
+        let from = backup_path(state_file, index);
+        if from.exists() {
+            tokio::fs::rename(&from, backup_path(state_file, index + 1))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    write_atomically(&backup_path(state_file, 1), &content).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::provider_data::ProviderData;
+    use crate::state::state::AppStateDeep;
+    use rand::RngCore;
+
+    fn make_state_file() -> PathBuf {
+        let directory = std::env::temp_dir().join(format!(
+            "storage-orchestra-state-backup-{}",
+            rand::rng().next_u64()
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        directory.join("state.json")
+    }
+
+    fn make_content(owner: &str) -> Vec<u8> {
+        let state = AppStateDeep {
+            providers: vec![ProviderData {
+                owner: owner.to_owned(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        serde_json::to_vec(&state).unwrap()
+    }
+
+    fn read_backup(state_file: &Path, index: usize) -> String {
+        let content = std::fs::read(backup_path(state_file, index)).unwrap();
+        read_marker(decode_state_file(&content).unwrap()).unwrap()
+    }
+
+    fn read_marker(state_file: StateFile) -> Result<String, String> {
+        match state_file {
+            StateFile::Plain(state) => {
+                Ok(state["providers"][0]["owner"].as_str().unwrap().to_owned())
+            }
+            StateFile::Container(_) => Err("Expected a plain state file".to_owned()),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rotate_backups() {
+        let state_file = make_state_file();
+
+        for index in 0..=STATE_BACKUP_COUNT + 1 {
+            write_state_file(&state_file, &make_content(&index.to_string()))
+                .await
+                .unwrap();
+        }
+
+        // the newest backup holds the previous state, the oldest ones are dropped
+        let (marker, backup) = load_state_file(&state_file, async |f| read_marker(f))
+            .await
+            .unwrap();
+        assert_eq!(marker, (STATE_BACKUP_COUNT + 1).to_string());
+        assert_eq!(backup, 0);
+        assert_eq!(read_backup(&state_file, 1), STATE_BACKUP_COUNT.to_string());
+        assert!(!backup_path(&state_file, STATE_BACKUP_COUNT + 1).exists());
+
+        // the index of the backup the state is restored from is reported
+        std::fs::write(&state_file, b"{\"password\":").unwrap();
+        std::fs::write(backup_path(&state_file, 1), b"").unwrap();
+        let (marker, backup) = load_state_file(&state_file, async |f| read_marker(f))
+            .await
+            .unwrap();
+        assert_eq!(marker, (STATE_BACKUP_COUNT - 1).to_string());
+        assert_eq!(backup, 2);
+
+        std::fs::remove_dir_all(state_file.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_load_state_file_fallback() {
+        let state_file = make_state_file();
+        write_state_file(&state_file, &make_content("first"))
+            .await
+            .unwrap();
+        write_state_file(&state_file, &make_content("second"))
+            .await
+            .unwrap();
+
+        // simulate a write interrupted halfway
+        std::fs::write(&state_file, b"{\"password\":").unwrap();
+        let (marker, backup) = load_state_file(&state_file, async |f| read_marker(f))
+            .await
+            .unwrap();
+        assert_eq!(marker, "first");
+        assert_eq!(backup, 1);
+
+        // a damaged state file never replaces a good backup
+        write_state_file(&state_file, &make_content("third"))
+            .await
+            .unwrap();
+        assert_eq!(read_backup(&state_file, 1), "first");
+
+        // a state that is not readable as a state is skipped as well
+        std::fs::write(&state_file, b"{\"providers\":1}").unwrap();
+        let (marker, backup) = load_state_file(&state_file, async |f| read_marker(f))
+            .await
+            .unwrap();
+        assert_eq!(marker, "first");
+        assert_eq!(backup, 1);
+
+        // a state failing to open is never replaced by a backup, the backups may still be
+        // protected by a previous password
+        write_state_file(&state_file, &make_content("fourth"))
+            .await
+            .unwrap();
+        assert_eq!(read_backup(&state_file, 1), "first");
+        let error = load_state_file(&state_file, async |f| {
+            read_marker(f).and_then(|marker| match marker.as_str() {
+                "fourth" => Err("Invalid password".to_owned()),
+                _ => Ok(marker),
+            })
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(error, "Invalid password");
+
+        std::fs::remove_dir_all(state_file.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_missing_state_file() {
+        let state_file = make_state_file();
+        assert!(!has_state_file(&state_file));
+        assert!(
+            load_state_file(&state_file, async |f| read_marker(f))
+                .await
+                .is_err()
+        );
+
+        std::fs::remove_dir_all(state_file.parent().unwrap()).unwrap();
+    }
+}
@@ -1,31 +1,57 @@
-use crate::crypt;
-use crate::crypt::{CryptData, CryptDataMode, verify_hmac};
+use crate::crypt::{CryptData, CryptDataMode};
+use crate::secret_manager::{
+    is_vault_unlocked, lock_secret_store, read_data_key, record_activity,
+    require_recent_authentication, unlock_secret_store,
+};
+use crate::state::account_registry::{
+    ensure_stable_ids, reset_orphaned_default_page, validate_accounts,
+};
+use crate::state::key_slot::{KeySlot, KeySlotKind, find_key_slot, generate_data_key};
+use crate::state::password_rotation::{
+    PasswordRotation, read_rotation_journal, remove_rotation_journal,
+};
+use crate::state::provider_data::ProviderData;
+use crate::state::settings::recovery_key_commands::parse_recovery_key;
+use crate::state::settings::security_commands::{
+    PasswordUpdateEvent, migrate_to_data_key, replace_password, upgrade_legacy_cryptdata,
+};
+use crate::state::settings::two_factor_commands::check_second_factor;
 use crate::state::settings::{Settings, SettingsResult};
-use crate::state::state::{
-    AppState, AppStateDeep, AppStateDeepKeys, AppStateDeepResult, STATE_FILE,
+use crate::state::state::{AppState, AppStateDeep, AppStateDeepKeys, AppStateDeepResult};
+use crate::state::state_backup::{has_state_file, load_state_file};
+use crate::state::state_events::{emit_state_changed, emit_vault_locked};
+use crate::state::state_file::{StateFile, encode_state_file, state_file_path};
+use crate::state::state_file_watcher::{
+    acquire_state_file, open_unlocked_state_file, release_state_file, replace_state,
+    write_opened_state_file,
+};
+use crate::state::state_generation::{
+    read_state_generation, record_state_generation, remove_state_generation, state_generations_path,
+};
+use crate::state::state_migration::parse_state;
+use crate::state::state_patch::{StatePatchOperation, apply_state_patch};
+use crate::state::state_signature::{sign_state, verify_state_signature};
+use crate::state::unlock_attempts::{
+    guard_second_factor_attempt, guard_unlock_attempt, reset_unlock_attempts,
 };
-use once_cell::sync::OnceCell;
+use serde_json::{Value, json};
 use specta::specta;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tauri::path::BaseDirectory;
-use tauri::{AppHandle, Manager, State, command};
-use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, State, command};
 use tokio::sync::RwLock;
-use tracing::debug;
-
-/// The password for the application secure storage.
-///
-/// This is currently stored in plain text in memory.
-/// TODO: Implement a secret manager to store the password securely while the application is running.
-pub static PASSWORD: OnceCell<RwLock<String>> = OnceCell::new();
+use tracing::{debug, warn};
 
 /// Sets the password for the application secure storage.
 ///
 /// # Arguments
 ///
 /// * `password` - The password to set.
+/// * `totp_code` - The two factor authentication code (or a recovery code), required only if two
+///   factor authentication is enabled.
+/// * `allow_rollback` - Whether to open a state file older than the last one saved on this
+///   device or one of its backups, the unlock fails with `STATE_ROLLBACK_ERROR` otherwise.
 ///
 /// # Returns
 ///
@@ -36,50 +62,182 @@ pub async fn init_state(
     app: AppHandle,
     state: State<'_, AppState>,
     password: String,
+    totp_code: Option<String>,
+    allow_rollback: bool,
 ) -> Result<(), String> {
-    let resolver = app.path();
-    let state_file = resolver
-        .resolve(STATE_FILE, BaseDirectory::AppLocalData)
-        .map_err(|e| e.to_string())?;
+    if is_vault_unlocked().await {
+        return Err("The vault is already unlocked".to_owned());
+    }
+
+    let state_file = state_file_path(&app)?;
+
+    // a password rotation interrupted before the rotated state reached the disk
+    let rotation_journal = read_rotation_journal(&state_file).await;
+    let rotation_pending = rotation_journal.is_some();
 
     // check if the state file exists
-    if state_file.exists() {
-        let stored_state = check_password(password.clone(), state_file).await?;
+    let mut consumed_recovery_code = false;
+    let mut backup = 0;
+    let mut schema_migrated = false;
+    let mut rolled_back = false;
+    let data_key = if has_state_file(&state_file) {
+        let (mut stored_state, data_key, migrated_from_older_schema, backup_index, consumed) =
+            open_state_with_password(
+                &state_file,
+                password.as_str(),
+                totp_code.as_deref(),
+                rotation_journal,
+            )
+            .await?;
+        backup = backup_index;
+        schema_migrated = migrated_from_older_schema;
+        consumed_recovery_code = consumed;
+
+        rolled_back =
+            check_state_rollback(&app, &state_file, &mut stored_state, backup, allow_rollback)
+                .await?;
+
+        // a vault is only ever opened by a single instance of the application
+        acquire_state_file(&state_file).await?;
 
         // set the password
         let mut writable_state = state.write().await;
         *writable_state = stored_state;
 
-        // update the password in the state to ensure the password gets saved to disk
-        writable_state.password = Arc::new(RwLock::new(CryptData::new(
-            password.as_str().as_bytes().to_vec(),
-            CryptDataMode::to_u8(vec![CryptDataMode::Hash]),
-            None,
-            None,
-        )));
-
         // immediately drop the lock
         drop(writable_state);
+
+        data_key
     } else {
-        create_state_file(state_file, password.clone()).await?;
+        // a new state file starts over from the first generation
+        remove_state_generation(&state_generations_path(&app).await?, &state_file).await?;
+        acquire_state_file(&state_file).await?;
+
+        let data_key = generate_data_key();
+        let created_state =
+            create_state_file(state_file.clone(), password.clone(), &data_key).await?;
         let mut writable_state = state.write().await;
 
-        // update the password in the state to ensure the password gets saved to disk
-        writable_state.password = Arc::new(RwLock::new(CryptData::new(
-            password.as_str().as_bytes().to_vec(),
-            CryptDataMode::to_u8(vec![CryptDataMode::Hash]),
-            None,
-            None,
-        )));
+        // update the password and the key slots in the state to ensure they get saved to disk
+        writable_state.password = created_state.password;
+        writable_state.key_slots = created_state.key_slots;
 
         // immediately drop the lock
         drop(writable_state);
+
+        Some(data_key)
+    };
+
+    // states created before the key hierarchy encrypt every secret with the password, move them
+    // to a fresh data key wrapped by the password
+    let (data_key, migrated) = match data_key {
+        Some(data_key) => (data_key, false),
+        None => (
+            migrate_to_data_key(Arc::new(state.clone()), password.clone()).await?,
+            true,
+        ),
+    };
+
+    // keep the data key in memory until the vault is locked
+    unlock_secret_store(data_key).await;
+
+    // transparently upgrade the data protected by the legacy key derivation now that the password
+    // is known to be valid
+    let upgraded = upgrade_legacy_cryptdata(Arc::new(state.clone()), password).await?;
+
+    // saving re-signs the states migrated from an older schema
+    if migrated
+        || upgraded
+        || consumed_recovery_code
+        || backup > 0
+        || schema_migrated
+        || rotation_pending
+        || rolled_back
+    {
+        save(app, state.clone()).await?;
+    }
+
+    // the rotation is either resumed or rolled back once the state is on disk
+    if rotation_pending {
+        flush_state(state).await?;
+        remove_rotation_journal(&state_file).await?;
+    }
+
+    Ok(())
+}
+
+/// Unlocks the application secure storage using the recovery key and sets a new password.
+///
+/// # Arguments
+///
+/// * `recovery_key` - The recovery key as a word-list mnemonic.
+/// * `new_password` - The new password.
+/// * `totp_code` - The two factor authentication code (or a recovery code), required only if two
+///   factor authentication is enabled.
+/// * `allow_rollback` - Whether to recover a state file older than the last one saved on this
+///   device or one of its backups, the recovery fails with `STATE_ROLLBACK_ERROR` otherwise.
+/// * `ev` - The event channel.
+///
+/// # Returns
+///
+/// Nothing.
+#[command]
+#[specta]
+pub async fn recover_state(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    recovery_key: String,
+    new_password: String,
+    totp_code: Option<String>,
+    allow_rollback: bool,
+    ev: Channel<PasswordUpdateEvent>,
+) -> Result<(), String> {
+    if is_vault_unlocked().await {
+        return Err("The vault is already unlocked".to_owned());
     }
 
-    // store the password
-    PASSWORD
-        .set(RwLock::new(password))
-        .map_err(|e| "Password already defined")?;
+    let state_file = state_file_path(&app)?;
+    if !has_state_file(&state_file) {
+        return Err("State file not found".to_owned());
+    }
+
+    let secret = parse_recovery_key(recovery_key.as_str())?;
+    let ((mut stored_state, data_key), backup) = load_state_file(&state_file, |content| {
+        check_recovery_key(secret.as_slice(), content)
+    })
+    .await?;
+    check_state_rollback(&app, &state_file, &mut stored_state, backup, allow_rollback).await?;
+    acquire_state_file(&state_file).await?;
+
+    guard_second_factor_attempt(&state_file, totp_code.is_some(), || {
+        check_second_factor(
+            &mut stored_state.settings.security.two_factor_authentication,
+            data_key.as_slice(),
+            totp_code.as_deref(),
+        )
+    })
+    .await?;
+
+    replace_password(&mut stored_state, data_key.as_slice(), &new_password, &ev)?;
+
+    let mut writable_state = state.write().await;
+    *writable_state = stored_state;
+    drop(writable_state);
+
+    unlock_secret_store(data_key).await;
+
+    debug!("State recovered using the recovery key");
+    save(app, state.clone()).await?;
+
+    // an interrupted rotation must not replace the password set by the recovery
+    flush_state(state).await?;
+    remove_rotation_journal(&state_file).await?;
+
+    // the recovery key lifts the delays and the lockout imposed by the failed password checks
+    reset_unlock_attempts(&state_file).await?;
+
+    ev.send(PasswordUpdateEvent::Completed)
+        .map_err(|e| e.to_string())?;
 
     Ok(())
 }
@@ -92,35 +250,103 @@ pub async fn init_state(
 #[command]
 #[specta]
 pub async fn is_authenticated() -> bool {
-    PASSWORD.get().is_some()
+    is_vault_unlocked().await
 }
 
-/// Gets the password for the application secure storage if already loaded in memory.
+/// Records that the user is active, this postpones the idle lock of the vault.
 ///
-/// # Why
+/// # Returns
+///
+/// Nothing.
+#[command]
+#[specta]
+pub async fn report_activity() {
+    record_activity().await;
+}
+
+/// Locks the vault wiping the secrets and the decrypted state from memory.
 ///
-/// This function is useful when the frontend needs to initialize the State class or some other
-/// singleton but the password was not provided at application startup.
-/// This is most commonly a development issue (commonly derived from hot-reloading) but implementing
-/// a safe reboot of singleton in case the password is missing can be useful to improve the user
-/// experience.
+/// # Arguments
 ///
-/// **Note**: This function DOES NOT expose the password to other applications or the networks.
+/// * `state` - The application state.
 ///
 /// # Returns
 ///
-/// The password.
+/// Nothing.
 #[command]
 #[specta]
-pub async fn get_password() -> Result<String, String> {
-    let psw = PASSWORD
-        .get()
-        .ok_or("Password not set".to_owned())?
-        .read()
-        .await
-        .clone();
+pub async fn lock_vault(app: AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+    if !is_vault_unlocked().await {
+        return Err("The vault is already locked".to_owned());
+    }
 
-    Ok(psw)
+    clear_state(&app, state).await;
+
+    debug!("Vault locked");
+    Ok(())
+}
+
+/// Wipes the secrets and the decrypted state from memory.
+///
+/// # Arguments
+///
+/// * `app` - The application handle.
+/// * `state` - The application state.
+///
+/// # Returns
+///
+/// Nothing.
+pub async fn clear_state(app: &AppHandle, state: State<'_, AppState>) {
+    lock_secret_store().await;
+
+    // drop the decrypted state, the pending saves are already encoded and are not affected
+    let mut writable_state = state.write().await;
+    *writable_state = AppStateDeep {
+        debounced_saver: writable_state.debounced_saver.clone(),
+        cancellation_tokens: writable_state.cancellation_tokens.clone(),
+        ..Default::default()
+    };
+    drop(writable_state);
+
+    // the pending saves are written before another instance can open the vault
+    if let Err(e) = flush_state(state).await {
+        warn!("Cannot save the state before locking the vault: {}", e);
+    }
+    release_state_file().await;
+
+    emit_vault_locked(app);
+}
+
+/// Unlocks a previously locked vault.
+///
+/// # Arguments
+///
+/// * `password` - The password.
+/// * `totp_code` - The two factor authentication code (or a recovery code), required only if two
+///   factor authentication is enabled.
+/// * `allow_rollback` - Whether to open a state file older than the last one saved on this
+///   device.
+///
+/// # Returns
+///
+/// Nothing.
+#[command]
+#[specta]
+pub async fn unlock_vault(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    password: String,
+    totp_code: Option<String>,
+    allow_rollback: bool,
+) -> Result<(), String> {
+    if !has_state_file(&state_file_path(&app)?) {
+        return Err("State file not found".to_owned());
+    }
+
+    init_state(app, state, password, totp_code, allow_rollback).await?;
+
+    debug!("Vault unlocked");
+    Ok(())
 }
 
 /// Gets the settings of the application.
@@ -170,7 +396,9 @@ pub async fn update_settings(
             settings.general_behaviour = data;
         }
         SettingsResult::security(data) => {
-            settings.security = data;
+            // the two factor authentication and the signature are managed by dedicated commands
+            settings.security.encryption = data.encryption;
+            settings.security.idle_lock_minutes = data.idle_lock_minutes;
         }
     }
 
@@ -196,14 +424,21 @@ pub async fn get_from_state(
     let readable_state = state.read().await;
 
     match key {
+        AppStateDeepKeys::SchemaVersion => Ok(AppStateDeepResult::schema_version(
+            readable_state.schema_version,
+        )),
+        AppStateDeepKeys::Generation => {
+            Ok(AppStateDeepResult::generation(readable_state.generation))
+        }
         AppStateDeepKeys::Password => Err("Cannot get data from password".to_owned()),
+        AppStateDeepKeys::KeySlots => Err("Cannot get data from key slots".to_owned()),
         AppStateDeepKeys::DebouncedSaver => Err("Cannot get data from debounced saver".to_owned()),
         AppStateDeepKeys::CancellationTokens => {
             Err("Cannot get data from cancellation tokens".to_owned())
         }
-        AppStateDeepKeys::Providers => Ok(AppStateDeepResult::providers(
-            readable_state.providers.clone(),
-        )),
+        AppStateDeepKeys::Providers => {
+            Err("Cannot get data from providers, use 'list_accounts' instead".to_owned())
+        }
         AppStateDeepKeys::Settings => Ok(AppStateDeepResult::settings(
             readable_state.settings.clone(),
         )),
@@ -227,11 +462,23 @@ pub async fn remove_from_state(
     state: State<'_, AppState>,
     key: AppStateDeepKeys,
 ) -> Result<(), String> {
+    // checked before locking the state, the secret store is always locked before the state
+    let authentication = require_recent_authentication().await;
+
     let mut writable_state = state.write().await;
     match key {
+        AppStateDeepKeys::SchemaVersion => {
+            return Err("Cannot remove schema version from the state".to_owned());
+        }
+        AppStateDeepKeys::Generation => {
+            return Err("Cannot remove generation from the state".to_owned());
+        }
         AppStateDeepKeys::Password => {
             return Err("Cannot remove password from the state".to_owned());
         }
+        AppStateDeepKeys::KeySlots => {
+            return Err("Cannot remove key slots from the state".to_owned());
+        }
         AppStateDeepKeys::DebouncedSaver => {
             return Err("Cannot remove debounced saver from the state".to_owned());
         }
@@ -239,16 +486,35 @@ pub async fn remove_from_state(
             return Err("Cannot remove cancellation tokens from the state".to_owned());
         }
         AppStateDeepKeys::Providers => {
+            // removing the accounts is a sensitive operation
+            if !writable_state.providers.is_empty() {
+                authentication?;
+            }
             writable_state.providers = Vec::new();
         }
         AppStateDeepKeys::Settings => {
-            writable_state.settings = Settings::default();
+            // the two factor authentication and the signature are managed by dedicated commands
+            let mut settings = Settings::default();
+            settings.security.two_factor_authentication = writable_state
+                .settings
+                .security
+                .two_factor_authentication
+                .clone();
+            settings.security.signature = writable_state.settings.security.signature.clone();
+            writable_state.settings = settings;
         }
     }
 
+    let default_page_reset = reset_orphaned_default_page(&mut writable_state);
     drop(writable_state);
 
-    save(app, state).await
+    save(app.clone(), state.clone()).await?;
+    emit_state_changed(&app, &state, key).await;
+    if default_page_reset {
+        emit_state_changed(&app, &state, AppStateDeepKeys::Settings).await;
+    }
+
+    Ok(())
 }
 
 /// Inserts data in the state.
@@ -268,23 +534,150 @@ pub async fn insert_in_state(
     state: State<'_, AppState>,
     value: AppStateDeepResult,
 ) -> Result<(), String> {
-    match value {
+    // checked before locking the state, the secret store is always locked before the state
+    let authentication = require_recent_authentication().await;
+
+    let mut emit_settings = false;
+    let key = match value {
+        AppStateDeepResult::schema_version(_) => {
+            return Err("Cannot insert data in schema version".to_owned());
+        }
+        AppStateDeepResult::generation(_) => {
+            return Err("Cannot insert data in generation".to_owned());
+        }
         AppStateDeepResult::password(_) => {
             return Err("Cannot insert data in password, use 'init_state' instead".to_owned());
         }
+        AppStateDeepResult::key_slots(_) => {
+            return Err(
+                "Cannot insert data in key slots, use 'update_password' instead".to_owned(),
+            );
+        }
         AppStateDeepResult::providers(data) => {
+            validate_accounts(&data)?;
             let mut writable_state = state.write().await;
+            ensure_stable_ids(&writable_state.providers, &data)?;
+
+            // removing accounts is a sensitive operation
+            if removes_accounts(&writable_state.providers, &data) {
+                authentication?;
+            }
             writable_state.providers = data;
+
+            // the default page follows the account it shows
+            emit_settings = reset_orphaned_default_page(&mut writable_state);
             drop(writable_state);
+
+            AppStateDeepKeys::Providers
         }
-        AppStateDeepResult::settings(data) => {
+        AppStateDeepResult::settings(mut data) => {
             let mut writable_state = state.write().await;
+
+            // the two factor authentication and the signature are managed by dedicated commands
+            data.security.two_factor_authentication = writable_state
+                .settings
+                .security
+                .two_factor_authentication
+                .clone();
+            data.security.signature = writable_state.settings.security.signature.clone();
             writable_state.settings = data;
             drop(writable_state);
+
+            AppStateDeepKeys::Settings
         }
+    };
+
+    save(app.clone(), state.clone()).await?;
+    emit_state_changed(&app, &state, key).await;
+    if emit_settings {
+        emit_state_changed(&app, &state, AppStateDeepKeys::Settings).await;
     }
 
-    save(app, state).await
+    Ok(())
+}
+
+/// Applies a patch to the state.
+///
+/// The operations are applied in order under the write lock, either all of them are applied or
+/// none is.
+///
+/// # Arguments
+///
+/// * `state` - The state to patch.
+/// * `operations` - The patch operations.
+///
+/// # Returns
+///
+/// Nothing.
+#[command]
+#[specta]
+pub async fn patch_state(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    operations: Vec<StatePatchOperation>,
+) -> Result<(), String> {
+    // checked before locking the state, the secret store is always locked before the state
+    let authentication = require_recent_authentication().await;
+
+    let mut writable_state = state.write().await;
+
+    let mut patched_state = json!({
+        "providers": serde_json::to_value(&writable_state.providers).map_err(|e| e.to_string())?,
+        "settings": serde_json::to_value(&writable_state.settings).map_err(|e| e.to_string())?,
+    });
+    apply_state_patch(&mut patched_state, &operations)?;
+
+    // the patched values must still match the typed state
+    let providers = serde_json::from_value::<Vec<ProviderData>>(patched_state["providers"].take())
+        .map_err(|e| e.to_string())?;
+    let settings = serde_json::from_value::<Settings>(patched_state["settings"].take())
+        .map_err(|e| e.to_string())?;
+    validate_accounts(&providers)?;
+    ensure_stable_ids(&writable_state.providers, &providers)?;
+
+    // removing accounts is a sensitive operation
+    if removes_accounts(&writable_state.providers, &providers) {
+        authentication?;
+    }
+
+    writable_state.providers = providers;
+    writable_state.settings = settings;
+    let default_page_reset = reset_orphaned_default_page(&mut writable_state);
+    drop(writable_state);
+
+    save(app.clone(), state.clone()).await?;
+
+    let is_patched = |key: &str| {
+        operations
+            .iter()
+            .any(|operation| operation.path().split('.').next() == Some(key))
+    };
+    if is_patched("providers") {
+        emit_state_changed(&app, &state, AppStateDeepKeys::Providers).await;
+    }
+    if is_patched("settings") || default_page_reset {
+        emit_state_changed(&app, &state, AppStateDeepKeys::Settings).await;
+    }
+
+    Ok(())
+}
+
+/// Checks whether replacing the storage providers removes some accounts.
+///
+/// # Arguments
+///
+/// * `providers` - The current storage providers.
+/// * `updated` - The storage providers replacing them.
+///
+/// # Returns
+///
+/// Whether an account of the current storage providers is missing from the updated ones.
+fn removes_accounts(providers: &[ProviderData], updated: &[ProviderData]) -> bool {
+    providers.iter().any(|provider_data| {
+        !updated
+            .iter()
+            .any(|updated_data| updated_data.id == provider_data.id)
+    })
 }
 
 /// Saves the state to disk.
@@ -298,40 +691,109 @@ pub async fn insert_in_state(
 ///
 /// Nothing.
 pub async fn save(app: AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+    // each save is a new generation so that an older copy of the state file can be detected
+    let mut writable_state = state.write().await;
+    writable_state.generation += 1;
+    let generation = writable_state.generation;
+    drop(writable_state);
+
     update_state_signature(state.clone()).await?;
     let readable_state = state.read().await;
 
+    // seal the signed state if the state file encryption is enabled
+    let json = serde_json::to_string(&*readable_state).map_err(|e| e.to_string())?;
+    let data_key = read_data_key().await?;
+    let content = encode_state_file(json, &readable_state, data_key.as_slice())?;
+    drop(data_key);
+
+    // resolved now so that a pending save never lands in another vault after a switch
+    let state_file = state_file_path(&app)?;
+    let generations_file = state_generations_path(&app).await?;
+
     readable_state
         .debounced_saver
-        .save(
-            serde_json::to_string(&*readable_state).map_err(|e| e.to_string())?,
-            async move |content: String| -> Result<(), String> {
-                let resolver = app.path();
-                let state_file = resolver
-                    .resolve(STATE_FILE, BaseDirectory::AppLocalData)
-                    .map_err(|e| e.to_string())?;
-
-                let mut file = File::options()
-                    .write(true)
-                    .truncate(true)
-                    .create(true)
-                    .open(state_file)
-                    .await
-                    .map_err(|e| e.to_string())?;
-
-                file.write_all(content.as_bytes())
-                    .await
-                    .map_err(|e| e.to_string())?;
-                file.flush().await.map_err(|e| e.to_string())?;
-
-                Ok(())
-            },
-        )
+        .save(content, move |content: String| {
+            let state_file = state_file.clone();
+            let generations_file = generations_file.clone();
+            async move {
+                write_opened_state_file(&state_file, content.as_bytes()).await?;
+
+                // the generation is only mirrored once the state is on disk
+                record_state_generation(&generations_file, &state_file, generation).await
+            }
+        })
         .await;
 
     Ok(())
 }
 
+/// Writes the pending state save to disk without waiting for the debounce delay.
+///
+/// This is called when the application exits so that the last changes are never lost.
+///
+/// # Arguments
+///
+/// * `state` - The state to flush.
+///
+/// # Returns
+///
+/// Nothing.
+pub async fn flush_state(state: State<'_, AppState>) -> Result<(), String> {
+    let debounced_saver = state.read().await.debounced_saver.clone();
+    debounced_saver.flush().await
+}
+
+/// Replaces the state in memory with the state file, used when the state file was changed outside
+/// of the application.
+///
+/// The changes waiting to be saved are dropped.
+///
+/// # Arguments
+///
+/// * `app` - The application handle.
+/// * `state` - The application state.
+///
+/// # Returns
+///
+/// Nothing.
+#[command]
+#[specta]
+pub async fn reload_state_file(app: AppHandle, state: State<'_, AppState>) -> Result<(), String> {
+    let state_file = state_file_path(&app)?;
+    let content = tokio::fs::read(&state_file)
+        .await
+        .map_err(|e| e.to_string())?;
+    let stored_state = open_unlocked_state_file(&content).await?;
+
+    let debounced_saver = state.read().await.debounced_saver.clone();
+    debounced_saver.discard().await;
+    replace_state(&app, &state, stored_state).await;
+
+    debug!("State reloaded from the state file");
+    Ok(())
+}
+
+/// Overwrites the state file with the state in memory, used when the state file was changed
+/// outside of the application.
+///
+/// # Arguments
+///
+/// * `app` - The application handle.
+/// * `state` - The application state.
+///
+/// # Returns
+///
+/// Nothing.
+#[command]
+#[specta]
+pub async fn overwrite_state_file(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    save(app, state.clone()).await?;
+    flush_state(state).await
+}
+
 /// Updates the state signature.
 ///
 /// This function is used to update the state signature after a state modification.
@@ -344,19 +806,14 @@ pub async fn save(app: AppHandle, state: State<'_, AppState>) -> Result<(), Stri
 ///
 /// Nothing.
 async fn update_state_signature(state: State<'_, AppState>) -> Result<(), String> {
-    let mut unsigned_state = state.write().await;
-    // set the signature to a new empty signature, this is needed to compute the signature of the state
-    unsigned_state.settings.security.signature = Arc::new(RwLock::new(CryptData::default()));
-    let json = serde_json::to_string(&*unsigned_state).map_err(|e| e.to_string())?;
-    drop(unsigned_state);
-
-    // compute the signature of the state
-    let signature = CryptData::new(
-        json.into_bytes(),
-        CryptDataMode::to_u8(vec![CryptDataMode::SignatureHash, CryptDataMode::Hmac]),
-        Some(PASSWORD.get().unwrap().read().await.as_bytes()),
-        None,
-    );
+    let readable_state = state.read().await;
+    let unsigned_state = serde_json::to_value(&*readable_state).map_err(|e| e.to_string())?;
+    drop(readable_state);
+
+    // compute the signature of the state, the current signature is not part of the signed content
+    let data_key = read_data_key().await?;
+    let signature = sign_state(&unsigned_state, data_key.as_slice())?;
+    drop(data_key);
 
     let mut writable_state = state.write().await;
     writable_state.settings.security.signature = Arc::new(RwLock::new(signature));
@@ -365,76 +822,283 @@ async fn update_state_signature(state: State<'_, AppState>) -> Result<(), String
     Ok(())
 }
 
-/// Checks the password for the application secure storage.
+/// Checks that an opened state is not older than the last state saved on this device.
 ///
-/// If the password is correct, the state is returned.
+/// An older copy of the state file is validly signed but could bring back revoked tokens or
+/// disabled protections, it is only opened if explicitly allowed.
 ///
 /// # Arguments
 ///
-/// * `psw` - The password to check.
+/// * `app` - The application handle.
 /// * `state_file` - The path to the state file.
+/// * `stored_state` - The opened state.
+/// * `backup` - The index of the backup the state was restored from, 0 for the state file.
+/// * `allow_rollback` - Whether an older state can be opened.
 ///
 /// # Returns
 ///
-/// Nothing.
-async fn check_password(psw: String, state_file: PathBuf) -> Result<AppStateDeep, String> {
-    let state_file = File::options()
-        .read(true)
-        .open(state_file)
+/// Whether an older state was opened, it continues from the newest generation and must be saved.
+async fn check_state_rollback(
+    app: &AppHandle,
+    state_file: &Path,
+    stored_state: &mut AppStateDeep,
+    backup: usize,
+    allow_rollback: bool,
+) -> Result<bool, String> {
+    let recorded_generation =
+        read_state_generation(&state_generations_path(app).await?, state_file).await;
+
+    match recorded_generation.check(stored_state.generation, backup) {
+        Ok(()) => Ok(false),
+        Err(e) if !allow_rollback => Err(e),
+        Err(_) => {
+            debug!("Opening an older state file as requested");
+            stored_state.generation = recorded_generation.current;
+
+            Ok(true)
+        }
+    }
+}
+
+/// Checks the password of a vault without opening it, used before the irreversible operations on
+/// a vault that is not the unlocked one.
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file of the vault.
+/// * `password` - The password of the vault.
+///
+/// # Returns
+///
+/// Nothing if the password opens the state file or one of its backups.
+pub async fn check_vault_password(state_file: &Path, password: &str) -> Result<(), String> {
+    let rotation_journal = read_rotation_journal(state_file).await;
+
+    // the failed checks delay the next unlocks of the vault as well
+    guard_unlock_attempt(state_file, || async {
+        load_state_file(state_file, |content| {
+            check_password_or_resume_rotation(
+                password.to_owned(),
+                content,
+                rotation_journal.clone(),
+            )
+        })
         .await
-        .map_err(|err| err.to_string())?;
-    let stored_state = serde_json::from_reader::<_, AppStateDeep>(state_file.into_std().await)
-        .map_err(|err| err.to_string())?;
-
-    if crypt::verify(
-        psw.as_str().as_bytes(),
-        stored_state
-            .password
-            .read()
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Opens the state file with the password and checks the second factor.
+///
+/// The password and the second factor are counted separately, the second factor is checked once
+/// the password is known to be valid and its wrong codes impose their own delay. Only the first
+/// check without a code, asking whether a code is needed, is not counted.
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file.
+/// * `password` - The password.
+/// * `totp_code` - The two factor authentication code (or a recovery code), if any.
+/// * `rotation_journal` - The interrupted password rotation, if any.
+///
+/// # Returns
+///
+/// The stored state, the data key (`None` if the state predates the key hierarchy), whether the
+/// state was migrated from an older schema, the index of the backup it was restored from (0 for
+/// the state file) and whether a recovery code was consumed.
+async fn open_state_with_password(
+    state_file: &Path,
+    password: &str,
+    totp_code: Option<&str>,
+    rotation_journal: Option<PasswordRotation>,
+) -> Result<(AppStateDeep, Option<Vec<u8>>, bool, usize, bool), String> {
+    // the failed unlocks delay the next ones and count towards the lockout threshold
+    let ((mut stored_state, data_key, migrated), backup) =
+        guard_unlock_attempt(state_file, || async {
+            // a state file that cannot be read is replaced by the newest readable backup
+            load_state_file(state_file, |content| {
+                check_password_or_resume_rotation(
+                    password.to_owned(),
+                    content,
+                    rotation_journal.clone(),
+                )
+            })
             .await
-            .get_data_as_string()
-            .as_str(),
-    ) {
-        verify_state_signature(stored_state.clone(), psw.as_str()).await?;
+        })
+        .await?;
+
+    // the wrong codes impose their own delay, the correct password does not lift it
+    let consumed = guard_second_factor_attempt(state_file, totp_code.is_some(), || {
+        // states created before the key hierarchy encrypt the TOTP secret with the password
+        check_second_factor(
+            &mut stored_state.settings.security.two_factor_authentication,
+            data_key.as_deref().unwrap_or(password.as_bytes()),
+            totp_code,
+        )
+    })
+    .await?;
+
+    Ok((stored_state, data_key, migrated, backup, consumed))
+}
+
+/// Checks the password for the application secure storage.
+///
+/// If the password is correct, the state is returned along with the data key unwrapped from the
+/// password key slot.
+///
+/// # Arguments
+///
+/// * `psw` - The password to check.
+/// * `state_file` - The decoded state file.
+///
+/// # Returns
+///
+/// The stored state, the data key (`None` if the state predates the key hierarchy) and whether
+/// the state was migrated from an older schema.
+async fn check_password(
+    psw: String,
+    state_file: StateFile,
+) -> Result<(AppStateDeep, Option<Vec<u8>>, bool), String> {
+    let (raw_state, data_key) =
+        open_state_file(state_file, KeySlotKind::Password, psw.as_bytes()).await?;
+    let (stored_state, migrated) = parse_state(raw_state.clone())?;
 
-        Ok(stored_state)
+    if stored_state
+        .password
+        .read()
+        .await
+        .verify(psw.as_str().as_bytes())
+    {
+        // states created before the key hierarchy are signed using the password
+        let data_key = match (
+            data_key,
+            find_key_slot(&stored_state.key_slots, KeySlotKind::Password),
+        ) {
+            (Some(data_key), _) => Some(data_key),
+            (None, Some(slot)) => Some(slot.unwrap_key(psw.as_bytes()).await?),
+            (None, None) => None,
+        };
+        let signature_key = data_key.as_deref().unwrap_or(psw.as_bytes());
+
+        verify_state_signature(&raw_state, signature_key)?;
+
+        Ok((stored_state, data_key, migrated))
     } else {
         Err("Invalid password".to_string())
     }
 }
 
-/// Verifies the state signature.
+/// Checks the password for the application secure storage, resuming an interrupted password
+/// rotation if the password is the new password of the rotation.
+///
+/// The rotation is only resumed if the data key it wraps verifies the state signature, a journal
+/// left by another state is never applied.
 ///
 /// # Arguments
 ///
-/// * `state` - The state to verify the signature of.
-/// * `psw` - The password to use to verify the signature.
+/// * `psw` - The password to check.
+/// * `state_file` - The decoded state file.
+/// * `rotation` - The interrupted password rotation, if any.
 ///
 /// # Returns
 ///
-/// Nothing.
-async fn verify_state_signature(mut state: AppStateDeep, psw: &str) -> Result<(), String> {
-    // extract the signature from the state
-    let state_signature = state
-        .settings
-        .security
-        .signature
-        .read()
-        .await
-        .get_data_as_string();
-    debug!("verify_state_signature: {}", state_signature);
+/// The stored state, the data key (`None` if the state predates the key hierarchy) and whether
+/// the state was migrated from an older schema.
+async fn check_password_or_resume_rotation(
+    psw: String,
+    state_file: StateFile,
+    rotation: Option<PasswordRotation>,
+) -> Result<(AppStateDeep, Option<Vec<u8>>, bool), String> {
+    let Some(rotation) = rotation else {
+        return check_password(psw, state_file).await;
+    };
 
-    // reset the signature to a default empty signature
-    state.settings.security.signature = Arc::new(RwLock::new(CryptData::default()));
-    let json = serde_json::to_string(&state).map_err(|e| e.to_string())?;
+    match check_password(psw.clone(), state_file.clone()).await {
+        Ok(opened) => Ok(opened),
+        Err(e) if !rotation.verify(psw.as_str()).await => Err(e),
+        Err(_) => {
+            let data_key = rotation.key_slot.unwrap_key(psw.as_bytes()).await?;
+            let raw_state = match state_file {
+                StateFile::Plain(stored_state) => stored_state,
+                StateFile::Container(container) if container.is_encrypted() => {
+                    container.open(Some(&data_key))?
+                }
+                StateFile::Container(container) => container.open(None)?,
+            };
+            let (mut stored_state, migrated) = parse_state(raw_state.clone())?;
 
-    debug!("verify_state_signature.JSON: {}", json);
+            verify_state_signature(&raw_state, data_key.as_slice())?;
 
-    if !verify_hmac(json.as_bytes(), psw.as_bytes(), state_signature.as_str()) {
-        return Err("Invalid state signature".to_string());
+            debug!("Resuming the interrupted password rotation");
+            rotation.apply(&mut stored_state);
+
+            Ok((stored_state, Some(data_key), migrated))
+        }
     }
+}
 
-    Ok(())
+/// Checks the recovery key for the application secure storage.
+///
+/// If the recovery key is correct, the state is returned along with the data key unwrapped from
+/// the recovery key slot.
+///
+/// # Arguments
+///
+/// * `secret` - The entropy of the recovery key.
+/// * `state_file` - The decoded state file.
+///
+/// # Returns
+///
+/// The stored state and the data key.
+async fn check_recovery_key(
+    secret: &[u8],
+    state_file: StateFile,
+) -> Result<(AppStateDeep, Vec<u8>), String> {
+    let (raw_state, data_key) = open_state_file(state_file, KeySlotKind::Recovery, secret).await?;
+    let (stored_state, _) = parse_state(raw_state.clone())?;
+
+    // the data key is only unwrapped while reading encrypted state files
+    let data_key = match data_key {
+        Some(data_key) => data_key,
+        None => find_key_slot(&stored_state.key_slots, KeySlotKind::Recovery)
+            .ok_or("No recovery key configured")?
+            .unwrap_key(secret)
+            .await
+            .map_err(|_| "Invalid recovery key")?,
+    };
+
+    verify_state_signature(&raw_state, data_key.as_slice())?;
+
+    Ok((stored_state, data_key))
+}
+
+/// Opens a decoded state file.
+///
+/// # Arguments
+///
+/// * `state_file` - The decoded state file.
+/// * `kind` - The unlock method used if the state file is encrypted.
+/// * `secret` - The unlock secret used if the state file is encrypted.
+///
+/// # Returns
+///
+/// The serialized state and the data key if it was unwrapped to open the state file.
+async fn open_state_file(
+    state_file: StateFile,
+    kind: KeySlotKind,
+    secret: &[u8],
+) -> Result<(Value, Option<Vec<u8>>), String> {
+    // encrypted state files require the data key to be unwrapped before anything can be checked
+    match state_file {
+        StateFile::Plain(stored_state) => Ok((stored_state, None)),
+        StateFile::Container(container) if container.is_encrypted() => {
+            let data_key = container.unwrap_data_key(kind, secret).await?;
+            Ok((container.open(Some(&data_key))?, Some(data_key)))
+        }
+        StateFile::Container(container) => Ok((container.open(None)?, None)),
+    }
 }
 
 /// Creates the state file.
@@ -442,20 +1106,17 @@ async fn verify_state_signature(mut state: AppStateDeep, psw: &str) -> Result<()
 /// # Arguments
 ///
 /// * `state_file` - The path to the state file.
-/// * `state` - The state to write to the file.
+/// * `password` - The password protecting the state.
+/// * `data_key` - The data key to wrap with the password.
 ///
 /// # Returns
 ///
-/// Nothing.
-async fn create_state_file(state_file: PathBuf, password: String) -> Result<(), String> {
-    let state_file = File::options()
-        .write(true)
-        .truncate(true)
-        .create(true)
-        .open(state_file)
-        .await
-        .map_err(|e| e.to_string())?;
-
+/// The state written to the file.
+async fn create_state_file(
+    state_file: PathBuf,
+    password: String,
+    data_key: &[u8],
+) -> Result<AppStateDeep, String> {
     let state = AppStateDeep {
         password: Arc::new(RwLock::new(CryptData::new(
             password.as_str().as_bytes().to_vec(),
@@ -463,9 +1124,208 @@ async fn create_state_file(state_file: PathBuf, password: String) -> Result<(),
             None,
             None,
         ))),
+        key_slots: vec![KeySlot::wrap(
+            KeySlotKind::Password,
+            data_key,
+            password.as_bytes(),
+        )],
         ..Default::default()
     };
 
-    serde_json::to_writer(state_file.into_std().await, &state).map_err(|err| err.to_string())?;
-    Ok(())
+    let json = serde_json::to_vec(&state).map_err(|err| err.to_string())?;
+    write_opened_state_file(&state_file, &json).await?;
+
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypt::KdfAlgorithm;
+    use crate::state::provider_data::AccountCredential;
+    use crate::state::settings::security::TwoFactorAuthentication;
+    use crate::state::settings::two_factor_commands::make_totp;
+    use crate::state::state_backup::write_state_file;
+    use crate::state::storage_provider::StorageProvider;
+    use crate::state::unlock_attempts::{load_unlock_attempts, update_unlock_attempts};
+    use rand::RngCore;
+    use totp_rs::Secret;
+
+    #[test]
+    fn test_removes_accounts() {
+        let make_provider_data = |owner: &str| ProviderData {
+            id: owner.to_owned(),
+            owner: owner.to_owned(),
+            provider: StorageProvider::Google,
+            ..Default::default()
+        };
+        let providers = vec![make_provider_data("first"), make_provider_data("second")];
+
+        assert!(!removes_accounts(&providers, &providers));
+        assert!(!removes_accounts(
+            &providers,
+            &[
+                make_provider_data("second"),
+                make_provider_data("first"),
+                make_provider_data("third"),
+            ]
+        ));
+        assert!(removes_accounts(&providers, &[make_provider_data("first")]));
+        assert!(removes_accounts(
+            &providers,
+            &[make_provider_data("first"), make_provider_data("third")]
+        ));
+
+        // the accounts are matched by id
+        let renamed = ProviderData {
+            owner: "renamed".to_owned(),
+            ..make_provider_data("second")
+        };
+        assert!(!removes_accounts(
+            &providers,
+            &[make_provider_data("first"), renamed]
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_second_factor_attempts() {
+        let directory = std::env::temp_dir().join(format!(
+            "storage-orchestra-second-factor-{}",
+            rand::rng().next_u64()
+        ));
+        tokio::fs::create_dir_all(&directory).await.unwrap();
+        let state_file = directory.join("state.json");
+
+        let data_key = generate_data_key();
+        let secret = Secret::generate_secret().to_bytes().unwrap();
+        let mut stored_state = AppStateDeep {
+            password: Arc::new(RwLock::new(CryptData::new(
+                b"password".to_vec(),
+                CryptDataMode::to_u8(vec![CryptDataMode::Hash, CryptDataMode::PasswordHash]),
+                None,
+                None,
+            ))),
+            key_slots: vec![KeySlot::wrap(KeySlotKind::Password, &data_key, b"password")],
+            ..Default::default()
+        };
+        stored_state.settings.security.two_factor_authentication = TwoFactorAuthentication {
+            enabled: true,
+            secret: Some(Arc::new(RwLock::new(CryptData::new_with_kdf(
+                secret.clone(),
+                CryptDataMode::to_u8(vec![CryptDataMode::Encrypt, CryptDataMode::Encode]),
+                Some(data_key.as_slice()),
+                None,
+                KdfAlgorithm::HkdfSha3_512,
+            )))),
+            recovery_codes: Vec::new(),
+        };
+        let unsigned_state = serde_json::to_value(&stored_state).unwrap();
+        stored_state.settings.security.signature =
+            Arc::new(RwLock::new(sign_state(&unsigned_state, &data_key).unwrap()));
+        let json = serde_json::to_string(&stored_state).unwrap();
+        write_state_file(&state_file, json.as_bytes())
+            .await
+            .unwrap();
+
+        // asking whether a code is needed is not an attempt
+        let error = open_state_with_password(&state_file, "password", None, None)
+            .await
+            .unwrap_err();
+        assert_eq!(error, "Two factor authentication code required");
+        assert_eq!(
+            load_unlock_attempts(&state_file)
+                .await
+                .failed_second_factor_attempts,
+            0
+        );
+
+        // the wrong codes are counted even though the password is correct
+        let error = open_state_with_password(&state_file, "password", Some("000000"), None)
+            .await
+            .unwrap_err();
+        assert_eq!(error, "Invalid two factor authentication code");
+
+        let attempts = load_unlock_attempts(&state_file).await;
+        assert_eq!(attempts.failed_attempts, 0);
+        assert_eq!(attempts.failed_second_factor_attempts, 1);
+
+        // the missing codes as well once the code was asked for
+        update_unlock_attempts(&state_file, |attempts| {
+            attempts.last_second_factor_failure = Some(0);
+        })
+        .await
+        .unwrap();
+        assert!(
+            open_state_with_password(&state_file, "password", None, None)
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            load_unlock_attempts(&state_file)
+                .await
+                .failed_second_factor_attempts,
+            2
+        );
+
+        update_unlock_attempts(&state_file, |attempts| {
+            attempts.last_second_factor_failure = Some(0);
+        })
+        .await
+        .unwrap();
+        let code = make_totp(secret).unwrap().generate_current().unwrap();
+        assert!(
+            open_state_with_password(&state_file, "password", Some(code.as_str()), None)
+                .await
+                .is_ok()
+        );
+
+        let attempts = load_unlock_attempts(&state_file).await;
+        assert_eq!(attempts.failed_attempts, 0);
+        assert_eq!(attempts.failed_second_factor_attempts, 0);
+        assert!(attempts.history.iter().all(|attempt| attempt.succeeded));
+
+        tokio::fs::remove_dir_all(&directory).await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_unlock_state_written_by_the_first_release() {
+        let directory = std::env::temp_dir().join(format!(
+            "storage-orchestra-first-release-{}",
+            rand::rng().next_u64()
+        ));
+        tokio::fs::create_dir_all(&directory).await.unwrap();
+        let state_file = directory.join("state.json");
+        tokio::fs::write(&state_file, include_str!("fixtures/state_v0.json"))
+            .await
+            .unwrap();
+
+        let (stored_state, data_key, migrated, backup, _) =
+            open_state_with_password(&state_file, "password", None, None)
+                .await
+                .unwrap();
+        assert!(data_key.is_none());
+        assert!(migrated);
+        assert_eq!(backup, 0);
+
+        // the legacy password hash and secrets are upgraded once the vault is unlocked
+        assert!(stored_state.password.read().await.needs_rehash());
+        let AccountCredential::OAuth { access_token, .. } = &stored_state.providers[0].credential
+        else {
+            panic!("The account is not authorized by OAuth tokens");
+        };
+        assert_eq!(
+            access_token
+                .clone()
+                .get_raw_data(Some(b"password"))
+                .unwrap(),
+            b"access"
+        );
+        assert!(
+            open_state_with_password(&state_file, "wrong password", None, None)
+                .await
+                .is_err()
+        );
+
+        tokio::fs::remove_dir_all(&directory).await.unwrap();
+    }
 }
@@ -0,0 +1,161 @@
+use crate::state::provider_data::AccountView;
+use crate::state::settings::Settings;
+use crate::state::state::{AppState, AppStateDeepKeys};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{AppHandle, State};
+use tauri_specta::Event;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+
+/// Emitted when the settings change
+#[derive(Debug, Clone, Serialize, Deserialize, Type, Event)]
+pub struct SettingsChanged {
+    /// The key of the state that changed
+    pub key: AppStateDeepKeys,
+    /// The new settings
+    pub value: Settings,
+}
+
+/// Emitted when the storage providers change
+#[derive(Debug, Clone, Serialize, Deserialize, Type, Event)]
+pub struct ProvidersChanged {
+    /// The key of the state that changed
+    pub key: AppStateDeepKeys,
+    /// The new storage provider accounts, their credentials are not exposed
+    pub value: Vec<AccountView>,
+}
+
+/// Emitted when the password or the key slots change, the new value is never sent
+#[derive(Debug, Clone, Serialize, Deserialize, Type, Event)]
+pub struct CredentialsChanged {
+    /// The key of the state that changed
+    pub key: AppStateDeepKeys,
+}
+
+/// Emitted when the vault is locked and the decrypted state is wiped from memory
+#[derive(Debug, Clone, Serialize, Deserialize, Type, Event)]
+pub struct VaultLocked;
+
+/// Emitted when the state could not be saved to disk, the changes are only kept in memory
+#[derive(Debug, Clone, Serialize, Deserialize, Type, Event)]
+pub struct StateSaveFailed {
+    /// The error of the last save attempt
+    pub error: String,
+}
+
+/// Emitted when the state file was changed outside of the application and the change could not be
+/// applied automatically
+#[derive(Debug, Clone, Serialize, Deserialize, Type, Event)]
+pub struct StateFileChanged {
+    /// Whether the changed state file is validly signed and can replace the state in memory
+    pub reloadable: bool,
+}
+
+/// Notify the frontend that a key of the state changed
+///
+/// Failing to emit an event never fails the change, the state is already updated.
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `state` - The application state
+/// * `key` - The key of the state that changed
+///
+/// # Returns
+///
+/// Nothing
+pub async fn emit_state_changed(
+    app: &AppHandle,
+    state: &State<'_, AppState>,
+    key: AppStateDeepKeys,
+) {
+    let readable_state = state.read().await;
+
+    let result = match key {
+        AppStateDeepKeys::Settings => SettingsChanged {
+            key,
+            value: readable_state.settings.clone(),
+        }
+        .emit(app),
+        AppStateDeepKeys::Providers => ProvidersChanged {
+            key,
+            value: readable_state
+                .providers
+                .iter()
+                .map(AccountView::from_account)
+                .collect(),
+        }
+        .emit(app),
+        AppStateDeepKeys::Password | AppStateDeepKeys::KeySlots => {
+            CredentialsChanged { key }.emit(app)
+        }
+        AppStateDeepKeys::SchemaVersion
+        | AppStateDeepKeys::Generation
+        | AppStateDeepKeys::DebouncedSaver
+        | AppStateDeepKeys::CancellationTokens => Ok(()),
+    };
+    drop(readable_state);
+
+    if let Err(e) = result {
+        warn!("Cannot emit the state change event: {}", e);
+    }
+}
+
+/// Notify the frontend that the vault was locked
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+///
+/// # Returns
+///
+/// Nothing
+pub fn emit_vault_locked(app: &AppHandle) {
+    if let Err(e) = VaultLocked.emit(app) {
+        warn!("Cannot emit the vault locked event: {}", e);
+    }
+}
+
+/// Notify the frontend that the state file was changed outside of the application
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `reloadable` - Whether the changed state file can replace the state in memory
+///
+/// # Returns
+///
+/// Nothing
+pub fn emit_state_file_changed(app: &AppHandle, reloadable: bool) {
+    if let Err(e) = (StateFileChanged { reloadable }).emit(app) {
+        warn!("Cannot emit the state file changed event: {}", e);
+    }
+}
+
+/// Forward the failures of the state saves to the frontend
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `failures` - The receiver of the save failures of the debounced saver
+///
+/// # Returns
+///
+/// Nothing
+pub fn forward_save_failures(app: AppHandle, mut failures: broadcast::Receiver<String>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let error = match failures.recv().await {
+                Ok(error) => error,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+
+            if let Err(e) = (StateSaveFailed { error }).emit(&app) {
+                warn!("Cannot emit the state save failure event: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,329 @@
+use crate::crypt::{CryptData, CryptDataMode, KdfAlgorithm};
+use crate::state::key_slot::{KeySlot, KeySlotKind, find_key_slot};
+use crate::state::state::AppStateDeep;
+use crate::state::vault_registry::{load_vault_registry, vault_file_path};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+/// The version of the state container written by this build
+pub static STATE_CONTAINER_VERSION: u8 = 1;
+
+/// The compression algorithms that can be applied to the serialized state
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum StateCompression {
+    /// Zstandard compression
+    #[serde(rename = "zstd")]
+    Zstd,
+}
+
+/// A state file wrapping the serialized state.
+///
+/// The header is stored in clear and only contains what is needed to unlock the payload, the
+/// payload is the serialized (and signed) state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateContainer {
+    /// The container format version
+    pub container: u8,
+    /// The key slots needed to unwrap the data key, empty if the payload is not encrypted
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key_slots: Vec<KeySlot>,
+    /// The compression applied to the serialized state before any encryption
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<StateCompression>,
+    /// The serialized state
+    pub payload: CryptData,
+}
+
+/// The content of a state file
+///
+/// The state is kept as raw JSON so that it can be migrated before the typed deserialization.
+#[derive(Debug, Clone)]
+pub enum StateFile {
+    /// The serialized state stored as plain JSON
+    Plain(Value),
+    /// The serialized state wrapped in a container
+    Container(StateContainer),
+}
+
+impl StateContainer {
+    /// Check whether the payload is encrypted
+    ///
+    /// # Returns
+    ///
+    /// Whether the payload is encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.payload.get_modes().contains(&CryptDataMode::Encrypt)
+    }
+
+    /// Unwrap the data key using one of the key slots of the header
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The unlock method
+    /// * `secret` - The unlock secret, either the password or the recovery key
+    ///
+    /// # Returns
+    ///
+    /// The data key, an error if the secret is wrong
+    pub async fn unwrap_data_key(
+        &self,
+        kind: KeySlotKind,
+        secret: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let slot = find_key_slot(&self.key_slots, kind).ok_or(match kind {
+            KeySlotKind::Password => "Password key slot not found",
+            KeySlotKind::Recovery => "No recovery key configured",
+        })?;
+
+        slot.unwrap_key(secret).await.map_err(|_| {
+            match kind {
+                KeySlotKind::Password => "Invalid password",
+                KeySlotKind::Recovery => "Invalid recovery key",
+            }
+            .to_owned()
+        })
+    }
+
+    /// Open the container
+    ///
+    /// # Arguments
+    ///
+    /// * `data_key` - The data key, required if the payload is encrypted
+    ///
+    /// # Returns
+    ///
+    /// The serialized state
+    pub fn open(&self, data_key: Option<&[u8]>) -> Result<Value, String> {
+        if self.is_encrypted() && data_key.is_none() {
+            return Err("The state file is encrypted".to_owned());
+        }
+
+        let mut json = self
+            .payload
+            .decrypt_stored_data(data_key.unwrap_or_default())?;
+
+        if let Some(StateCompression::Zstd) = self.compression {
+            json = zstd::decode_all(json.as_slice()).map_err(|e| e.to_string())?;
+        }
+
+        serde_json::from_slice::<Value>(&json).map_err(|e| e.to_string())
+    }
+}
+
+/// Resolve the path of the state file of the selected vault
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+///
+/// # Returns
+///
+/// The path of the state file
+pub fn state_file_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let registry = load_vault_registry(app)?;
+
+    vault_file_path(app, registry.selected_vault()?)
+}
+
+/// Encode the serialized state into the content of the state file
+///
+/// The serialized state is compressed first and then encrypted, according to the encryption
+/// settings of the state.
+///
+/// # Arguments
+///
+/// * `json` - The serialized state
+/// * `state` - The state, used to read the encryption settings and the key slots
+/// * `data_key` - The data key used to encrypt the state
+///
+/// # Returns
+///
+/// The content of the state file
+pub fn encode_state_file(
+    json: String,
+    state: &AppStateDeep,
+    data_key: &[u8],
+) -> Result<String, String> {
+    let settings = &state.settings.security.encryption;
+    if !settings.encrypt_state && !settings.compress_state {
+        return Ok(json);
+    }
+
+    let (payload, compression) = if settings.compress_state {
+        let compressed = zstd::encode_all(json.as_bytes(), zstd::DEFAULT_COMPRESSION_LEVEL)
+            .map_err(|e| e.to_string())?;
+        (compressed, Some(StateCompression::Zstd))
+    } else {
+        (json.into_bytes(), None)
+    };
+
+    let (modes, key_slots) = if settings.encrypt_state {
+        (
+            vec![CryptDataMode::Encrypt, CryptDataMode::Encode],
+            state.key_slots.clone(),
+        )
+    } else {
+        (vec![CryptDataMode::Encode], Vec::new())
+    };
+
+    let container = StateContainer {
+        container: STATE_CONTAINER_VERSION,
+        key_slots,
+        compression,
+        // the data key has full entropy, the memory-hard key derivation is not needed
+        payload: CryptData::new_with_kdf(
+            payload,
+            CryptDataMode::to_u8(modes),
+            Some(data_key),
+            None,
+            KdfAlgorithm::HkdfSha3_512,
+        ),
+    };
+
+    serde_json::to_string(&container).map_err(|e| e.to_string())
+}
+
+/// Decode the content of the state file
+///
+/// # Arguments
+///
+/// * `content` - The content of the state file
+///
+/// # Returns
+///
+/// The decoded state file
+pub fn decode_state_file(content: &[u8]) -> Result<StateFile, String> {
+    let value = serde_json::from_slice::<Value>(content).map_err(|e| e.to_string())?;
+
+    // state files written before the container only hold the serialized state
+    let Some(version) = value.get("container") else {
+        return Ok(StateFile::Plain(value));
+    };
+
+    if version
+        .as_u64()
+        .is_none_or(|v| v > STATE_CONTAINER_VERSION as u64)
+    {
+        return Err(format!("Unsupported state container version {}", version));
+    }
+
+    let container = serde_json::from_value::<StateContainer>(value).map_err(|e| e.to_string())?;
+    Ok(StateFile::Container(container))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::key_slot::generate_data_key;
+
+    fn make_state(data_key: &[u8], encrypt_state: bool, compress_state: bool) -> AppStateDeep {
+        let mut state = AppStateDeep::default();
+        state.key_slots = vec![KeySlot::wrap(KeySlotKind::Password, data_key, b"password")];
+        state.settings.security.encryption.encrypt_state = encrypt_state;
+        state.settings.security.encryption.compress_state = compress_state;
+        state
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_plain_state_file() {
+        let data_key = generate_data_key();
+        let state = make_state(&data_key, false, false);
+        let json = serde_json::to_string(&state).unwrap();
+
+        let content = encode_state_file(json.clone(), &state, &data_key).unwrap();
+        assert_eq!(content, json);
+
+        match decode_state_file(content.as_bytes()).unwrap() {
+            StateFile::Plain(decoded) => {
+                assert_eq!(decoded["key_slots"].as_array().unwrap().len(), 1)
+            }
+            StateFile::Container(_) => panic!("Expected a plain state file"),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sealed_state_file() {
+        let data_key = generate_data_key();
+        let state = make_state(&data_key, true, false);
+        let json = serde_json::to_string(&state).unwrap();
+
+        let content = encode_state_file(json, &state, &data_key).unwrap();
+        assert!(!content.contains("encrypt_state"));
+
+        let StateFile::Container(container) = decode_state_file(content.as_bytes()).unwrap() else {
+            panic!("Expected a state container");
+        };
+        assert!(container.is_encrypted());
+        assert!(container.open(None).is_err());
+        assert!(
+            container
+                .unwrap_data_key(KeySlotKind::Password, b"wrong password")
+                .await
+                .is_err()
+        );
+
+        let unwrapped_key = container
+            .unwrap_data_key(KeySlotKind::Password, b"password")
+            .await
+            .unwrap();
+        assert_eq!(unwrapped_key, data_key);
+
+        let decoded = container.open(Some(&unwrapped_key)).unwrap();
+        assert_eq!(
+            decoded["settings"]["security"]["encryption"]["encrypt_state"],
+            true
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_compressed_state_file() {
+        let data_key = generate_data_key();
+        let state = make_state(&data_key, false, true);
+        let json = serde_json::to_string(&state).unwrap();
+
+        let content = encode_state_file(json.clone(), &state, &data_key).unwrap();
+
+        let StateFile::Container(container) = decode_state_file(content.as_bytes()).unwrap() else {
+            panic!("Expected a state container");
+        };
+        assert!(!container.is_encrypted());
+        assert!(container.key_slots.is_empty());
+        assert_eq!(container.compression, Some(StateCompression::Zstd));
+
+        let decoded = container.open(None).unwrap();
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_compressed_and_sealed_state_file() {
+        let data_key = generate_data_key();
+        let state = make_state(&data_key, true, true);
+        let json = serde_json::to_string(&state).unwrap();
+
+        let content = encode_state_file(json, &state, &data_key).unwrap();
+
+        let StateFile::Container(container) = decode_state_file(content.as_bytes()).unwrap() else {
+            panic!("Expected a state container");
+        };
+        assert!(container.is_encrypted());
+        assert_eq!(container.compression, Some(StateCompression::Zstd));
+
+        let unwrapped_key = container
+            .unwrap_data_key(KeySlotKind::Password, b"password")
+            .await
+            .unwrap();
+        let decoded = container.open(Some(&unwrapped_key)).unwrap();
+        assert_eq!(
+            decoded["settings"]["security"]["encryption"]["compress_state"],
+            true
+        );
+    }
+
+    #[test]
+    fn test_decode_unsupported_container_version() {
+        let content = r#"{"container":255,"payload":{}}"#;
+        assert!(decode_state_file(content.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,362 @@
+use crate::secret_manager::read_data_key;
+use crate::state::state::{AppState, AppStateDeep, AppStateDeepKeys};
+use crate::state::state_backup::write_state_file;
+use crate::state::state_events::{emit_state_changed, emit_state_file_changed};
+use crate::state::state_file::{StateFile, decode_state_file};
+use crate::state::state_migration::parse_state;
+use crate::state::state_signature::verify_state_signature;
+use sha3::{Digest, Sha3_256};
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+/// How often the state file of the opened vault is checked for external changes
+static STATE_FILE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The error returned when the vault is opened by another instance of the application
+pub static VAULT_IN_USE_ERROR: &str = "The vault is open in another instance of the application";
+
+/// The state file of the vault opened by this instance, the writes and the checks for external
+/// changes are serialized by this lock so that the writes of the application are never mistaken
+/// for external changes
+static OPENED_STATE_FILE: Mutex<Option<OpenedStateFile>> = Mutex::const_new(None);
+
+/// The state file of the opened vault
+struct OpenedStateFile {
+    /// The path to the state file
+    path: PathBuf,
+    /// The advisory lock held while the vault is open, released when dropped
+    _lock: File,
+    /// The fingerprint of the content last written or read
+    fingerprint: Option<Vec<u8>>,
+    /// The modification time of the content last written or read
+    modified: Option<SystemTime>,
+}
+
+/// Get the path of the advisory lock of a state file
+///
+/// The lock is taken on a dedicated file as the state file is replaced on each write.
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// The path of the lock
+pub fn state_file_lock_path(state_file: &Path) -> PathBuf {
+    let mut file_name = state_file.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".lock");
+
+    state_file.with_file_name(file_name)
+}
+
+/// Take the advisory lock of a state file
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// The locked file, the lock is released when it is dropped, `VAULT_IN_USE_ERROR` if another
+/// instance holds the lock
+pub fn lock_state_file(state_file: &Path) -> Result<File, String> {
+    let lock = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(state_file_lock_path(state_file))
+        .map_err(|e| e.to_string())?;
+
+    match lock.try_lock() {
+        Ok(()) => Ok(lock),
+        Err(TryLockError::WouldBlock) => Err(VAULT_IN_USE_ERROR.to_owned()),
+        Err(TryLockError::Error(e)) => Err(e.to_string()),
+    }
+}
+
+/// Remove the advisory lock of a state file, used once the state file is deleted
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// Nothing
+pub async fn remove_state_file_lock(state_file: &Path) -> Result<(), String> {
+    match tokio::fs::remove_file(state_file_lock_path(state_file)).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+/// Get the fingerprint of the content of a state file
+///
+/// # Arguments
+///
+/// * `content` - The content of the state file
+///
+/// # Returns
+///
+/// The fingerprint
+fn fingerprint(content: &[u8]) -> Vec<u8> {
+    Sha3_256::digest(content).to_vec()
+}
+
+/// Get the modification time of a state file
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// The modification time, `None` if the state file cannot be read
+async fn modified(state_file: &Path) -> Option<SystemTime> {
+    tokio::fs::metadata(state_file).await.ok()?.modified().ok()
+}
+
+/// Open the state file of a vault in this instance, taking its advisory lock until the vault is
+/// locked
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// Nothing, `VAULT_IN_USE_ERROR` if another instance opened the vault
+pub async fn acquire_state_file(state_file: &Path) -> Result<(), String> {
+    let mut opened = OPENED_STATE_FILE.lock().await;
+    if opened
+        .as_ref()
+        .is_some_and(|opened| opened.path == state_file)
+    {
+        return Ok(());
+    }
+
+    // the lock of the previous vault is released before the new one is taken
+    opened.take();
+    let lock = lock_state_file(state_file)?;
+    let content = tokio::fs::read(state_file).await.ok();
+
+    *opened = Some(OpenedStateFile {
+        path: state_file.to_path_buf(),
+        _lock: lock,
+        fingerprint: content.as_deref().map(fingerprint),
+        modified: modified(state_file).await,
+    });
+
+    Ok(())
+}
+
+/// Close the state file of the opened vault, releasing its advisory lock
+pub async fn release_state_file() {
+    OPENED_STATE_FILE.lock().await.take();
+}
+
+/// Write the state file of the opened vault
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+/// * `content` - The new content of the state file
+///
+/// # Returns
+///
+/// Nothing, an error if the vault is not opened by this instance
+pub async fn write_opened_state_file(state_file: &Path, content: &[u8]) -> Result<(), String> {
+    let mut opened = OPENED_STATE_FILE.lock().await;
+    let opened = opened
+        .as_mut()
+        .filter(|opened| opened.path == state_file)
+        .ok_or("The vault is not open in this instance of the application")?;
+
+    // the previous state is kept as a backup and replaced atomically
+    write_state_file(state_file, content).await?;
+
+    opened.fingerprint = Some(fingerprint(content));
+    opened.modified = modified(state_file).await;
+
+    Ok(())
+}
+
+/// Read the state file of the opened vault if it was changed outside of the application
+///
+/// # Returns
+///
+/// The new content of the state file, `None` if it did not change
+async fn read_external_change() -> Option<Vec<u8>> {
+    let mut opened = OPENED_STATE_FILE.lock().await;
+    let opened = opened.as_mut()?;
+
+    // a deleted state file is written again by the next save
+    let modified = modified(&opened.path).await?;
+    if opened.modified == Some(modified) {
+        return None;
+    }
+    opened.modified = Some(modified);
+
+    let content = tokio::fs::read(&opened.path).await.ok()?;
+    let fingerprint = fingerprint(&content);
+    if opened.fingerprint.as_ref() == Some(&fingerprint) {
+        return None;
+    }
+    opened.fingerprint = Some(fingerprint);
+
+    Some(content)
+}
+
+/// Open the content of the state file of the unlocked vault, its signature is verified with the
+/// data key of the vault
+///
+/// # Arguments
+///
+/// * `content` - The content of the state file
+///
+/// # Returns
+///
+/// The state
+pub async fn open_unlocked_state_file(content: &[u8]) -> Result<AppStateDeep, String> {
+    let data_key = read_data_key().await?;
+    let raw_state = match decode_state_file(content)? {
+        StateFile::Plain(raw_state) => raw_state,
+        StateFile::Container(container) => container.open(Some(data_key.as_slice()))?,
+    };
+    verify_state_signature(&raw_state, data_key.as_slice())?;
+    drop(data_key);
+
+    let (state, _) = parse_state(raw_state)?;
+
+    Ok(state)
+}
+
+/// Replace the state in memory with a state read from the state file
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `state` - The application state
+/// * `stored_state` - The state read from the state file
+///
+/// # Returns
+///
+/// Nothing
+pub async fn replace_state(
+    app: &AppHandle,
+    state: &State<'_, AppState>,
+    mut stored_state: AppStateDeep,
+) {
+    let mut writable_state = state.write().await;
+
+    // an older state continues from the current generation so that it is never taken for a
+    // rollback once saved
+    stored_state.generation = stored_state.generation.max(writable_state.generation);
+    *writable_state = AppStateDeep {
+        debounced_saver: writable_state.debounced_saver.clone(),
+        cancellation_tokens: writable_state.cancellation_tokens.clone(),
+        ..stored_state
+    };
+    drop(writable_state);
+
+    for key in [
+        AppStateDeepKeys::Password,
+        AppStateDeepKeys::Providers,
+        AppStateDeepKeys::Settings,
+    ] {
+        emit_state_changed(app, state, key).await;
+    }
+}
+
+/// Apply a change of the state file made outside of the application.
+///
+/// The state is reloaded if the changed state file is validly signed, newer than the state in
+/// memory and no change is waiting to be saved, the frontend is asked what to do otherwise.
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `content` - The new content of the state file
+///
+/// # Returns
+///
+/// Nothing
+async fn apply_external_change(app: &AppHandle, content: &[u8]) {
+    let stored_state = match open_unlocked_state_file(content).await {
+        Ok(stored_state) => stored_state,
+        Err(e) => {
+            warn!("The state file was changed and cannot be opened: {}", e);
+            emit_state_file_changed(app, false);
+            return;
+        }
+    };
+
+    let state = app.state::<AppState>();
+    let readable_state = state.read().await;
+    let is_newer = stored_state.generation > readable_state.generation;
+    let has_pending_changes = readable_state.debounced_saver.has_pending().await;
+    drop(readable_state);
+
+    if !is_newer || has_pending_changes {
+        debug!("The state file was changed and conflicts with the state in memory");
+        emit_state_file_changed(app, true);
+        return;
+    }
+
+    debug!("Reloading the state file changed outside of the application");
+    replace_state(app, &state, stored_state).await;
+}
+
+/// Watch the state file of the opened vault for changes made outside of the application
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+///
+/// # Returns
+///
+/// Nothing
+pub fn watch_state_file(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(STATE_FILE_CHECK_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            if let Some(content) = read_external_change().await {
+                apply_external_change(&app, &content).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    #[test]
+    fn test_lock_state_file() {
+        let directory =
+            std::env::temp_dir().join(format!("storage-orchestra-lock-{}", rand::rng().next_u64()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let state_file = directory.join("state.json");
+
+        let lock = lock_state_file(&state_file).unwrap();
+        assert_eq!(
+            lock_state_file(&state_file).unwrap_err(),
+            VAULT_IN_USE_ERROR
+        );
+        assert!(lock_state_file(&directory.join("vault.json")).is_ok());
+
+        drop(lock);
+        assert!(lock_state_file(&state_file).is_ok());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
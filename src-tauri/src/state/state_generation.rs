@@ -0,0 +1,402 @@
+use crate::utility::atomic_file::write_atomically;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::path::BaseDirectory;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// The file mirroring the generation of each state file
+pub static STATE_GENERATIONS_FILE: &str = "state_generations.json";
+
+/// The suffix of the directory storing the mirrored generations, next to the application data
+/// directory in the local data directory so that it is not restored along with the vault directory
+/// and does not roam with the user profile on any platform
+static STATE_GENERATIONS_DIRECTORY_SUFFIX: &str = ".generations";
+
+/// The error returned when the state file is older than the last state written on this device,
+/// the frontend matches it to offer opening the older state anyway
+pub static STATE_ROLLBACK_ERROR: &str =
+    "The state file is older than the last one saved on this device";
+
+/// Serializes the changes to the mirrored generations
+static STATE_GENERATIONS_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// The generation of a state file written on this device
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StateGeneration {
+    /// The generation of the last state written
+    pub current: u64,
+}
+
+impl StateGeneration {
+    /// Check that a state is not older than the last state written on this device
+    ///
+    /// A backup is always older than the state file it was rotated from, restoring one is a
+    /// rollback even if the state file only looks unreadable, it could have been damaged on
+    /// purpose to bring back a backup opened by an old password.
+    ///
+    /// # Arguments
+    ///
+    /// * `generation` - The generation of the state
+    /// * `backup` - The index of the backup the state was restored from, 0 for the state file
+    ///
+    /// # Returns
+    ///
+    /// Nothing if the state is not older than expected, `STATE_ROLLBACK_ERROR` otherwise
+    pub fn check(&self, generation: u64, backup: usize) -> Result<(), String> {
+        if backup > 0 {
+            warn!(
+                "State rollback detected: restored from backup {} with generation {}",
+                backup, generation
+            );
+            return Err(STATE_ROLLBACK_ERROR.to_owned());
+        }
+
+        if generation < self.current {
+            warn!(
+                "State rollback detected: generation {}, expected at least {}",
+                generation, self.current
+            );
+            return Err(STATE_ROLLBACK_ERROR.to_owned());
+        }
+
+        Ok(())
+    }
+
+    /// Record that a state was written, older generations are ignored
+    ///
+    /// # Arguments
+    ///
+    /// * `generation` - The generation of the state
+    pub fn record(&mut self, generation: u64) {
+        self.current = self.current.max(generation);
+    }
+}
+
+/// Get the path of the mirrored generations, moving the ones stored in the application config
+/// directory by the previous versions
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+///
+/// # Returns
+///
+/// The path of the mirrored generations
+pub async fn state_generations_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let generations_file = app
+        .path()
+        .resolve(
+            format!(
+                "{}{}",
+                app.config().identifier,
+                STATE_GENERATIONS_DIRECTORY_SUFFIX
+            ),
+            BaseDirectory::LocalData,
+        )
+        .map_err(|e| e.to_string())?
+        .join(STATE_GENERATIONS_FILE);
+
+    let legacy_generations_file = app
+        .path()
+        .resolve(STATE_GENERATIONS_FILE, BaseDirectory::AppConfig)
+        .map_err(|e| e.to_string())?;
+    move_legacy_state_generations(&legacy_generations_file, &generations_file).await;
+
+    Ok(generations_file)
+}
+
+/// Move the mirrored generations stored by the previous versions, kept in place if they cannot
+/// be moved so that they are not lost
+///
+/// # Arguments
+///
+/// * `legacy_generations_file` - The path of the generations stored by the previous versions
+/// * `generations_file` - The path of the mirrored generations
+async fn move_legacy_state_generations(legacy_generations_file: &Path, generations_file: &Path) {
+    if !tokio::fs::try_exists(legacy_generations_file)
+        .await
+        .unwrap_or(false)
+    {
+        return;
+    }
+
+    let _guard = STATE_GENERATIONS_LOCK.lock().await;
+
+    // the generations recorded since then are newer
+    if tokio::fs::try_exists(generations_file)
+        .await
+        .unwrap_or(true)
+    {
+        return;
+    }
+
+    let moved = async {
+        let content = tokio::fs::read(legacy_generations_file)
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Some(directory) = generations_file.parent() {
+            tokio::fs::create_dir_all(directory)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        write_atomically(generations_file, &content).await
+    }
+    .await;
+    if let Err(e) = moved {
+        warn!("Cannot move the state generations: {}", e);
+        return;
+    }
+
+    if let Err(e) = tokio::fs::remove_file(legacy_generations_file).await {
+        warn!("Cannot remove the moved state generations: {}", e);
+    }
+}
+
+/// Get the key of a state file in the mirrored generations
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// The name of the state file
+fn state_generation_key(state_file: &Path) -> String {
+    state_file
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Load the mirrored generations
+///
+/// # Arguments
+///
+/// * `generations_file` - The path of the mirrored generations
+///
+/// # Returns
+///
+/// The generations by state file name, empty if none were recorded
+async fn load_state_generations(generations_file: &Path) -> BTreeMap<String, StateGeneration> {
+    let Ok(content) = tokio::fs::read(generations_file).await else {
+        return BTreeMap::new();
+    };
+
+    serde_json::from_slice::<BTreeMap<String, StateGeneration>>(&content)
+        .inspect_err(|e| warn!("Ignoring the unreadable state generations: {}", e))
+        .unwrap_or_default()
+}
+
+/// Update the mirrored generations
+///
+/// # Arguments
+///
+/// * `generations_file` - The path of the mirrored generations
+/// * `update` - The function updating the generations
+///
+/// # Returns
+///
+/// Nothing
+async fn update_state_generations<F>(generations_file: &Path, update: F) -> Result<(), String>
+where
+    F: FnOnce(&mut BTreeMap<String, StateGeneration>),
+{
+    let _guard = STATE_GENERATIONS_LOCK.lock().await;
+
+    let mut generations = load_state_generations(generations_file).await;
+    update(&mut generations);
+    let json = serde_json::to_vec(&generations).map_err(|e| e.to_string())?;
+
+    if let Some(directory) = generations_file.parent() {
+        tokio::fs::create_dir_all(directory)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    write_atomically(generations_file, &json).await
+}
+
+/// Read the mirrored generation of a state file
+///
+/// # Arguments
+///
+/// * `generations_file` - The path of the mirrored generations
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// The generations written on this device, zero if none were recorded
+pub async fn read_state_generation(generations_file: &Path, state_file: &Path) -> StateGeneration {
+    load_state_generations(generations_file)
+        .await
+        .get(&state_generation_key(state_file))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Record that a state file was written
+///
+/// # Arguments
+///
+/// * `generations_file` - The path of the mirrored generations
+/// * `state_file` - The path to the state file
+/// * `generation` - The generation of the state written
+///
+/// # Returns
+///
+/// Nothing
+pub async fn record_state_generation(
+    generations_file: &Path,
+    state_file: &Path,
+    generation: u64,
+) -> Result<(), String> {
+    update_state_generations(generations_file, |generations| {
+        generations
+            .entry(state_generation_key(state_file))
+            .or_default()
+            .record(generation)
+    })
+    .await
+}
+
+/// Forget the mirrored generation of a state file, used once the state file is deleted
+///
+/// # Arguments
+///
+/// * `generations_file` - The path of the mirrored generations
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// Nothing
+pub async fn remove_state_generation(
+    generations_file: &Path,
+    state_file: &Path,
+) -> Result<(), String> {
+    update_state_generations(generations_file, |generations| {
+        generations.remove(&state_generation_key(state_file));
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    #[test]
+    fn test_check_generation() {
+        let mut generation = StateGeneration::default();
+        assert!(generation.check(0, 0).is_ok());
+
+        generation.record(3);
+        generation.record(5);
+        generation.record(4);
+        assert_eq!(generation, StateGeneration { current: 5 });
+
+        assert!(generation.check(5, 0).is_ok());
+        assert!(generation.check(6, 0).is_ok());
+        assert_eq!(generation.check(4, 0), Err(STATE_ROLLBACK_ERROR.to_owned()));
+    }
+
+    #[test]
+    fn test_check_backup() {
+        let generation = StateGeneration { current: 5 };
+
+        // a backup needs the rollback to be allowed, whatever its generation
+        assert_eq!(generation.check(4, 1), Err(STATE_ROLLBACK_ERROR.to_owned()));
+        assert_eq!(generation.check(5, 2), Err(STATE_ROLLBACK_ERROR.to_owned()));
+        assert!(StateGeneration::default().check(0, 1).is_err());
+
+        // the generations mirrored along with the backups by the previous versions
+        let legacy =
+            serde_json::from_str::<StateGeneration>(r#"{"current":5,"previous":4,"older":[3]}"#)
+                .unwrap();
+        assert_eq!(legacy, generation);
+    }
+
+    #[tokio::test]
+    async fn test_state_generations_file() {
+        let directory = std::env::temp_dir().join(format!(
+            "storage-orchestra-generations-{}",
+            rand::rng().next_u64()
+        ));
+        let generations_file = directory.join("config").join(STATE_GENERATIONS_FILE);
+        let state_file = directory.join("state.json");
+        let other_state_file = directory.join("vault.json");
+
+        record_state_generation(&generations_file, &state_file, 2)
+            .await
+            .unwrap();
+        record_state_generation(&generations_file, &other_state_file, 7)
+            .await
+            .unwrap();
+        assert_eq!(
+            read_state_generation(&generations_file, &state_file).await,
+            StateGeneration { current: 2 }
+        );
+
+        remove_state_generation(&generations_file, &state_file)
+            .await
+            .unwrap();
+        assert_eq!(
+            read_state_generation(&generations_file, &state_file).await,
+            StateGeneration::default()
+        );
+        assert_eq!(
+            read_state_generation(&generations_file, &other_state_file)
+                .await
+                .current,
+            7
+        );
+
+        tokio::fs::remove_dir_all(&directory).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_move_legacy_state_generations() {
+        let directory = std::env::temp_dir().join(format!(
+            "storage-orchestra-legacy-generations-{}",
+            rand::rng().next_u64()
+        ));
+        let legacy_generations_file = directory.join("config").join(STATE_GENERATIONS_FILE);
+        let generations_file = directory
+            .join("data.generations")
+            .join(STATE_GENERATIONS_FILE);
+        let state_file = directory.join("state.json");
+
+        // nothing to move
+        move_legacy_state_generations(&legacy_generations_file, &generations_file).await;
+        assert!(!generations_file.exists());
+
+        record_state_generation(&legacy_generations_file, &state_file, 4)
+            .await
+            .unwrap();
+        move_legacy_state_generations(&legacy_generations_file, &generations_file).await;
+        assert!(!legacy_generations_file.exists());
+        assert_eq!(
+            read_state_generation(&generations_file, &state_file).await,
+            StateGeneration { current: 4 }
+        );
+
+        // the generations recorded after the move are kept
+        record_state_generation(&legacy_generations_file, &state_file, 2)
+            .await
+            .unwrap();
+        record_state_generation(&generations_file, &state_file, 6)
+            .await
+            .unwrap();
+        move_legacy_state_generations(&legacy_generations_file, &generations_file).await;
+        assert_eq!(
+            read_state_generation(&generations_file, &state_file)
+                .await
+                .current,
+            6
+        );
+
+        tokio::fs::remove_dir_all(&directory).await.unwrap();
+    }
+}
@@ -0,0 +1,404 @@
+use crate::state::account_registry::new_account_id;
+use crate::state::state::AppStateDeep;
+use serde_json::{Map, Value, json};
+use tracing::debug;
+
+/// The version of the state schema written by this build
+pub const STATE_SCHEMA_VERSION: u32 = 5;
+
+/// A migration upgrading the serialized state by one schema version
+type Migration = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+/// The migrations in order, the migration at index `n` upgrades a state from version `n` to `n + 1`
+static MIGRATIONS: [Migration; STATE_SCHEMA_VERSION as usize] = [
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+];
+
+/// Get the schema version of a serialized state
+///
+/// # Arguments
+///
+/// * `state` - The serialized state
+///
+/// # Returns
+///
+/// The schema version, states written before the schema was versioned are version 0
+pub fn schema_version(state: &Value) -> Result<u32, String> {
+    match state.get("schema_version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or(format!("Invalid state schema version {}", version)),
+    }
+}
+
+/// Migrate a serialized state to the current schema version
+///
+/// # Arguments
+///
+/// * `state` - The serialized state, upgraded in place
+///
+/// # Returns
+///
+/// Whether the state was migrated
+pub fn migrate_state(state: &mut Value) -> Result<bool, String> {
+    let version = schema_version(state)?;
+    if version > STATE_SCHEMA_VERSION {
+        return Err(format!("Unsupported state schema version {}", version));
+    }
+
+    let object = state.as_object_mut().ok_or("The state is not an object")?;
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        debug!("Migrating the state from version {} to {}", from, from + 1);
+        migration(object)?;
+        object.insert("schema_version".to_owned(), json!(from + 1));
+    }
+
+    Ok(version < STATE_SCHEMA_VERSION)
+}
+
+/// Migrate a serialized state to the current schema version and deserialize it
+///
+/// # Arguments
+///
+/// * `state` - The serialized state
+///
+/// # Returns
+///
+/// The state and whether it was migrated
+pub fn parse_state(mut state: Value) -> Result<(AppStateDeep, bool), String> {
+    let migrated = migrate_state(&mut state)?;
+    let state = serde_json::from_value::<AppStateDeep>(state).map_err(|e| e.to_string())?;
+
+    Ok((state, migrated))
+}
+
+/// Version 1 introduces the schema version, the fields added by the unversioned releases are
+/// filled in so that the next migrations can rely on them
+///
+/// # Arguments
+///
+/// * `state` - The serialized state
+///
+/// # Returns
+///
+/// Nothing
+fn migrate_v0_to_v1(state: &mut Map<String, Value>) -> Result<(), String> {
+    state.entry("key_slots").or_insert(json!([]));
+
+    if let Some(two_factor_authentication) = state
+        .get_mut("settings")
+        .and_then(|settings| settings.pointer_mut("/security/two_factor_authentication"))
+        .and_then(Value::as_object_mut)
+    {
+        two_factor_authentication
+            .entry("recovery_codes")
+            .or_insert(json!([]));
+    }
+
+    Ok(())
+}
+
+/// Version 2 signs the canonical serialization of the state, the fields are unchanged and the
+/// state is re-signed when the migration is saved
+///
+/// # Arguments
+///
+/// * `_state` - The serialized state
+///
+/// # Returns
+///
+/// Nothing
+fn migrate_v1_to_v2(_state: &mut Map<String, Value>) -> Result<(), String> {
+    Ok(())
+}
+
+/// Version 3 identifies the accounts by a stable id, the duplicated accounts of the same provider
+/// and owner are merged into the one with the latest token
+///
+/// # Arguments
+///
+/// * `state` - The serialized state
+///
+/// # Returns
+///
+/// Nothing
+fn migrate_v2_to_v3(state: &mut Map<String, Value>) -> Result<(), String> {
+    let Some(providers) = state.get_mut("providers").and_then(Value::as_array_mut) else {
+        return Ok(());
+    };
+
+    let expiry = |account: &Value| account.get("expiry").and_then(Value::as_u64);
+    let mut accounts: Vec<Value> = Vec::with_capacity(providers.len());
+    for mut account in providers.drain(..) {
+        let existing = accounts.iter_mut().find(|existing| {
+            existing.get("provider") == account.get("provider")
+                && existing.get("owner") == account.get("owner")
+        });
+
+        match existing {
+            Some(existing) if expiry(existing) < expiry(&account) => {
+                account["id"] = existing["id"].take();
+                *existing = account;
+            }
+            Some(_) => {}
+            None => {
+                account
+                    .as_object_mut()
+                    .ok_or("The account is not an object")?
+                    .insert("id".to_owned(), json!(new_account_id()));
+                accounts.push(account);
+            }
+        }
+    }
+    *providers = accounts;
+
+    Ok(())
+}
+
+/// Version 4 moves the tokens of the accounts to a credential of a given kind and records the
+/// profile of the accounts, the accounts written before are all authorized by OAuth tokens
+///
+/// # Arguments
+///
+/// * `state` - The serialized state
+///
+/// # Returns
+///
+/// Nothing
+fn migrate_v3_to_v4(state: &mut Map<String, Value>) -> Result<(), String> {
+    let Some(providers) = state.get_mut("providers").and_then(Value::as_array_mut) else {
+        return Ok(());
+    };
+
+    for provider in providers.iter_mut() {
+        let provider = provider
+            .as_object_mut()
+            .ok_or("The account is not an object")?;
+
+        let credential = json!({
+            "kind": "oauth",
+            "access_token": provider.remove("access_token"),
+            "refresh_token": provider.remove("refresh_token"),
+            "expiry": provider.remove("expiry").unwrap_or(json!(0)),
+            "token_type": "Bearer",
+            "scopes": [],
+        });
+        provider.insert("credential".to_owned(), credential);
+        provider.entry("display_name").or_insert(Value::Null);
+        provider.entry("avatar_url").or_insert(Value::Null);
+        provider.entry("quota").or_insert(Value::Null);
+    }
+
+    Ok(())
+}
+
+/// Version 5 refers to the account shown by the default page by its id, a default page showing an
+/// account that is not registered is reset to the dashboard
+///
+/// # Arguments
+///
+/// * `state` - The serialized state
+///
+/// # Returns
+///
+/// Nothing
+fn migrate_v4_to_v5(state: &mut Map<String, Value>) -> Result<(), String> {
+    let Some(page) = state
+        .get("settings")
+        .and_then(|settings| settings.pointer("/general_behaviour/default_page/providers"))
+    else {
+        return Ok(());
+    };
+
+    let account_id = state
+        .get("providers")
+        .and_then(Value::as_array)
+        .and_then(|providers| {
+            providers.iter().find(|account| {
+                account.get("provider") == page.get("provider")
+                    && account.get("owner") == page.get("owner")
+            })
+        })
+        .and_then(|account| account.get("id"))
+        .cloned();
+
+    let default_page = match account_id {
+        Some(account_id) => json!({ "providers": { "account_id": account_id } }),
+        None => json!({ "general": "dashboard" }),
+    };
+    if let Some(page) = state
+        .get_mut("settings")
+        .and_then(|settings| settings.pointer_mut("/general_behaviour/default_page"))
+    {
+        *page = default_page;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The state files written by every schema version
+    static FIXTURES: [&str; STATE_SCHEMA_VERSION as usize + 1] = [
+        include_str!("fixtures/state_v0.json"),
+        include_str!("fixtures/state_v1.json"),
+        include_str!("fixtures/state_v2.json"),
+        include_str!("fixtures/state_v3.json"),
+        include_str!("fixtures/state_v4.json"),
+        include_str!("fixtures/state_v5.json"),
+    ];
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_migrate_fixtures() {
+        for (version, fixture) in FIXTURES.iter().enumerate() {
+            let fixture = serde_json::from_str::<Value>(fixture).unwrap();
+            assert_eq!(schema_version(&fixture).unwrap(), version as u32);
+
+            let (state, migrated) = parse_state(fixture).unwrap();
+            assert_eq!(migrated, version < STATE_SCHEMA_VERSION as usize);
+            assert_eq!(state.schema_version, STATE_SCHEMA_VERSION);
+            assert_eq!(state.providers.len(), 1);
+            assert_eq!(state.providers[0].owner, "user@example.com");
+            assert!(state.password.read().await.verify(b"password"));
+        }
+    }
+
+    #[test]
+    fn test_migrate_v0_to_v1() {
+        let mut state = json!({
+            "providers": [],
+            "settings": { "security": { "two_factor_authentication": { "enabled": false } } },
+        });
+
+        assert!(migrate_state(&mut state).unwrap());
+        assert_eq!(state["schema_version"], STATE_SCHEMA_VERSION);
+        assert_eq!(state["key_slots"], json!([]));
+        assert_eq!(
+            state["settings"]["security"]["two_factor_authentication"]["recovery_codes"],
+            json!([])
+        );
+
+        // migrating a state at the current version is a no-op
+        let migrated = state.clone();
+        assert!(!migrate_state(&mut state).unwrap());
+        assert_eq!(state, migrated);
+    }
+
+    #[test]
+    fn test_migrate_v2_to_v3() {
+        let mut state = json!({
+            "schema_version": 2,
+            "providers": [
+                { "provider": "google", "owner": "first", "expiry": 1 },
+                { "provider": "google", "owner": "second", "expiry": 1 },
+                { "provider": "google", "owner": "first", "expiry": 2 },
+                { "provider": "dropbox", "owner": "first", "expiry": 1 },
+            ],
+        });
+
+        assert!(migrate_state(&mut state).unwrap());
+        let providers = state["providers"].as_array().unwrap();
+        assert_eq!(providers.len(), 3);
+        assert_eq!(providers[0]["owner"], "first");
+        assert_eq!(providers[0]["credential"]["expiry"], 2);
+
+        let ids = providers
+            .iter()
+            .map(|provider| provider["id"].as_str().unwrap())
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(ids.len(), 3);
+    }
+
+    #[test]
+    fn test_migrate_v3_to_v4() {
+        let mut state = json!({
+            "schema_version": 3,
+            "providers": [{
+                "id": "1",
+                "access_token": { "data": "access" },
+                "refresh_token": { "data": "refresh" },
+                "expiry": 10,
+                "owner": "first",
+                "provider": "google",
+            }],
+        });
+
+        assert!(migrate_state(&mut state).unwrap());
+        assert_eq!(
+            state["providers"][0],
+            json!({
+                "id": "1",
+                "owner": "first",
+                "provider": "google",
+                "credential": {
+                    "kind": "oauth",
+                    "access_token": { "data": "access" },
+                    "refresh_token": { "data": "refresh" },
+                    "expiry": 10,
+                    "token_type": "Bearer",
+                    "scopes": [],
+                },
+                "display_name": null,
+                "avatar_url": null,
+                "quota": null,
+            })
+        );
+    }
+
+    #[test]
+    fn test_migrate_v4_to_v5() {
+        let make_state = |owner: &str| {
+            json!({
+                "schema_version": 4,
+                "providers": [{ "id": "1", "provider": "google", "owner": "first" }],
+                "settings": {
+                    "general_behaviour": {
+                        "default_page": { "providers": { "provider": "google", "owner": owner } },
+                    },
+                },
+            })
+        };
+
+        let mut state = make_state("first");
+        assert!(migrate_state(&mut state).unwrap());
+        assert_eq!(
+            state["settings"]["general_behaviour"]["default_page"],
+            json!({ "providers": { "account_id": "1" } })
+        );
+
+        // the page of an account that is not registered cannot be shown
+        let mut state = make_state("second");
+        assert!(migrate_state(&mut state).unwrap());
+        assert_eq!(
+            state["settings"]["general_behaviour"]["default_page"],
+            json!({ "general": "dashboard" })
+        );
+
+        let mut state = json!({
+            "schema_version": 4,
+            "settings": { "general_behaviour": { "default_page": { "general": "settings" } } },
+        });
+        assert!(migrate_state(&mut state).unwrap());
+        assert_eq!(
+            state["settings"]["general_behaviour"]["default_page"],
+            json!({ "general": "settings" })
+        );
+    }
+
+    #[test]
+    fn test_unsupported_schema_version() {
+        let mut state = json!({ "schema_version": STATE_SCHEMA_VERSION + 1 });
+        assert!(migrate_state(&mut state).is_err());
+
+        let mut state = json!({ "schema_version": "1" });
+        assert!(migrate_state(&mut state).is_err());
+    }
+}
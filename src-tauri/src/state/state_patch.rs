@@ -0,0 +1,252 @@
+use crate::utility::get_json_value::get_json_value_mut;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use specta::Type;
+
+/// The top-level keys of the state that can be patched
+static PATCHABLE_KEYS: [&str; 2] = ["providers", "settings"];
+
+/// The fields managed by dedicated commands, they cannot be patched at any depth
+static PROTECTED_FIELDS: [&str; 4] = [
+    "password",
+    "signature",
+    "key_slots",
+    "two_factor_authentication",
+];
+
+/// The paths of the protected settings, their parents cannot be set as a whole
+static PROTECTED_SETTINGS_PATHS: [&str; 2] = [
+    "settings.security.signature",
+    "settings.security.two_factor_authentication",
+];
+
+/// An operation of a state patch, paths are dot-separated (e.g. `settings.theme.theme` or
+/// `providers.0.owner`)
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case", tag = "op")]
+pub enum StatePatchOperation {
+    /// Set the value at the path, `-` as the last segment of an array path appends the value
+    Set {
+        /// The path of the value
+        path: String,
+        /// The new value
+        value: Value,
+    },
+    /// Remove an element of an array
+    Remove {
+        /// The path of the element
+        path: String,
+    },
+}
+
+impl StatePatchOperation {
+    /// Get the path of the operation
+    ///
+    /// # Returns
+    ///
+    /// The dot-separated path
+    pub fn path(&self) -> &str {
+        match self {
+            StatePatchOperation::Set { path, .. } => path,
+            StatePatchOperation::Remove { path } => path,
+        }
+    }
+}
+
+/// Split a path and check that it can be patched
+///
+/// # Arguments
+///
+/// * `path` - The dot-separated path
+///
+/// # Returns
+///
+/// The path of the parent, if any, and the last segment
+fn split_path(path: &str) -> Result<(Option<&str>, &str), String> {
+    let segments = path.split('.').collect::<Vec<_>>();
+    if segments.iter().any(|segment| segment.is_empty()) {
+        return Err(format!("Invalid state path {}", path));
+    }
+
+    if !PATCHABLE_KEYS.contains(&segments[0]) {
+        return Err(format!("Cannot patch {}", path));
+    }
+
+    if segments
+        .iter()
+        .any(|segment| PROTECTED_FIELDS.contains(segment))
+    {
+        return Err(format!(
+            "Cannot patch {}, it is managed by a dedicated command",
+            path
+        ));
+    }
+
+    // setting a parent would overwrite the protected fields it contains
+    if PROTECTED_SETTINGS_PATHS.iter().any(|protected| {
+        protected
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.starts_with('.'))
+    }) {
+        return Err(format!(
+            "Cannot patch {}, it contains fields managed by a dedicated command",
+            path
+        ));
+    }
+
+    Ok(match path.rsplit_once('.') {
+        Some((parent, key)) => (Some(parent), key),
+        None => (None, path),
+    })
+}
+
+/// Apply a patch to the serialized state
+///
+/// Only existing fields can be set and only array elements can be added or removed so that the
+/// patched state keeps the shape of the typed state, the values are checked when the state is
+/// deserialized.
+///
+/// # Arguments
+///
+/// * `state` - The serialized state, an object with the patchable keys
+/// * `operations` - The operations, applied in order
+///
+/// # Returns
+///
+/// Nothing, the state is left partially patched on error
+pub fn apply_state_patch(
+    state: &mut Value,
+    operations: &[StatePatchOperation],
+) -> Result<(), String> {
+    for operation in operations {
+        let path = operation.path();
+        let (parent_path, key) = split_path(path)?;
+
+        let parent = match parent_path {
+            Some(parent_path) => get_json_value_mut(state, parent_path),
+            None => Some(&mut *state),
+        }
+        .ok_or(format!("Unknown state path {}", path))?;
+
+        match (operation, parent) {
+            (StatePatchOperation::Set { value, .. }, Value::Object(map)) => {
+                let field = map
+                    .get_mut(key)
+                    .ok_or(format!("Unknown state path {}", path))?;
+                *field = value.clone();
+            }
+            (StatePatchOperation::Set { value, .. }, Value::Array(array)) if key == "-" => {
+                array.push(value.clone());
+            }
+            (StatePatchOperation::Set { value, .. }, Value::Array(array)) => {
+                let element = key
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| array.get_mut(index))
+                    .ok_or(format!("Unknown state path {}", path))?;
+                *element = value.clone();
+            }
+            (StatePatchOperation::Remove { .. }, Value::Array(array)) => {
+                let index = key
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|index| *index < array.len())
+                    .ok_or(format!("Unknown state path {}", path))?;
+                array.remove(index);
+            }
+            (StatePatchOperation::Remove { .. }, Value::Object(_)) => {
+                return Err(format!(
+                    "Cannot remove {}, only array elements can be removed",
+                    path
+                ));
+            }
+            _ => return Err(format!("Unknown state path {}", path)),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn make_state() -> Value {
+        json!({
+            "providers": [{ "owner": "first" }, { "owner": "second" }],
+            "settings": {
+                "theme": { "theme": "light" },
+                "security": { "signature": "", "two_factor_authentication": { "enabled": false } },
+            },
+        })
+    }
+
+    fn set(path: &str, value: Value) -> StatePatchOperation {
+        StatePatchOperation::Set {
+            path: path.to_owned(),
+            value,
+        }
+    }
+
+    fn remove(path: &str) -> StatePatchOperation {
+        StatePatchOperation::Remove {
+            path: path.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_apply_state_patch() {
+        let mut state = make_state();
+
+        apply_state_patch(
+            &mut state,
+            &[
+                set("settings.theme.theme", json!("dark")),
+                set("providers.1.owner", json!("updated")),
+                set("providers.-", json!({ "owner": "third" })),
+                remove("providers.0"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(state["settings"]["theme"]["theme"], "dark");
+        assert_eq!(
+            state["providers"],
+            json!([{ "owner": "updated" }, { "owner": "third" }])
+        );
+    }
+
+    #[test]
+    fn test_apply_state_patch_invalid_paths() {
+        let invalid = [
+            set("settings.theme.unknown", json!(true)),
+            set("providers.5.owner", json!("x")),
+            set("providers.first", json!({})),
+            set("settings..theme", json!({})),
+            set("schema_version", json!(2)),
+            set("password", json!("")),
+            set("settings.security.signature", json!("")),
+            set(
+                "settings.security.two_factor_authentication.enabled",
+                json!(true),
+            ),
+            set("settings", json!({})),
+            set(
+                "settings.security",
+                json!({ "signature": "", "two_factor_authentication": { "enabled": false } }),
+            ),
+            remove("settings.theme"),
+            remove("providers.2"),
+        ];
+
+        for operation in invalid {
+            let mut state = make_state();
+            assert!(
+                apply_state_patch(&mut state, &[operation.clone()]).is_err(),
+                "{:?} should be refused",
+                operation
+            );
+        }
+    }
+}
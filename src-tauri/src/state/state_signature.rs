@@ -0,0 +1,227 @@
+use crate::crypt::{CryptData, CryptDataMode, verify_hmac};
+use crate::state::state_migration::schema_version;
+use crate::utility::canonical_json::to_canonical_json;
+use serde_json::Value;
+use tracing::debug;
+
+/// The first schema version whose signature covers the canonical serialization of the state
+pub static CANONICAL_SIGNATURE_SCHEMA_VERSION: u32 = 2;
+
+/// The placeholder the unversioned states were signed with, the default signature as serialized
+/// by the releases before the versioned envelope
+static UNVERSIONED_SIGNATURE_PLACEHOLDER: &str =
+    r#"{"data":"","mode":130,"salt":null,"related_keys":[]}"#;
+
+/// The placeholder the version 1 states were signed with, the default signature as serialized in
+/// the first version of the envelope
+static V1_SIGNATURE_PLACEHOLDER: &str = r#"{"version":2,"data":"","mode":130,"salt":null,"related_keys":[],"aead":null,"nonce":null,"kdf":{"id":"hkdf-sha3-512"},"encoding":"base64-unpadded"}"#;
+
+/// Get the content covered by the signature of a serialized state.
+///
+/// The signature covers the canonical serialization of the state without the signature field,
+/// states written before the canonical serialization are signed over their serialization as
+/// written with a default signature in place of the signature.
+///
+/// # Arguments
+///
+/// * `state` - The serialized state
+///
+/// # Returns
+///
+/// The signed content
+pub fn signed_content(state: &Value) -> Result<Vec<u8>, String> {
+    if schema_version(state)? < CANONICAL_SIGNATURE_SCHEMA_VERSION {
+        return legacy_signed_content(state);
+    }
+
+    let mut state = state.clone();
+    state
+        .pointer_mut("/settings/security")
+        .and_then(Value::as_object_mut)
+        .ok_or("Missing state security settings")?
+        .remove("signature");
+
+    Ok(to_canonical_json(&state)?.into_bytes())
+}
+
+/// Get the content covered by the signature of a state written before the canonical serialization
+///
+/// The placeholder is the default signature exactly as the release that wrote the state serialized
+/// it, it must not follow the current serialization of `CryptData`.
+///
+/// # Arguments
+///
+/// * `state` - The serialized state
+///
+/// # Returns
+///
+/// The signed content
+fn legacy_signed_content(state: &Value) -> Result<Vec<u8>, String> {
+    let placeholder = match schema_version(state)? {
+        0 => UNVERSIONED_SIGNATURE_PLACEHOLDER,
+        _ => V1_SIGNATURE_PLACEHOLDER,
+    };
+
+    let mut state = state.clone();
+    let signature = state
+        .pointer_mut("/settings/security/signature")
+        .ok_or("Missing state signature")?;
+    *signature = serde_json::from_str::<Value>(placeholder).map_err(|e| e.to_string())?;
+
+    serde_json::to_vec(&state).map_err(|e| e.to_string())
+}
+
+/// Get the signature of a serialized state
+///
+/// # Arguments
+///
+/// * `state` - The serialized state
+///
+/// # Returns
+///
+/// The signature
+pub fn read_signature(state: &Value) -> Result<String, String> {
+    let signature = state
+        .pointer("/settings/security/signature")
+        .ok_or("Missing state signature")?;
+
+    Ok(serde_json::from_value::<CryptData>(signature.clone())
+        .map_err(|e| e.to_string())?
+        .get_data_as_string())
+}
+
+/// Sign a serialized state
+///
+/// # Arguments
+///
+/// * `state` - The serialized state, the current signature is ignored
+/// * `key` - The key to sign the state with
+///
+/// # Returns
+///
+/// The signature
+pub fn sign_state(state: &Value, key: &[u8]) -> Result<CryptData, String> {
+    Ok(CryptData::new(
+        signed_content(state)?,
+        CryptDataMode::to_u8(vec![CryptDataMode::SignatureHash, CryptDataMode::Hmac]),
+        Some(key),
+        None,
+    ))
+}
+
+/// Verify the signature of a serialized state
+///
+/// The signature is verified on the serialized state as it was written, before any migration, so
+/// that it does not depend on the current shape of the state. States written before the canonical
+/// serialization are re-signed over it when their migration is saved.
+///
+/// # Arguments
+///
+/// * `state` - The serialized state
+/// * `key` - The key the state was signed with
+///
+/// # Returns
+///
+/// Nothing if the signature is valid
+pub fn verify_state_signature(state: &Value, key: &[u8]) -> Result<(), String> {
+    let state_signature = read_signature(state)?;
+    debug!("verify_state_signature: {}", state_signature);
+
+    if !verify_hmac(&signed_content(state)?, key, state_signature.as_str()) {
+        return Err("Invalid state signature".to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::state_migration::parse_state;
+    use serde_json::json;
+
+    #[test]
+    fn test_signed_content() {
+        let state = json!({
+            "schema_version": CANONICAL_SIGNATURE_SCHEMA_VERSION,
+            "providers": [],
+            "settings": { "security": { "signature": { "data": "first" }, "a": 1.0 } },
+        });
+        let reordered = json!({
+            "settings": { "security": { "a": 1, "signature": { "data": "second" } } },
+            "providers": [],
+            "schema_version": CANONICAL_SIGNATURE_SCHEMA_VERSION,
+        });
+
+        let content = signed_content(&state).unwrap();
+        assert_eq!(content, signed_content(&reordered).unwrap());
+        assert_eq!(
+            String::from_utf8(content).unwrap(),
+            "{\"providers\":[],\"schema_version\":2,\"settings\":{\"security\":{\"a\":1}}}"
+        );
+
+        // the legacy states are signed as written
+        let mut legacy_state = state.clone();
+        legacy_state["schema_version"] = json!(1);
+        let legacy_content = String::from_utf8(signed_content(&legacy_state).unwrap()).unwrap();
+        assert!(legacy_content.starts_with("{\"schema_version\":1,\"providers\":[]"));
+        assert!(legacy_content.contains(V1_SIGNATURE_PLACEHOLDER));
+        assert!(!legacy_content.contains("first"));
+
+        // the unversioned states were signed before the envelope existed
+        let mut unversioned_state = state.clone();
+        unversioned_state
+            .as_object_mut()
+            .unwrap()
+            .remove("schema_version");
+        let unversioned_content =
+            String::from_utf8(signed_content(&unversioned_state).unwrap()).unwrap();
+        assert!(unversioned_content.contains(
+            "\"signature\":{\"data\":\"\",\"mode\":130,\"salt\":null,\"related_keys\":[]}"
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_verify_state_signature_before_migration() {
+        let legacy_state =
+            serde_json::from_str::<Value>(include_str!("fixtures/state_v0.json")).unwrap();
+        assert!(verify_state_signature(&legacy_state, b"password").is_ok());
+        assert!(verify_state_signature(&legacy_state, b"wrong password").is_err());
+
+        let (_, migrated) = parse_state(legacy_state.clone()).unwrap();
+        assert!(migrated);
+
+        let state = serde_json::from_str::<Value>(include_str!("fixtures/state_v1.json")).unwrap();
+        assert!(verify_state_signature(&state, &[7; 32]).is_ok());
+
+        let mut tampered_state = state.clone();
+        tampered_state["providers"][0]["owner"] = Value::from("attacker@example.com");
+        assert!(verify_state_signature(&tampered_state, &[7; 32]).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_verify_canonical_state_signature() {
+        let state = serde_json::from_str::<Value>(include_str!("fixtures/state_v2.json")).unwrap();
+        assert!(verify_state_signature(&state, &[7; 32]).is_ok());
+        assert!(verify_state_signature(&state, &[8; 32]).is_err());
+
+        // the signature does not depend on the key order nor on the formatting
+        let mut reordered_state = state.clone();
+        let object = reordered_state.as_object_mut().unwrap();
+        let providers = object.remove("providers").unwrap();
+        object.insert("providers".to_owned(), providers);
+        let reordered_state =
+            serde_json::from_str::<Value>(&serde_json::to_string(&reordered_state).unwrap())
+                .unwrap();
+        assert!(verify_state_signature(&reordered_state, &[7; 32]).is_ok());
+
+        let mut tampered_state = state.clone();
+        tampered_state["providers"][0]["owner"] = Value::from("attacker@example.com");
+        assert!(verify_state_signature(&tampered_state, &[7; 32]).is_err());
+
+        // downgrading the schema version falls back to the legacy content, which was never signed
+        let mut downgraded_state = state.clone();
+        downgraded_state["schema_version"] = Value::from(1);
+        assert!(verify_state_signature(&downgraded_state, &[7; 32]).is_err());
+    }
+}
@@ -0,0 +1,535 @@
+use crate::utility::atomic_file::write_atomically;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// The number of attempts kept in the history
+pub static UNLOCK_ATTEMPTS_HISTORY_LENGTH: usize = 20;
+
+/// The delay imposed after the first failed attempt in seconds, doubled after each failed attempt
+static BASE_RETRY_DELAY: u64 = 1;
+
+/// The longest delay imposed between two attempts in seconds
+static MAX_RETRY_DELAY: u64 = 300;
+
+/// Serializes the password checks so that the delays cannot be bypassed by concurrent attempts
+static UNLOCK_ATTEMPTS_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// A password check
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Type)]
+pub struct UnlockAttempt {
+    /// When the attempt was made (utc unix timestamp)
+    pub timestamp: u64,
+    /// Whether the password was correct
+    pub succeeded: bool,
+}
+
+/// The password checks of a vault.
+///
+/// The attempts are stored in clear next to the state file as they must be read before the vault
+/// is unlocked, for the same reason the lockout threshold lives here instead of in the settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Type)]
+pub struct UnlockAttempts {
+    /// The number of failed attempts since the last successful one
+    pub failed_attempts: u32,
+    /// The number of consecutive failed attempts after which only the recovery key can unlock
+    /// the vault, `None` to never lock the vault out
+    pub lockout_threshold: Option<u32>,
+    /// The latest attempts, the newest last
+    pub history: Vec<UnlockAttempt>,
+    /// The number of wrong or missing second factor codes since the last accepted one, they
+    /// impose their own delay as a correct password does not prove the code
+    #[serde(default)]
+    pub failed_second_factor_attempts: u32,
+    /// When the last second factor code was refused (utc unix timestamp)
+    #[serde(default)]
+    pub last_second_factor_failure: Option<u64>,
+    /// Whether a code was asked for since the last accepted one, a check without a code after it
+    /// counts as a failed attempt
+    #[serde(default)]
+    pub second_factor_requested: bool,
+}
+
+/// Get the delay imposed after a number of failed attempts
+///
+/// # Arguments
+///
+/// * `failed_attempts` - The number of consecutive failed attempts
+///
+/// # Returns
+///
+/// The delay in seconds
+fn retry_delay_after(failed_attempts: u32) -> u64 {
+    match failed_attempts {
+        0 => 0,
+        failed_attempts => BASE_RETRY_DELAY
+            .checked_shl(failed_attempts - 1)
+            .unwrap_or(MAX_RETRY_DELAY)
+            .min(MAX_RETRY_DELAY),
+    }
+}
+
+impl UnlockAttempts {
+    /// Get the delay imposed after the last failed attempt
+    ///
+    /// # Returns
+    ///
+    /// The delay in seconds
+    pub fn retry_delay(&self) -> u64 {
+        retry_delay_after(self.failed_attempts)
+    }
+
+    /// Get the delay imposed after the last refused second factor code
+    ///
+    /// # Returns
+    ///
+    /// The delay in seconds
+    pub fn second_factor_retry_delay(&self) -> u64 {
+        retry_delay_after(self.failed_second_factor_attempts)
+    }
+
+    /// Check whether the vault is locked out after too many failed attempts
+    ///
+    /// # Returns
+    ///
+    /// Whether the vault is locked out
+    pub fn is_locked_out(&self) -> bool {
+        self.lockout_threshold
+            .is_some_and(|threshold| self.failed_attempts >= threshold)
+    }
+
+    /// Check whether a new attempt can be made
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time (utc unix timestamp)
+    ///
+    /// # Returns
+    ///
+    /// Nothing if the attempt can be made, the reason it is refused otherwise
+    pub fn check(&self, now: u64) -> Result<(), String> {
+        if self.is_locked_out() {
+            return Err(
+                "Too many failed attempts, use the recovery key to unlock the vault".to_owned(),
+            );
+        }
+
+        let last_failure = self
+            .history
+            .last()
+            .filter(|attempt| !attempt.succeeded)
+            .map(|attempt| attempt.timestamp);
+        let Some(last_failure) = last_failure else {
+            return Ok(());
+        };
+
+        let retry_at = last_failure.saturating_add(self.retry_delay());
+        if now < retry_at {
+            return Err(format!(
+                "Too many failed attempts, try again in {} seconds",
+                retry_at - now
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Record an attempt
+    ///
+    /// # Arguments
+    ///
+    /// * `succeeded` - Whether the password was correct
+    /// * `now` - The current time (utc unix timestamp)
+    pub fn record(&mut self, succeeded: bool, now: u64) {
+        self.failed_attempts = if succeeded {
+            0
+        } else {
+            self.failed_attempts.saturating_add(1)
+        };
+
+        self.history.push(UnlockAttempt {
+            timestamp: now,
+            succeeded,
+        });
+        let overflow = self
+            .history
+            .len()
+            .saturating_sub(UNLOCK_ATTEMPTS_HISTORY_LENGTH);
+        self.history.drain(..overflow);
+    }
+
+    /// Check whether a new second factor code can be checked
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time (utc unix timestamp)
+    ///
+    /// # Returns
+    ///
+    /// Nothing if the code can be checked, the reason it is refused otherwise
+    pub fn check_second_factor(&self, now: u64) -> Result<(), String> {
+        let Some(last_failure) = self.last_second_factor_failure else {
+            return Ok(());
+        };
+
+        let retry_at = last_failure.saturating_add(self.second_factor_retry_delay());
+        if now < retry_at {
+            return Err(format!(
+                "Too many invalid two factor authentication codes, try again in {} seconds",
+                retry_at - now
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Record a second factor check
+    ///
+    /// # Arguments
+    ///
+    /// * `succeeded` - Whether the code was accepted
+    /// * `now` - The current time (utc unix timestamp)
+    pub fn record_second_factor(&mut self, succeeded: bool, now: u64) {
+        if succeeded {
+            self.failed_second_factor_attempts = 0;
+            self.last_second_factor_failure = None;
+            self.second_factor_requested = false;
+        } else {
+            self.failed_second_factor_attempts =
+                self.failed_second_factor_attempts.saturating_add(1);
+            self.last_second_factor_failure = Some(now);
+            self.second_factor_requested = true;
+        }
+    }
+}
+
+/// Get the current time
+///
+/// # Returns
+///
+/// The current time (utc unix timestamp)
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// Get the path of the unlock attempts of a state file
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// The path of the unlock attempts
+pub fn unlock_attempts_path(state_file: &Path) -> PathBuf {
+    let mut file_name = state_file.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".attempts");
+
+    state_file.with_file_name(file_name)
+}
+
+/// Load the unlock attempts of a state file
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// The unlock attempts, empty if none were recorded
+pub async fn load_unlock_attempts(state_file: &Path) -> UnlockAttempts {
+    let Ok(content) = tokio::fs::read(unlock_attempts_path(state_file)).await else {
+        return UnlockAttempts::default();
+    };
+
+    serde_json::from_slice::<UnlockAttempts>(&content)
+        .inspect_err(|e| warn!("Ignoring the unreadable unlock attempts: {}", e))
+        .unwrap_or_default()
+}
+
+/// Save the unlock attempts of a state file
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+/// * `attempts` - The unlock attempts
+///
+/// # Returns
+///
+/// Nothing
+pub async fn save_unlock_attempts(
+    state_file: &Path,
+    attempts: &UnlockAttempts,
+) -> Result<(), String> {
+    let json = serde_json::to_vec(attempts).map_err(|e| e.to_string())?;
+
+    write_atomically(&unlock_attempts_path(state_file), &json).await
+}
+
+/// Run a password check, refusing it while the previous failures impose a delay and recording its
+/// outcome
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file of the vault
+/// * `attempt` - The password check
+///
+/// # Returns
+///
+/// The result of the password check
+pub async fn guard_unlock_attempt<T, F, Fut>(state_file: &Path, attempt: F) -> Result<T, String>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let _guard = UNLOCK_ATTEMPTS_LOCK.lock().await;
+
+    let mut attempts = load_unlock_attempts(state_file).await;
+    attempts.check(now())?;
+
+    let result = attempt().await;
+
+    attempts.record(result.is_ok(), now());
+    save_unlock_attempts(state_file, &attempts).await?;
+
+    result
+}
+
+/// Run a second factor check, refusing it while the previous wrong codes impose a delay and
+/// recording its outcome
+///
+/// The first check without a code only asks whether a code is needed and is not counted, any
+/// other refused check counts as a failed attempt, the missing codes included.
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file of the vault
+/// * `has_code` - Whether a code was given
+/// * `attempt` - The second factor check
+///
+/// # Returns
+///
+/// The result of the second factor check
+pub async fn guard_second_factor_attempt<T, F, Fut>(
+    state_file: &Path,
+    has_code: bool,
+    attempt: F,
+) -> Result<T, String>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let _guard = UNLOCK_ATTEMPTS_LOCK.lock().await;
+
+    let mut attempts = load_unlock_attempts(state_file).await;
+    let counted = has_code || attempts.second_factor_requested;
+    if counted {
+        attempts.check_second_factor(now())?;
+    }
+
+    let result = attempt().await;
+
+    match (&result, counted) {
+        (Ok(_), _) => attempts.record_second_factor(true, now()),
+        (Err(_), false) => attempts.second_factor_requested = true,
+        (Err(_), true) => attempts.record_second_factor(false, now()),
+    }
+    save_unlock_attempts(state_file, &attempts).await?;
+
+    result
+}
+
+/// Update the unlock attempts of a state file
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+/// * `update` - The function updating the unlock attempts
+///
+/// # Returns
+///
+/// The updated unlock attempts
+pub async fn update_unlock_attempts<F>(
+    state_file: &Path,
+    update: F,
+) -> Result<UnlockAttempts, String>
+where
+    F: FnOnce(&mut UnlockAttempts),
+{
+    let _guard = UNLOCK_ATTEMPTS_LOCK.lock().await;
+
+    let mut attempts = load_unlock_attempts(state_file).await;
+    update(&mut attempts);
+    save_unlock_attempts(state_file, &attempts).await?;
+
+    Ok(attempts)
+}
+
+/// Record a successful unlock that did not use the password, lifting any delay or lockout
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// Nothing
+pub async fn reset_unlock_attempts(state_file: &Path) -> Result<(), String> {
+    update_unlock_attempts(state_file, |attempts| attempts.record(true, now())).await?;
+
+    Ok(())
+}
+
+/// Remove the unlock attempts of a state file
+///
+/// # Arguments
+///
+/// * `state_file` - The path to the state file
+///
+/// # Returns
+///
+/// Nothing
+pub async fn remove_unlock_attempts(state_file: &Path) -> Result<(), String> {
+    match tokio::fs::remove_file(unlock_attempts_path(state_file)).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    #[test]
+    fn test_retry_delay() {
+        let mut attempts = UnlockAttempts::default();
+        assert!(attempts.check(100).is_ok());
+
+        attempts.record(false, 100);
+        assert_eq!(attempts.retry_delay(), 1);
+        assert!(attempts.check(100).is_err());
+        assert!(attempts.check(101).is_ok());
+
+        for _ in 0..3 {
+            attempts.record(false, 100);
+        }
+        assert_eq!(attempts.retry_delay(), 8);
+        assert!(attempts.check(107).is_err());
+        assert!(attempts.check(108).is_ok());
+
+        for _ in 0..60 {
+            attempts.record(false, 100);
+        }
+        assert_eq!(attempts.retry_delay(), MAX_RETRY_DELAY);
+        assert_eq!(attempts.history.len(), UNLOCK_ATTEMPTS_HISTORY_LENGTH);
+
+        attempts.record(true, 200);
+        assert_eq!(attempts.failed_attempts, 0);
+        assert!(attempts.check(200).is_ok());
+    }
+
+    #[test]
+    fn test_lockout() {
+        let mut attempts = UnlockAttempts {
+            lockout_threshold: Some(3),
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            attempts.record(false, 100);
+        }
+        assert!(!attempts.is_locked_out());
+
+        attempts.record(false, 100);
+        assert!(attempts.is_locked_out());
+        assert!(attempts.check(100_000).is_err());
+
+        attempts.record(true, 100_000);
+        assert!(!attempts.is_locked_out());
+    }
+
+    #[test]
+    fn test_second_factor_retry_delay() {
+        let mut attempts = UnlockAttempts::default();
+        assert!(attempts.check_second_factor(100).is_ok());
+
+        attempts.record_second_factor(false, 100);
+        attempts.record_second_factor(false, 100);
+        assert_eq!(attempts.second_factor_retry_delay(), 2);
+        assert!(attempts.check_second_factor(101).is_err());
+        assert!(attempts.check_second_factor(102).is_ok());
+
+        // a correct password does not lift the delay of the codes
+        attempts.record(true, 102);
+        assert_eq!(attempts.failed_second_factor_attempts, 2);
+        assert!(attempts.check(102).is_ok());
+
+        attempts.record_second_factor(true, 102);
+        assert_eq!(attempts.failed_second_factor_attempts, 0);
+        assert!(!attempts.second_factor_requested);
+        assert!(attempts.check_second_factor(102).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_guard_second_factor_attempt() {
+        let directory = std::env::temp_dir().join(format!(
+            "storage-orchestra-second-factor-attempts-{}",
+            rand::rng().next_u64()
+        ));
+        tokio::fs::create_dir_all(&directory).await.unwrap();
+        let state_file = directory.join("state.json");
+        let refuse = || async { Err::<(), String>("refused".to_owned()) };
+
+        // asking whether a code is needed is not an attempt
+        assert!(
+            guard_second_factor_attempt(&state_file, false, refuse)
+                .await
+                .is_err()
+        );
+        let attempts = load_unlock_attempts(&state_file).await;
+        assert_eq!(attempts.failed_second_factor_attempts, 0);
+        assert!(attempts.second_factor_requested);
+
+        // asking again is
+        assert_eq!(
+            guard_second_factor_attempt(&state_file, false, refuse).await,
+            Err("refused".to_owned())
+        );
+        assert_eq!(
+            load_unlock_attempts(&state_file)
+                .await
+                .failed_second_factor_attempts,
+            1
+        );
+
+        // the next code waits for the delay
+        let error = guard_second_factor_attempt(&state_file, true, || async { Ok(()) })
+            .await
+            .unwrap_err();
+        assert!(error.starts_with("Too many invalid two factor authentication codes"));
+
+        update_unlock_attempts(&state_file, |attempts| {
+            attempts.last_second_factor_failure = Some(0);
+        })
+        .await
+        .unwrap();
+        assert!(
+            guard_second_factor_attempt(&state_file, true, || async { Ok(()) })
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            load_unlock_attempts(&state_file).await,
+            UnlockAttempts::default()
+        );
+
+        tokio::fs::remove_dir_all(&directory).await.unwrap();
+    }
+}
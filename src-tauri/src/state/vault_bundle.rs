@@ -0,0 +1,571 @@
+use crate::crypt::{CryptData, CryptDataMode};
+use crate::state::account_registry::new_account_id;
+use crate::state::provider_data::{AccountCredential, ProviderData};
+use crate::state::settings::Settings;
+use crate::state::settings::general_behaviour::GeneralBehaviour;
+use crate::state::settings::security::EncryptionSettings;
+use crate::state::settings::theme::ThemeSettings;
+use crate::state::storage_provider::StorageProvider;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use specta::Type;
+
+/// The version of the bundle format written by this build
+pub static VAULT_BUNDLE_FORMAT_VERSION: u32 = 3;
+
+/// How an imported bundle is applied to the vault
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Type)]
+pub enum VaultImportMode {
+    /// The imported accounts are added to the vault, accounts present in both keep the tokens
+    /// expiring last, the local settings are kept
+    #[serde(rename = "merge")]
+    Merge,
+    /// The accounts and the settings of the vault are replaced by the imported ones
+    #[serde(rename = "replace")]
+    Replace,
+}
+
+/// The description of the content of a bundle
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Type)]
+pub struct VaultBundleManifest {
+    /// The version of the bundle format
+    pub format_version: u32,
+    /// The version of the application that exported the bundle
+    pub app_version: String,
+    /// The export date (utc unix timestamp)
+    pub created_at: u64,
+    /// The accounts in the bundle
+    pub accounts: Vec<BundledAccount>,
+}
+
+/// An account listed in the manifest of a bundle
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Type)]
+pub struct BundledAccount {
+    /// The provider of the account
+    pub provider: StorageProvider,
+    /// The owner of the account (email or username)
+    pub owner: String,
+}
+
+/// An account in a bundle, the secrets are in clear as the whole payload is sealed
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BundledProvider {
+    /// The id of the account in the exporting vault, the settings refer to the accounts by id
+    pub id: String,
+    /// The provider of the account
+    pub provider: StorageProvider,
+    /// The owner of the account (email or username)
+    pub owner: String,
+    /// The credential of the account
+    pub credential: BundledCredential,
+    /// The name of the account as shown by the provider
+    pub display_name: Option<String>,
+    /// The url of the avatar of the account
+    pub avatar_url: Option<String>,
+}
+
+/// The credential of an account in a bundle, mirrors `AccountCredential` with the secrets in clear
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum BundledCredential {
+    /// OAuth tokens
+    #[serde(rename = "oauth")]
+    OAuth {
+        /// The access token
+        access_token: String,
+        /// The refresh token
+        refresh_token: String,
+        /// The expiry date of the access token (utc unix timestamp)
+        expiry: u64,
+        /// The type of the access token
+        token_type: String,
+        /// The scopes granted to the application
+        scopes: Vec<String>,
+    },
+    /// A static key pair
+    KeyPair {
+        /// The access key id
+        access_key_id: String,
+        /// The secret access key
+        secret_access_key: String,
+    },
+    /// A username and a password
+    Password {
+        /// The username
+        username: String,
+        /// The password
+        password: String,
+        /// Whether the password is an application specific password
+        app_password: bool,
+    },
+}
+
+impl BundledCredential {
+    /// Get the bundled form of a credential
+    ///
+    /// # Arguments
+    ///
+    /// * `credential` - The credential of the account
+    /// * `decrypt` - Decrypts a secret of the credential
+    ///
+    /// # Returns
+    ///
+    /// The bundled credential
+    pub fn from_credential(
+        credential: &AccountCredential,
+        decrypt: impl Fn(&CryptData) -> Result<String, String>,
+    ) -> Result<Self, String> {
+        Ok(match credential {
+            AccountCredential::OAuth {
+                access_token,
+                refresh_token,
+                expiry,
+                token_type,
+                scopes,
+            } => BundledCredential::OAuth {
+                access_token: decrypt(access_token)?,
+                refresh_token: decrypt(refresh_token)?,
+                expiry: *expiry,
+                token_type: token_type.clone(),
+                scopes: scopes.clone(),
+            },
+            AccountCredential::KeyPair {
+                access_key_id,
+                secret_access_key,
+            } => BundledCredential::KeyPair {
+                access_key_id: access_key_id.clone(),
+                secret_access_key: decrypt(secret_access_key)?,
+            },
+            AccountCredential::Password {
+                username,
+                password,
+                app_password,
+            } => BundledCredential::Password {
+                username: username.clone(),
+                password: decrypt(password)?,
+                app_password: *app_password,
+            },
+        })
+    }
+
+    /// Restore the credential of an account
+    ///
+    /// # Arguments
+    ///
+    /// * `encrypt` - Encrypts a secret of the credential
+    ///
+    /// # Returns
+    ///
+    /// The credential of the account
+    pub fn into_credential(self, encrypt: impl Fn(String) -> CryptData) -> AccountCredential {
+        match self {
+            BundledCredential::OAuth {
+                access_token,
+                refresh_token,
+                expiry,
+                token_type,
+                scopes,
+            } => AccountCredential::OAuth {
+                access_token: encrypt(access_token),
+                refresh_token: encrypt(refresh_token),
+                expiry,
+                token_type,
+                scopes,
+            },
+            BundledCredential::KeyPair {
+                access_key_id,
+                secret_access_key,
+            } => AccountCredential::KeyPair {
+                access_key_id,
+                secret_access_key: encrypt(secret_access_key),
+            },
+            BundledCredential::Password {
+                username,
+                password,
+                app_password,
+            } => AccountCredential::Password {
+                username,
+                password: encrypt(password),
+                app_password,
+            },
+        }
+    }
+}
+
+/// The settings in a bundle
+///
+/// The two factor authentication and the signature belong to the local vault and are never
+/// exported.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BundledSettings {
+    /// The theme settings
+    pub theme: ThemeSettings,
+    /// The general behaviour settings
+    pub general_behaviour: GeneralBehaviour,
+    /// The state file encryption settings
+    pub encryption: EncryptionSettings,
+}
+
+impl BundledSettings {
+    /// Get the exportable part of the settings
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The settings of the vault
+    ///
+    /// # Returns
+    ///
+    /// The bundled settings
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            theme: settings.theme.clone(),
+            general_behaviour: settings.general_behaviour.clone(),
+            encryption: settings.security.encryption.clone(),
+        }
+    }
+
+    /// Apply the bundled settings, the local security settings are kept
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The settings of the vault
+    pub fn apply(self, settings: &mut Settings) {
+        settings.theme = self.theme;
+        settings.general_behaviour = self.general_behaviour;
+        settings.security.encryption = self.encryption;
+    }
+}
+
+/// The content of a bundle
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VaultBundleContents {
+    /// The description of the bundle
+    pub manifest: VaultBundleManifest,
+    /// The accounts
+    pub providers: Vec<BundledProvider>,
+    /// The settings
+    pub settings: BundledSettings,
+}
+
+/// A bundle as written to disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VaultBundle {
+    /// The version of the bundle format
+    format_version: u32,
+    /// The serialized content encrypted with a key derived from the export passphrase
+    payload: CryptData,
+}
+
+/// Seal the content of a bundle with the export passphrase
+///
+/// # Arguments
+///
+/// * `contents` - The content of the bundle
+/// * `passphrase` - The export passphrase
+///
+/// # Returns
+///
+/// The bundle file content
+pub fn seal_bundle(contents: &VaultBundleContents, passphrase: &str) -> Result<String, String> {
+    if passphrase.is_empty() {
+        return Err("The export passphrase cannot be empty".to_owned());
+    }
+
+    let json = serde_json::to_vec(contents).map_err(|e| e.to_string())?;
+
+    // the passphrase has low entropy, the memory-hard key derivation is used
+    let bundle = VaultBundle {
+        format_version: VAULT_BUNDLE_FORMAT_VERSION,
+        payload: CryptData::new(
+            json,
+            CryptDataMode::to_u8(vec![CryptDataMode::Encrypt, CryptDataMode::Encode]),
+            Some(passphrase.as_bytes()),
+            None,
+        ),
+    };
+
+    serde_json::to_string(&bundle).map_err(|e| e.to_string())
+}
+
+/// Open a bundle sealed with the export passphrase
+///
+/// # Arguments
+///
+/// * `bundle` - The bundle file content
+/// * `passphrase` - The export passphrase
+///
+/// # Returns
+///
+/// The content of the bundle
+pub fn open_bundle(bundle: &str, passphrase: &str) -> Result<VaultBundleContents, String> {
+    let bundle = serde_json::from_str::<VaultBundle>(bundle)
+        .map_err(|_| "The file is not a vault export".to_owned())?;
+
+    if bundle.format_version > VAULT_BUNDLE_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported vault export version {}",
+            bundle.format_version
+        ));
+    }
+
+    let json = bundle
+        .payload
+        .decrypt_stored_data(passphrase.as_bytes())
+        .map_err(|_| "Invalid export passphrase".to_owned())?;
+    let mut contents = serde_json::from_slice::<Value>(&json).map_err(|e| e.to_string())?;
+
+    if bundle.format_version < 2 {
+        upgrade_v1_contents(&mut contents);
+    }
+    if bundle.format_version < 3 {
+        upgrade_v2_contents(&mut contents);
+    }
+
+    serde_json::from_value::<VaultBundleContents>(contents).map_err(|e| e.to_string())
+}
+
+/// Upgrade the content of a bundle written before the credential kinds, its accounts all use
+/// OAuth tokens
+///
+/// # Arguments
+///
+/// * `contents` - The serialized content of the bundle, upgraded in place
+fn upgrade_v1_contents(contents: &mut Value) {
+    let Some(providers) = contents.get_mut("providers").and_then(Value::as_array_mut) else {
+        return;
+    };
+
+    for provider in providers.iter_mut().filter_map(Value::as_object_mut) {
+        let credential = json!({
+            "kind": "oauth",
+            "access_token": provider.remove("access_token"),
+            "refresh_token": provider.remove("refresh_token"),
+            "expiry": provider.remove("expiry"),
+            "token_type": "Bearer",
+            "scopes": [],
+        });
+        provider.insert("credential".to_owned(), credential);
+    }
+}
+
+/// Upgrade the content of a bundle written before the accounts were exported with their id, the
+/// accounts are given an id and the default page refers to its account by id
+///
+/// # Arguments
+///
+/// * `contents` - The serialized content of the bundle, upgraded in place
+fn upgrade_v2_contents(contents: &mut Value) {
+    let page = contents
+        .pointer("/settings/general_behaviour/default_page/providers")
+        .cloned()
+        .unwrap_or_default();
+
+    let mut account_id = None;
+    if let Some(providers) = contents.get_mut("providers").and_then(Value::as_array_mut) {
+        for provider in providers.iter_mut().filter_map(Value::as_object_mut) {
+            let id = new_account_id();
+            if provider.get("provider") == page.get("provider")
+                && provider.get("owner") == page.get("owner")
+            {
+                account_id = Some(id.clone());
+            }
+            provider.insert("id".to_owned(), json!(id));
+        }
+    }
+
+    let Some(page) = contents.pointer_mut("/settings/general_behaviour/default_page") else {
+        return;
+    };
+    if page.get("providers").is_some() {
+        *page = match account_id {
+            Some(account_id) => json!({ "providers": { "account_id": account_id } }),
+            None => json!({ "general": "dashboard" }),
+        };
+    }
+}
+
+/// Merge the imported accounts in the accounts of the vault
+///
+/// # Arguments
+///
+/// * `providers` - The accounts of the vault
+/// * `imported` - The imported accounts, already encrypted with the local data key
+/// * `mode` - How the imported accounts are applied
+pub fn merge_providers(
+    providers: &mut Vec<ProviderData>,
+    imported: Vec<ProviderData>,
+    mode: VaultImportMode,
+) {
+    if mode == VaultImportMode::Replace {
+        providers.clear();
+    }
+
+    // an account is only registered once, the replaced accounts keep their id
+    for mut provider_data in imported {
+        match providers.iter_mut().find(|existing| {
+            existing.provider == provider_data.provider && existing.owner == provider_data.owner
+        }) {
+            Some(existing) if existing.credential.expiry() < provider_data.credential.expiry() => {
+                provider_data.id = existing.id.clone();
+                *existing = provider_data;
+            }
+            Some(_) => {}
+            None => providers.push(provider_data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::settings::general_behaviour::{DefaultPageGroups, ProviderPage};
+
+    fn make_contents() -> VaultBundleContents {
+        VaultBundleContents {
+            manifest: VaultBundleManifest {
+                format_version: VAULT_BUNDLE_FORMAT_VERSION,
+                app_version: "0.1.0".to_owned(),
+                created_at: 1,
+                accounts: vec![BundledAccount {
+                    provider: StorageProvider::Google,
+                    owner: "user@example.com".to_owned(),
+                }],
+            },
+            providers: vec![BundledProvider {
+                id: "1".to_owned(),
+                provider: StorageProvider::Google,
+                owner: "user@example.com".to_owned(),
+                credential: BundledCredential::OAuth {
+                    access_token: "access".to_owned(),
+                    refresh_token: "refresh".to_owned(),
+                    expiry: 1,
+                    token_type: "Bearer".to_owned(),
+                    scopes: vec!["email".to_owned()],
+                },
+                display_name: Some("User".to_owned()),
+                avatar_url: None,
+            }],
+            settings: BundledSettings::from_settings(&Settings::default()),
+        }
+    }
+
+    fn make_provider_data(owner: &str, expiry: u64) -> ProviderData {
+        ProviderData {
+            id: format!("{}-{}", owner, expiry),
+            credential: AccountCredential::OAuth {
+                access_token: CryptData::default(),
+                refresh_token: CryptData::default(),
+                expiry,
+                token_type: "Bearer".to_owned(),
+                scopes: Vec::new(),
+            },
+            owner: owner.to_owned(),
+            provider: StorageProvider::Google,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_seal_and_open_bundle() {
+        let contents = make_contents();
+        let bundle = seal_bundle(&contents, "passphrase").unwrap();
+
+        assert!(!bundle.contains("refresh"));
+        assert_eq!(open_bundle(&bundle, "passphrase").unwrap(), contents);
+        assert_eq!(
+            open_bundle(&bundle, "wrong passphrase").unwrap_err(),
+            "Invalid export passphrase"
+        );
+        assert!(open_bundle("{}", "passphrase").is_err());
+        assert!(seal_bundle(&contents, "").is_err());
+    }
+
+    #[test]
+    fn test_open_v1_bundle() {
+        let mut contents = json!({
+            "manifest": {
+                "format_version": 1,
+                "app_version": "0.1.0",
+                "created_at": 1,
+                "accounts": [{ "provider": "google", "owner": "user@example.com" }],
+            },
+            "providers": [{
+                "provider": "google",
+                "owner": "user@example.com",
+                "expiry": 1,
+                "access_token": "access",
+                "refresh_token": "refresh",
+            }],
+            "settings": serde_json::to_value(BundledSettings::from_settings(&Settings::default()))
+                .unwrap(),
+        });
+        contents["settings"]["general_behaviour"]["default_page"] =
+            json!({ "providers": { "provider": "google", "owner": "user@example.com" } });
+        let bundle = VaultBundle {
+            format_version: 1,
+            payload: CryptData::new(
+                serde_json::to_vec(&contents).unwrap(),
+                CryptDataMode::to_u8(vec![CryptDataMode::Encrypt, CryptDataMode::Encode]),
+                Some(b"passphrase"),
+                None,
+            ),
+        };
+
+        let contents = open_bundle(&serde_json::to_string(&bundle).unwrap(), "passphrase").unwrap();
+        assert_eq!(
+            contents.providers[0].credential,
+            BundledCredential::OAuth {
+                access_token: "access".to_owned(),
+                refresh_token: "refresh".to_owned(),
+                expiry: 1,
+                token_type: "Bearer".to_owned(),
+                scopes: Vec::new(),
+            }
+        );
+        assert_eq!(contents.providers[0].display_name, None);
+
+        // the default page refers to the account by the id it was given
+        assert!(!contents.providers[0].id.is_empty());
+        assert_eq!(
+            contents.settings.general_behaviour.default_page,
+            DefaultPageGroups::Providers(ProviderPage {
+                account_id: contents.providers[0].id.clone(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_merge_providers() {
+        let mut providers = vec![
+            make_provider_data("first", 10),
+            make_provider_data("second", 10),
+        ];
+
+        merge_providers(
+            &mut providers,
+            vec![
+                make_provider_data("first", 20),
+                make_provider_data("second", 5),
+                make_provider_data("third", 5),
+            ],
+            VaultImportMode::Merge,
+        );
+        let expiries = providers
+            .iter()
+            .map(|data| (data.owner.as_str(), data.credential.expiry().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(expiries, vec![("first", 20), ("second", 10), ("third", 5)]);
+        assert_eq!(providers[0].id, "first-10");
+
+        merge_providers(
+            &mut providers,
+            vec![
+                make_provider_data("fourth", 5),
+                make_provider_data("fourth", 1),
+            ],
+            VaultImportMode::Replace,
+        );
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].owner, "fourth");
+    }
+}
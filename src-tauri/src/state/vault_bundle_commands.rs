@@ -0,0 +1,173 @@
+use crate::crypt::{CryptData, CryptDataMode, KdfAlgorithm};
+use crate::secret_manager::read_data_key;
+use crate::state::account_registry::{new_account_id, reset_orphaned_default_page};
+use crate::state::emit_state_changed;
+use crate::state::provider_data::ProviderData;
+use crate::state::save;
+use crate::state::settings::security_commands::check_password;
+use crate::state::state::{AppState, AppStateDeepKeys};
+use crate::state::vault_bundle::{
+    BundledAccount, BundledCredential, BundledProvider, BundledSettings,
+    VAULT_BUNDLE_FORMAT_VERSION, VaultBundleContents, VaultBundleManifest, VaultImportMode,
+    merge_providers, open_bundle, seal_bundle,
+};
+use specta::specta;
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, State, command};
+use tracing::debug;
+
+/// Export the accounts and the settings of the vault as a bundle sealed with an export passphrase
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `state` - The application state
+/// * `password` - The password of the vault
+/// * `passphrase` - The export passphrase, required to import the bundle
+///
+/// # Returns
+///
+/// The bundle file content
+#[command]
+#[specta]
+pub async fn export_vault(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    password: String,
+    passphrase: String,
+) -> Result<String, String> {
+    check_password(app.clone(), state.clone(), password).await?;
+
+    let readable_state = state.read().await;
+    let data_key = read_data_key().await?;
+
+    let decrypt = |secret: &CryptData| -> Result<String, String> {
+        let secret = secret.decrypt_stored_data(data_key.as_slice())?;
+        String::from_utf8(secret).map_err(|e| e.to_string())
+    };
+
+    // the quota snapshots are local to the device and are not exported
+    let providers = readable_state
+        .providers
+        .iter()
+        .map(|provider_data| {
+            Ok(BundledProvider {
+                id: provider_data.id.clone(),
+                provider: provider_data.provider.clone(),
+                owner: provider_data.owner.clone(),
+                credential: BundledCredential::from_credential(&provider_data.credential, decrypt)?,
+                display_name: provider_data.display_name.clone(),
+                avatar_url: provider_data.avatar_url.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    drop(data_key);
+
+    let contents = VaultBundleContents {
+        manifest: VaultBundleManifest {
+            format_version: VAULT_BUNDLE_FORMAT_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_owned(),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            accounts: providers
+                .iter()
+                .map(|provider| BundledAccount {
+                    provider: provider.provider.clone(),
+                    owner: provider.owner.clone(),
+                })
+                .collect(),
+        },
+        providers,
+        settings: BundledSettings::from_settings(&readable_state.settings),
+    };
+    drop(readable_state);
+
+    debug!("Exporting {} accounts", contents.providers.len());
+    seal_bundle(&contents, passphrase.as_str())
+}
+
+/// Import a bundle in the vault, the imported tokens are encrypted with the data key of the vault
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `state` - The application state
+/// * `password` - The password of the vault
+/// * `bundle` - The bundle file content
+/// * `passphrase` - The export passphrase of the bundle
+/// * `mode` - How the bundle is applied to the vault
+///
+/// # Returns
+///
+/// The manifest of the imported bundle
+#[command]
+#[specta]
+pub async fn import_vault(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    password: String,
+    bundle: String,
+    passphrase: String,
+    mode: VaultImportMode,
+) -> Result<VaultBundleManifest, String> {
+    check_password(app.clone(), state.clone(), password).await?;
+
+    let contents = open_bundle(bundle.as_str(), passphrase.as_str())?;
+
+    // the data key has full entropy, the memory-hard key derivation is not needed
+    let data_key = read_data_key().await?;
+    let encrypt = |secret: String| {
+        CryptData::new_with_kdf(
+            secret.into_bytes(),
+            CryptDataMode::to_u8(vec![CryptDataMode::Encrypt, CryptDataMode::Encode]),
+            Some(data_key.as_slice()),
+            None,
+            KdfAlgorithm::HkdfSha3_512,
+        )
+    };
+
+    // the replaced settings refer to the accounts by their id in the bundle, the merged accounts
+    // are given new ids so that they cannot take the id of a local account
+    let mut imported_ids = HashSet::new();
+    let imported = contents
+        .providers
+        .into_iter()
+        .map(|provider| ProviderData {
+            id: if mode == VaultImportMode::Replace
+                && !provider.id.is_empty()
+                && imported_ids.insert(provider.id.clone())
+            {
+                provider.id
+            } else {
+                new_account_id()
+            },
+            credential: provider.credential.into_credential(encrypt),
+            owner: provider.owner,
+            provider: provider.provider,
+            display_name: provider.display_name,
+            avatar_url: provider.avatar_url,
+            quota: None,
+        })
+        .collect::<Vec<_>>();
+    drop(data_key);
+
+    let mut writable_state = state.write().await;
+    merge_providers(&mut writable_state.providers, imported, mode);
+    if mode == VaultImportMode::Replace {
+        contents.settings.apply(&mut writable_state.settings);
+        reset_orphaned_default_page(&mut writable_state);
+    }
+    drop(writable_state);
+
+    debug!("Imported {} accounts", contents.manifest.accounts.len());
+    save(app.clone(), state.clone()).await?;
+    emit_state_changed(&app, &state, AppStateDeepKeys::Providers).await;
+    if mode == VaultImportMode::Replace {
+        emit_state_changed(&app, &state, AppStateDeepKeys::Settings).await;
+    }
+
+    Ok(contents.manifest)
+}
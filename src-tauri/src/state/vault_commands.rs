@@ -0,0 +1,195 @@
+use crate::secret_manager::is_vault_unlocked;
+use crate::state::password_rotation::remove_rotation_journal;
+use crate::state::state::AppState;
+use crate::state::state_backup::{has_state_file, state_file_candidates};
+use crate::state::state_commands::{check_vault_password, clear_state};
+use crate::state::state_file_watcher::{lock_state_file, remove_state_file_lock};
+use crate::state::state_generation::{remove_state_generation, state_generations_path};
+use crate::state::unlock_attempts::remove_unlock_attempts;
+use crate::state::vault_registry::{
+    Vault, load_vault_registry, lock_vault_registry, save_vault_registry, vault_file_path,
+};
+use serde::{Deserialize, Serialize};
+use specta::{Type, specta};
+use tauri::{AppHandle, State, command};
+use tracing::debug;
+
+/// A vault as listed to the user
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Type)]
+pub struct VaultSummary {
+    /// The unique identifier of the vault
+    pub id: String,
+    /// The name of the vault
+    pub name: String,
+    /// Whether the vault is the selected one
+    pub selected: bool,
+    /// Whether the vault has been set up with a password
+    pub initialized: bool,
+}
+
+impl VaultSummary {
+    /// Summarize a vault
+    ///
+    /// # Arguments
+    ///
+    /// * `app` - The application handle
+    /// * `vault` - The vault
+    /// * `selected` - Whether the vault is the selected one
+    ///
+    /// # Returns
+    ///
+    /// The summary of the vault
+    fn new(app: &AppHandle, vault: &Vault, selected: bool) -> Result<Self, String> {
+        Ok(Self {
+            id: vault.id.clone(),
+            name: vault.name.clone(),
+            selected,
+            initialized: has_state_file(&vault_file_path(app, vault)?),
+        })
+    }
+}
+
+/// List the vaults
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+///
+/// # Returns
+///
+/// The vaults
+#[command]
+#[specta]
+pub async fn list_vaults(app: AppHandle) -> Result<Vec<VaultSummary>, String> {
+    let registry = load_vault_registry(&app)?;
+
+    registry
+        .vaults
+        .iter()
+        .map(|vault| VaultSummary::new(&app, vault, vault.id == registry.selected))
+        .collect()
+}
+
+/// Create a new vault, the vault is set up with its own password once selected
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `name` - The name of the vault
+///
+/// # Returns
+///
+/// The new vault
+#[command]
+#[specta]
+pub async fn create_vault(app: AppHandle, name: String) -> Result<VaultSummary, String> {
+    let _guard = lock_vault_registry().await;
+    let mut registry = load_vault_registry(&app)?;
+
+    let vault = registry.create(name.as_str())?;
+    save_vault_registry(&app, &registry).await?;
+
+    debug!("Vault {} created", vault.id);
+    VaultSummary::new(&app, &vault, false)
+}
+
+/// Rename a vault
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `id` - The id of the vault
+/// * `name` - The new name of the vault
+///
+/// # Returns
+///
+/// Nothing
+#[command]
+#[specta]
+pub async fn rename_vault(app: AppHandle, id: String, name: String) -> Result<(), String> {
+    let _guard = lock_vault_registry().await;
+    let mut registry = load_vault_registry(&app)?;
+
+    registry.rename(id.as_str(), name.as_str())?;
+    save_vault_registry(&app, &registry).await
+}
+
+/// Delete a vault along with its state file and backups, the selected vault cannot be deleted
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `id` - The id of the vault
+/// * `password` - The password of the vault, ignored if the vault has not been set up
+///
+/// # Returns
+///
+/// Nothing
+#[command]
+#[specta]
+pub async fn delete_vault(app: AppHandle, id: String, password: String) -> Result<(), String> {
+    let _guard = lock_vault_registry().await;
+    let mut registry = load_vault_registry(&app)?;
+
+    let vault = registry.remove(id.as_str())?;
+    let state_file = vault_file_path(&app, &vault)?;
+
+    // the vault may be open in another instance of the application
+    let lock = lock_state_file(&state_file)?;
+
+    // deleting the state file is irreversible, only the owner of the vault can do it
+    if has_state_file(&state_file) {
+        check_vault_password(&state_file, password.as_str()).await?;
+    }
+
+    // forget the vault first, leftover files are harmless while a dangling vault is not
+    save_vault_registry(&app, &registry).await?;
+
+    for path in state_file_candidates(&state_file) {
+        tokio::fs::remove_file(path)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    remove_rotation_journal(&state_file).await?;
+    remove_unlock_attempts(&state_file).await?;
+    remove_state_generation(&state_generations_path(&app).await?, &state_file).await?;
+    drop(lock);
+    remove_state_file_lock(&state_file).await?;
+
+    debug!("Vault {} deleted", vault.id);
+    Ok(())
+}
+
+/// Select the vault used by the application, the current vault is locked
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `state` - The application state
+/// * `id` - The id of the vault
+///
+/// # Returns
+///
+/// Nothing
+#[command]
+#[specta]
+pub async fn select_vault(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    let _guard = lock_vault_registry().await;
+    let mut registry = load_vault_registry(&app)?;
+
+    registry.select(id.as_str())?;
+
+    // the secrets of a vault never outlive its selection
+    if is_vault_unlocked().await {
+        clear_state(&app, state).await;
+    }
+
+    save_vault_registry(&app, &registry).await?;
+
+    debug!("Vault {} selected", id);
+    Ok(())
+}
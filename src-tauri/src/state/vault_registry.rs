@@ -0,0 +1,313 @@
+use crate::state::state::STATE_FILE;
+use crate::utility::atomic_file::write_atomically;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::path::BaseDirectory;
+use tauri::{AppHandle, Manager};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// The file listing the vaults, stored next to the state files
+pub static VAULTS_FILE: &str = "vaults.json";
+
+/// The id of the vault using the state file written before multiple vaults were supported
+pub static DEFAULT_VAULT_ID: &str = "default";
+
+/// Serializes the changes to the vault registry
+static VAULT_REGISTRY_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// A vault, each vault has its own state file and therefore its own password, providers and
+/// settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Vault {
+    /// The unique identifier of the vault
+    pub id: String,
+    /// The name of the vault
+    pub name: String,
+    /// The name of the state file of the vault, relative to the application local data directory
+    pub file: String,
+}
+
+/// The list of vaults and the one currently selected
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VaultRegistry {
+    /// The id of the selected vault
+    pub selected: String,
+    /// The vaults
+    pub vaults: Vec<Vault>,
+}
+
+impl Default for VaultRegistry {
+    fn default() -> Self {
+        Self {
+            selected: DEFAULT_VAULT_ID.to_owned(),
+            vaults: vec![Vault {
+                id: DEFAULT_VAULT_ID.to_owned(),
+                name: "Default".to_owned(),
+                file: STATE_FILE.to_owned(),
+            }],
+        }
+    }
+}
+
+impl VaultRegistry {
+    /// Find a vault by id
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the vault
+    ///
+    /// # Returns
+    ///
+    /// The vault, an error if it does not exist
+    pub fn find(&self, id: &str) -> Result<&Vault, String> {
+        self.vaults
+            .iter()
+            .find(|vault| vault.id == id)
+            .ok_or("Vault not found".to_owned())
+    }
+
+    /// Get the selected vault
+    ///
+    /// # Returns
+    ///
+    /// The selected vault
+    pub fn selected_vault(&self) -> Result<&Vault, String> {
+        self.find(self.selected.as_str())
+    }
+
+    /// Add a new vault
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the vault
+    ///
+    /// # Returns
+    ///
+    /// The new vault
+    pub fn create(&mut self, name: &str) -> Result<Vault, String> {
+        let name = self.validate_name(name, None)?;
+
+        let mut id = [0u8; 8];
+        rand::rng().fill_bytes(&mut id);
+        let id = id
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+
+        let vault = Vault {
+            file: format!("vault-{}.json", id),
+            id,
+            name,
+        };
+        self.vaults.push(vault.clone());
+
+        Ok(vault)
+    }
+
+    /// Rename a vault
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the vault
+    /// * `name` - The new name of the vault
+    ///
+    /// # Returns
+    ///
+    /// Nothing
+    pub fn rename(&mut self, id: &str, name: &str) -> Result<(), String> {
+        let name = self.validate_name(name, Some(id))?;
+
+        self.vaults
+            .iter_mut()
+            .find(|vault| vault.id == id)
+            .ok_or("Vault not found")?
+            .name = name;
+
+        Ok(())
+    }
+
+    /// Select a vault
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the vault
+    ///
+    /// # Returns
+    ///
+    /// Nothing
+    pub fn select(&mut self, id: &str) -> Result<(), String> {
+        self.find(id)?;
+        self.selected = id.to_owned();
+
+        Ok(())
+    }
+
+    /// Remove a vault, the selected vault cannot be removed
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The id of the vault
+    ///
+    /// # Returns
+    ///
+    /// The removed vault
+    pub fn remove(&mut self, id: &str) -> Result<Vault, String> {
+        if self.selected == id {
+            return Err(
+                "Cannot delete the selected vault, switch to another vault first".to_owned(),
+            );
+        }
+
+        let index = self
+            .vaults
+            .iter()
+            .position(|vault| vault.id == id)
+            .ok_or("Vault not found")?;
+
+        Ok(self.vaults.remove(index))
+    }
+
+    /// Validate the name of a vault
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the vault
+    /// * `id` - The id of the vault being renamed, if any
+    ///
+    /// # Returns
+    ///
+    /// The trimmed name
+    fn validate_name(&self, name: &str, id: Option<&str>) -> Result<String, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("The vault name cannot be empty".to_owned());
+        }
+
+        if self.vaults.iter().any(|vault| {
+            Some(vault.id.as_str()) != id && vault.name.to_lowercase() == name.to_lowercase()
+        }) {
+            return Err(format!("A vault named {} already exists", name));
+        }
+
+        Ok(name.to_owned())
+    }
+}
+
+/// Lock the vault registry for a read-modify-write cycle
+///
+/// # Returns
+///
+/// The guard, the registry must be saved before it is dropped
+pub async fn lock_vault_registry() -> MutexGuard<'static, ()> {
+    VAULT_REGISTRY_LOCK.lock().await
+}
+
+/// Load the vault registry, a missing registry only lists the default vault
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+///
+/// # Returns
+///
+/// The vault registry
+pub fn load_vault_registry(app: &AppHandle) -> Result<VaultRegistry, String> {
+    let path = app
+        .path()
+        .resolve(VAULTS_FILE, BaseDirectory::AppLocalData)
+        .map_err(|e| e.to_string())?;
+
+    if !path.exists() {
+        return Ok(VaultRegistry::default());
+    }
+
+    let content = std::fs::read(path).map_err(|e| e.to_string())?;
+    serde_json::from_slice::<VaultRegistry>(&content).map_err(|e| e.to_string())
+}
+
+/// Save the vault registry
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `registry` - The vault registry
+///
+/// # Returns
+///
+/// Nothing
+pub async fn save_vault_registry(app: &AppHandle, registry: &VaultRegistry) -> Result<(), String> {
+    let path = app
+        .path()
+        .resolve(VAULTS_FILE, BaseDirectory::AppLocalData)
+        .map_err(|e| e.to_string())?;
+
+    if let Some(directory) = path.parent() {
+        tokio::fs::create_dir_all(directory)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let content = serde_json::to_vec(registry).map_err(|e| e.to_string())?;
+    write_atomically(&path, &content).await
+}
+
+/// Resolve the path of the state file of a vault
+///
+/// # Arguments
+///
+/// * `app` - The application handle
+/// * `vault` - The vault
+///
+/// # Returns
+///
+/// The path of the state file
+pub fn vault_file_path(app: &AppHandle, vault: &Vault) -> Result<PathBuf, String> {
+    // the registry is user-writable, never let it point outside the application directory
+    if Path::new(&vault.file).file_name() != Some(vault.file.as_ref()) {
+        return Err(format!("Invalid state file for the vault {}", vault.name));
+    }
+
+    app.path()
+        .resolve(&vault.file, BaseDirectory::AppLocalData)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_vault_registry() {
+        let registry = VaultRegistry::default();
+        let vault = registry.selected_vault().unwrap();
+
+        assert_eq!(vault.id, DEFAULT_VAULT_ID);
+        assert_eq!(vault.file, STATE_FILE);
+    }
+
+    #[test]
+    fn test_manage_vaults() {
+        let mut registry = VaultRegistry::default();
+
+        let work = registry.create(" Work ").unwrap();
+        assert_eq!(work.name, "Work");
+        assert_ne!(work.file, STATE_FILE);
+        assert!(registry.create("work").is_err());
+        assert!(registry.create("   ").is_err());
+
+        registry.rename(work.id.as_str(), "Office").unwrap();
+        registry.rename(work.id.as_str(), "office").unwrap();
+        assert!(registry.rename(work.id.as_str(), "default").is_err());
+        assert!(registry.rename("missing", "Personal").is_err());
+
+        registry.select(work.id.as_str()).unwrap();
+        assert_eq!(registry.selected_vault().unwrap().name, "office");
+        assert!(registry.select("missing").is_err());
+        assert!(registry.remove(work.id.as_str()).is_err());
+
+        registry.select(DEFAULT_VAULT_ID).unwrap();
+        assert_eq!(registry.remove(work.id.as_str()).unwrap().id, work.id);
+        assert_eq!(registry.vaults.len(), 1);
+    }
+}
@@ -0,0 +1,89 @@
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+/// Get the path of the temporary file used to atomically replace a file
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to replace
+///
+/// # Returns
+///
+/// The path of the temporary file, in the same directory so that it can be renamed atomically
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".tmp");
+
+    path.with_file_name(file_name)
+}
+
+/// Atomically replace the content of a file
+///
+/// The content is written to a temporary file that is synced to disk and then renamed over the
+/// destination, a crash leaves either the old or the new content but never a truncated file.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to write
+/// * `content` - The content of the file
+///
+/// # Returns
+///
+/// Nothing
+pub async fn write_atomically(path: &Path, content: &[u8]) -> Result<(), String> {
+    let temporary_path = temporary_path(path);
+
+    let mut file = File::options()
+        .write(true)
+        .truncate(true)
+        .create(true)
+        .open(&temporary_path)
+        .await
+        .map_err(|e| e.to_string())?;
+    file.write_all(content).await.map_err(|e| e.to_string())?;
+    file.sync_all().await.map_err(|e| e.to_string())?;
+    drop(file);
+
+    tokio::fs::rename(&temporary_path, path)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // persist the rename itself, directories cannot be opened on windows
+    #[cfg(unix)]
+    if let Some(directory) = path.parent() {
+        File::open(directory)
+            .await
+            .map_err(|e| e.to_string())?
+            .sync_all()
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    #[tokio::test]
+    async fn test_write_atomically() {
+        let directory = std::env::temp_dir().join(format!(
+            "storage-orchestra-atomic-file-{}",
+            rand::rng().next_u64()
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("state.json");
+
+        write_atomically(&path, b"first").await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"first");
+
+        write_atomically(&path, b"second").await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"second");
+        assert!(!temporary_path(&path).exists());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
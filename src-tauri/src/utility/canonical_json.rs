@@ -0,0 +1,227 @@
+use serde_json::{Number, Value};
+use std::fmt::Write;
+
+/// Serialize a JSON value following the JSON Canonicalization Scheme (RFC 8785)
+///
+/// The output does not depend on the order of the object keys nor on how the numbers were
+/// formatted, two equal values always give the same bytes.
+///
+/// # Arguments
+///
+/// * `value` - The value to serialize
+///
+/// # Returns
+///
+/// The canonical serialization
+pub fn to_canonical_json(value: &Value) -> Result<String, String> {
+    let mut output = String::new();
+    write_value(&mut output, value)?;
+
+    Ok(output)
+}
+
+/// Write a value in its canonical form
+///
+/// # Arguments
+///
+/// * `output` - The output buffer
+/// * `value` - The value to write
+///
+/// # Returns
+///
+/// Nothing
+fn write_value(output: &mut String, value: &Value) -> Result<(), String> {
+    match value {
+        Value::Null => output.push_str("null"),
+        Value::Bool(value) => output.push_str(if *value { "true" } else { "false" }),
+        Value::Number(number) => write_number(output, number)?,
+        Value::String(value) => write_string(output, value),
+        Value::Array(values) => {
+            output.push('[');
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_value(output, value)?;
+            }
+            output.push(']');
+        }
+        Value::Object(map) => {
+            // the keys are sorted by their utf-16 code units
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            output.push('{');
+            for (index, (key, value)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_string(output, key);
+                output.push(':');
+                write_value(output, value)?;
+            }
+            output.push('}');
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a string escaped as `JSON.stringify` does
+///
+/// # Arguments
+///
+/// * `output` - The output buffer
+/// * `value` - The string to write
+fn write_string(output: &mut String, value: &str) {
+    output.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\u{08}' => output.push_str("\\b"),
+            '\t' => output.push_str("\\t"),
+            '\n' => output.push_str("\\n"),
+            '\u{0c}' => output.push_str("\\f"),
+            '\r' => output.push_str("\\r"),
+            character if character < ' ' => {
+                // writing to a string cannot fail
+                let _ = write!(output, "\\u{:04x}", character as u32);
+            }
+            character => output.push(character),
+        }
+    }
+    output.push('"');
+}
+
+/// Write a number as the ECMAScript `Number.prototype.toString` does, numbers are IEEE 754 double
+/// precision values
+///
+/// # Arguments
+///
+/// * `output` - The output buffer
+/// * `number` - The number to write
+///
+/// # Returns
+///
+/// Nothing
+fn write_number(output: &mut String, number: &Number) -> Result<(), String> {
+    let value = number
+        .as_f64()
+        .filter(|value| value.is_finite())
+        .ok_or(format!("Cannot canonicalize the number {}", number))?;
+
+    if value == 0.0 {
+        output.push('0');
+        return Ok(());
+    }
+    if value < 0.0 {
+        output.push('-');
+    }
+
+    // the shortest digits that round-trip and the decimal exponent, e.g. `1.25e-7`
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .ok_or(format!("Cannot canonicalize the number {}", number))?;
+    let digits = mantissa.replace('.', "");
+    let exponent = exponent.parse::<i32>().map_err(|e| e.to_string())?;
+
+    // the position of the decimal point relative to the digits
+    let digit_count = digits.len() as i32;
+    let point = exponent + 1;
+
+    if digit_count <= point && point <= 21 {
+        output.push_str(&digits);
+        output.push_str(&"0".repeat((point - digit_count) as usize));
+    } else if 0 < point && point <= 21 {
+        let (integer, fraction) = digits.split_at(point as usize);
+        output.push_str(integer);
+        output.push('.');
+        output.push_str(fraction);
+    } else if -6 < point && point <= 0 {
+        output.push_str("0.");
+        output.push_str(&"0".repeat(-point as usize));
+        output.push_str(&digits);
+    } else {
+        let (first, rest) = digits.split_at(1);
+        output.push_str(first);
+        if !rest.is_empty() {
+            output.push('.');
+            output.push_str(rest);
+        }
+        output.push('e');
+        output.push(if point - 1 < 0 { '-' } else { '+' });
+        output.push_str(&(point - 1).abs().to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_canonical_json_structure() {
+        let value = json!({
+            "b": [1, "two", null, true, { "z": false, "a": {} }],
+            "a": "text",
+            "\u{20ac}": 1,
+            "\r": 2,
+            "\u{1f600}": 3,
+            "\u{fb33}": 4,
+        });
+
+        assert_eq!(
+            to_canonical_json(&value).unwrap(),
+            "{\"\\r\":2,\"a\":\"text\",\"b\":[1,\"two\",null,true,{\"a\":{},\"z\":false}],\
+             \"\u{20ac}\":1,\"\u{1f600}\":3,\"\u{fb33}\":4}"
+        );
+
+        // the key order of the input does not matter
+        let reordered = serde_json::from_str::<Value>(
+            "{\"\u{fb33}\":4,\"\u{1f600}\":3,\"\\r\":2,\"\u{20ac}\":1,\"a\":\"text\",\
+             \"b\":[1,\"two\",null,true,{\"a\":{},\"z\":false}]}",
+        )
+        .unwrap();
+        assert_eq!(
+            to_canonical_json(&reordered).unwrap(),
+            to_canonical_json(&value).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_canonical_json_strings() {
+        assert_eq!(
+            to_canonical_json(&json!("\u{20ac}$\u{0f}\nA'B\"\\\\\"/")).unwrap(),
+            "\"\u{20ac}$\\u000f\\nA'B\\\"\\\\\\\\\\\"/\""
+        );
+    }
+
+    #[test]
+    fn test_canonical_json_numbers() {
+        let cases = [
+            (json!(0), "0"),
+            (json!(-0.0), "0"),
+            (json!(1), "1"),
+            (json!(-1), "-1"),
+            (json!(1.0), "1"),
+            (json!(1.5), "1.5"),
+            (json!(333333333.3333333), "333333333.3333333"),
+            (json!(1e21), "1e+21"),
+            (json!(1e20), "100000000000000000000"),
+            (json!(1e-7), "1e-7"),
+            (json!(0.000001), "0.000001"),
+            (json!(4.5e-7), "4.5e-7"),
+            (json!(9007199254740992u64), "9007199254740992"),
+            (json!(1.7976931348623157e308), "1.7976931348623157e+308"),
+            (json!(5e-324), "5e-324"),
+        ];
+
+        for (value, expected) in cases {
+            assert_eq!(to_canonical_json(&value).unwrap(), expected, "{}", value);
+        }
+    }
+}
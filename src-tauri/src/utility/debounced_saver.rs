@@ -1,39 +1,76 @@
 use std::fmt;
 use std::fmt::{Debug, Formatter};
+use std::pin::Pin;
 use std::sync::Arc;
-use tokio::sync::{Mutex, Notify};
-use tokio::time::{Duration, sleep};
-use tracing::error;
+use tokio::sync::{Mutex, Notify, broadcast};
+use tokio::time::{Duration, Instant, sleep, sleep_until};
+use tracing::{error, warn};
+
+/// The number of times a failed save is attempted before the failure is reported
+static SAVE_ATTEMPTS: u32 = 3;
+
+/// The delay before the first retry of a failed save, doubled after each attempt
+static RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// The function that writes the content, called again when a save is retried
+type SaveFn =
+    Arc<dyn Fn(String) -> Pin<Box<dyn Future<Output = Result<(), String>> + Send>> + Send + Sync>;
+
+/// Content waiting to be saved
+struct PendingSave {
+    /// The content to save.
+    content: String,
+    /// The function to save the content.
+    save_fn: SaveFn,
+    /// When the first save since the last write was requested.
+    requested_at: Instant,
+}
+
+impl Debug for PendingSave {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingSave")
+            .field("content", &self.content)
+            .field("requested_at", &self.requested_at)
+            .finish()
+    }
+}
 
 /// A debounced saver that saves content after a delay.
 /// This is useful for saving content that is frequently updated.
+///
+/// The content is saved once no new content was received for the delay, or once the maximum
+/// delay passed since the first unsaved content so that constant updates cannot postpone the
+/// write forever. Failed saves are retried with a backoff, the final failures are broadcast to the
+/// subscribers.
 #[derive(Clone)]
 pub struct DebouncedSaver {
     /// The delay before saving the content.
     delay: Duration,
+    /// The maximum delay between the first unsaved content and the write.
+    max_delay: Duration,
     /// The pending content to save.
-    pending_content: Arc<Mutex<Option<String>>>,
+    pending: Arc<Mutex<Option<PendingSave>>>,
     /// A notification to trigger the debounced task.
     notify: Arc<Notify>,
-    save_fn: Arc<
-        Mutex<
-            Option<Box<dyn FnOnce(String) -> tauri::async_runtime::JoinHandle<()> + Send + Sync>>,
-        >,
-    >,
+    /// Held while content is written so that a flush never races the debounced task.
+    write_lock: Arc<Mutex<()>>,
+    /// The errors of the saves that failed after all the attempts.
+    failures: broadcast::Sender<String>,
 }
 
 impl Debug for DebouncedSaver {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("DebouncedSaver")
             .field("delay", &self.delay)
-            .field("pending_content", &self.pending_content)
+            .field("max_delay", &self.max_delay)
+            .field("pending", &self.pending)
             .finish()
     }
 }
 
 impl Default for DebouncedSaver {
     fn default() -> Self {
-        Self::new(100)
+        Self::new(100, 2000)
     }
 }
 
@@ -43,16 +80,21 @@ impl DebouncedSaver {
     /// # Arguments
     ///
     /// * `delay_ms` - The delay before saving the content in milliseconds.
+    /// * `max_delay_ms` - The maximum delay between the first unsaved content and the write in
+    ///   milliseconds.
     ///
     /// # Returns
     ///
     /// The debounced saver.
-    pub fn new(delay_ms: u64) -> Self {
+    pub fn new(delay_ms: u64, max_delay_ms: u64) -> Self {
+        let (failures, _) = broadcast::channel(16);
         let saver = Self {
             delay: Duration::from_millis(delay_ms),
-            pending_content: Arc::new(Mutex::new(None)),
+            max_delay: Duration::from_millis(max_delay_ms.max(delay_ms)),
+            pending: Arc::new(Mutex::new(None)),
             notify: Arc::new(Notify::new()),
-            save_fn: Arc::new(Mutex::new(None)),
+            write_lock: Arc::new(Mutex::new(())),
+            failures,
         };
 
         saver.start_background_task();
@@ -61,56 +103,150 @@ impl DebouncedSaver {
     }
 
     /// Save the content after the delay has passed.
-    /// If the content is saved before the delay has passed, the timer is reset.
+    /// If the content is saved before the delay has passed, the timer is reset, unless the
+    /// maximum delay has passed.
     ///
     /// # Arguments
     ///
     /// * `content` - The content to save.
-    /// * `save_fn` - The function to save the content.
+    /// * `save_fn` - The function to save the content, called again if the save is retried.
     ///
     /// # Returns
     ///
     /// Nothing.
     pub async fn save<F, Fut>(&self, content: String, save_fn: F)
     where
-        F: FnOnce(String) -> Fut + Send + Sync + 'static,
+        F: Fn(String) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = Result<(), String>> + Send + 'static,
     {
-        let mut pending = self.pending_content.lock().await;
-        *pending = Some(content);
-
-        let mut fn_lock = self.save_fn.lock().await;
-        *fn_lock = Some(Box::new(move |content| {
-            let fut = save_fn(content);
-            tauri::async_runtime::spawn(async move {
-                if let Err(e) = fut.await {
-                    error!("Error saving content: {}", e);
-                }
-            })
-        }));
+        let save_fn: SaveFn = Arc::new(move |content| Box::pin(save_fn(content)));
+
+        let mut pending = self.pending.lock().await;
+        let requested_at = pending
+            .as_ref()
+            .map(|pending| pending.requested_at)
+            .unwrap_or_else(Instant::now);
+        *pending = Some(PendingSave {
+            content,
+            save_fn,
+            requested_at,
+        });
+        drop(pending);
 
         self.notify.notify_one();
     }
 
+    /// Write the pending content now, waiting for a write in progress to complete.
+    ///
+    /// # Returns
+    ///
+    /// Nothing, or the error of the last attempt if the content could not be saved.
+    pub async fn flush(&self) -> Result<(), String> {
+        Self::write_pending(&self.pending, &self.write_lock, &self.failures).await
+    }
+
+    /// Check whether content is waiting to be saved.
+    ///
+    /// # Returns
+    ///
+    /// Whether content is waiting to be saved.
+    pub async fn has_pending(&self) -> bool {
+        self.pending.lock().await.is_some()
+    }
+
+    /// Drop the pending content without saving it, waiting for a write in progress to complete.
+    pub async fn discard(&self) {
+        let _write_guard = self.write_lock.lock().await;
+        self.pending.lock().await.take();
+    }
+
+    /// Subscribe to the errors of the saves that failed after all the attempts.
+    ///
+    /// # Returns
+    ///
+    /// The receiver of the errors.
+    pub fn subscribe_failures(&self) -> broadcast::Receiver<String> {
+        self.failures.subscribe()
+    }
+
+    /// Write the pending content, retrying with a backoff on failure.
+    ///
+    /// A retry is abandoned when newer content is pending, the newer content supersedes it.
+    ///
+    /// # Arguments
+    ///
+    /// * `pending` - The pending content.
+    /// * `write_lock` - The lock held while writing.
+    /// * `failures` - Where the final failures are reported.
+    ///
+    /// # Returns
+    ///
+    /// Nothing, or the error of the last attempt.
+    async fn write_pending(
+        pending: &Mutex<Option<PendingSave>>,
+        write_lock: &Mutex<()>,
+        failures: &broadcast::Sender<String>,
+    ) -> Result<(), String> {
+        let _write_guard = write_lock.lock().await;
+        let Some(pending_save) = pending.lock().await.take() else {
+            return Ok(());
+        };
+
+        let mut backoff = RETRY_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            let Err(e) = (pending_save.save_fn)(pending_save.content.clone()).await else {
+                return Ok(());
+            };
+
+            if attempt >= SAVE_ATTEMPTS {
+                error!("Error saving content: {}", e);
+                // nobody listening is not an error, the failure is already logged
+                let _ = failures.send(e.clone());
+                return Err(e);
+            }
+
+            warn!("Error saving content, attempt {}: {}", attempt, e);
+            sleep(backoff).await;
+            if pending.lock().await.is_some() {
+                return Ok(());
+            }
+
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+
     fn start_background_task(&self) {
-        let pending_content = Arc::clone(&self.pending_content);
+        let pending = Arc::clone(&self.pending);
         let notify = Arc::clone(&self.notify);
+        let write_lock = Arc::clone(&self.write_lock);
+        let failures = self.failures.clone();
         let delay = self.delay;
-        let save_fn = Arc::clone(&self.save_fn);
+        let max_delay = self.max_delay;
 
         tauri::async_runtime::spawn(async move {
             loop {
                 notify.notified().await; // Wait for notification
-                sleep(delay).await; // Debounce timer
 
-                let mut pending = pending_content.lock().await;
-                let mut fn_lock = save_fn.lock().await;
+                let Some(requested_at) = pending.lock().await.as_ref().map(|p| p.requested_at)
+                else {
+                    continue;
+                };
 
-                if let Some(content) = pending.take() {
-                    if let Some(save_fn) = fn_lock.take() {
-                        save_fn(content);
+                // Debounce timer, reset by each new content until the maximum delay
+                let max_deadline = requested_at + max_delay;
+                loop {
+                    let deadline = (Instant::now() + delay).min(max_deadline);
+                    tokio::select! {
+                        biased;
+                        _ = sleep_until(deadline) => break,
+                        _ = notify.notified() => {}
                     }
                 }
+
+                // the error is already reported to the subscribers
+                let _ = Self::write_pending(&pending, &write_lock, &failures).await;
             }
         });
     }
@@ -119,24 +255,27 @@ impl DebouncedSaver {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
     use tokio::sync::mpsc;
     use tokio::time::timeout;
 
     #[tokio::test]
     async fn test_debounced_saver_new() {
-        let saver = DebouncedSaver::new(100);
+        let saver = DebouncedSaver::new(100, 1000);
         assert_eq!(saver.delay, Duration::from_millis(100));
+        assert_eq!(saver.max_delay, Duration::from_millis(1000));
     }
 
     #[tokio::test]
     async fn test_debounced_saver_default() {
         let saver = DebouncedSaver::default();
         assert_eq!(saver.delay, Duration::from_millis(100));
+        assert_eq!(saver.max_delay, Duration::from_millis(2000));
     }
 
     #[tokio::test]
     async fn test_debounced_saver_save() {
-        let saver = DebouncedSaver::new(100);
+        let saver = DebouncedSaver::new(100, 1000);
         let (tx, mut rx) = mpsc::channel(1);
 
         saver
@@ -156,14 +295,15 @@ mod tests {
 
     #[tokio::test]
     async fn test_debounced_saver_save_error() {
-        let saver = DebouncedSaver::new(100);
+        let saver = DebouncedSaver::new(100, 1000);
         let (tx, mut rx) = mpsc::channel(1);
 
         saver
             .save("test content".to_string(), move |_content| {
                 let tx = tx.clone();
                 async move {
-                    tx.send("error".to_string()).await.unwrap();
+                    // the failed save is retried after the receiver is dropped
+                    let _ = tx.send("error".to_string()).await;
                     Err("save error".to_string())
                 }
             })
@@ -173,4 +313,109 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap().unwrap(), "error");
     }
+
+    #[tokio::test]
+    async fn test_debounced_saver_flush() {
+        let saver = DebouncedSaver::new(10_000, 10_000);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        saver
+            .save("test content".to_string(), move |content| {
+                let tx = tx.clone();
+                async move {
+                    tx.send(content).unwrap();
+                    Ok(())
+                }
+            })
+            .await;
+
+        assert!(saver.flush().await.is_ok());
+        assert_eq!(rx.try_recv().unwrap(), "test content");
+
+        // nothing is left to save
+        assert!(saver.flush().await.is_ok());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_debounced_saver_discard() {
+        let saver = DebouncedSaver::new(10_000, 10_000);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        saver
+            .save("test content".to_string(), move |content| {
+                let tx = tx.clone();
+                async move {
+                    tx.send(content).unwrap();
+                    Ok(())
+                }
+            })
+            .await;
+        assert!(saver.has_pending().await);
+
+        saver.discard().await;
+        assert!(!saver.has_pending().await);
+        assert!(saver.flush().await.is_ok());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_debounced_saver_max_delay() {
+        let saver = DebouncedSaver::new(200, 400);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        // the content changes faster than the debounce delay
+        for index in 0..20 {
+            let tx = tx.clone();
+            saver
+                .save(format!("content {}", index), move |content| {
+                    let tx = tx.clone();
+                    async move {
+                        tx.send(content).unwrap();
+                        Ok(())
+                    }
+                })
+                .await;
+            sleep(Duration::from_millis(50)).await;
+        }
+
+        assert!(rx.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_debounced_saver_retry() {
+        let saver = DebouncedSaver::new(10_000, 10_000);
+        let attempts = Arc::new(AtomicU32::new(0));
+
+        let counter = Arc::clone(&attempts);
+        saver
+            .save("test content".to_string(), move |_content| {
+                let counter = Arc::clone(&counter);
+                async move {
+                    match counter.fetch_add(1, Ordering::SeqCst) {
+                        0 | 1 => Err("save error".to_string()),
+                        _ => Ok(()),
+                    }
+                }
+            })
+            .await;
+
+        assert!(saver.flush().await.is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_debounced_saver_failure_is_reported() {
+        let saver = DebouncedSaver::new(10_000, 10_000);
+        let mut failures = saver.subscribe_failures();
+
+        saver
+            .save("test content".to_string(), |_content| async move {
+                Err("save error".to_string())
+            })
+            .await;
+
+        assert_eq!(saver.flush().await.unwrap_err(), "save error");
+        assert_eq!(failures.try_recv().unwrap(), "save error");
+    }
 }
@@ -32,6 +32,38 @@ pub fn get_json_value<'a>(json: &'a Value, path: &str) -> Option<&'a Value> {
     Some(current)
 }
 
+/// Retrieve a mutable value from a `serde_json::Value` using a dot-separated JSON path.
+///
+/// # Arguments
+///
+/// * `json` - The JSON structure to search.
+/// * `path` - A dot-separated string representing the JSON path.
+///
+/// # Returns
+///
+/// An `Option<&mut Value>` containing the value at the specified path, or `None` if not found.
+pub fn get_json_value_mut<'a>(json: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    let mut current = json;
+
+    for key in path.split('.') {
+        match current {
+            Value::Object(map) => {
+                current = map.get_mut(key)?;
+            }
+            Value::Array(arr) => {
+                if let Ok(index) = key.parse::<usize>() {
+                    current = arr.get_mut(index)?;
+                } else {
+                    return None; // Invalid index for an array
+                }
+            }
+            _ => return None, // Path is invalid (trying to index a non-object/array)
+        }
+    }
+
+    Some(current)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -85,6 +117,20 @@ mod tests {
         assert_eq!(get_json_value(&json, "a.b.x"), None);
     }
 
+    #[test]
+    fn test_get_json_value_mut() {
+        let mut json = json!({
+            "a": [
+                {"b": "value1"}
+            ]
+        });
+
+        *get_json_value_mut(&mut json, "a.0.b").unwrap() = json!("value2");
+        assert_eq!(get_json_value(&json, "a.0.b"), Some(&json!("value2")));
+        assert_eq!(get_json_value_mut(&mut json, "a.1"), None);
+        assert_eq!(get_json_value_mut(&mut json, "a.b"), None);
+    }
+
     #[test]
     fn test_get_json_value_non_object_array() {
         let json = json!("value");
@@ -1,2 +1,4 @@
+pub mod atomic_file;
+pub mod canonical_json;
 pub mod debounced_saver;
 pub mod get_json_value;
@@ -15,7 +15,6 @@ import {
     DefaultPageGroups,
     Settings,
     SettingsResult,
-    StorageProvider,
 } from "../../../../tauri-bindings.ts";
 import {SettingRow} from "../../setting-row.tsx";
 
@@ -35,7 +34,7 @@ function pageGroupToDropdownValue(page_group: DefaultPageGroups): string {
         return page_group.general;
     }
 
-    return `${ page_group.providers.provider }-${ page_group.providers.owner }`;
+    return page_group.providers.account_id;
 }
 
 async function handleOnChange(
@@ -72,18 +71,13 @@ async function handleOnChange(
         );
     }
     else {
-        const [ provider, owner ] = (
-            value as string
-        ).split("-");
-
         await updateSettings(
             {
                 general_behaviour: {
                     ...settings?.general_behaviour,
                     default_page: {
                         providers: {
-                            provider: provider as StorageProvider,
-                            owner,
+                            account_id: value,
                         },
                     },
                 },
@@ -120,7 +114,7 @@ export const DefaultPage: FC<DefaultPageProps> = ({settings, updateSettings, ref
                 items: providers.map((provider, i) => (
                     {
                         label: `${ title(provider.provider) } - ${ provider.owner }`,
-                        value: `${ provider.provider }-${ provider.owner }`,
+                        value: provider.id,
                     }
                 )),
             },
@@ -0,0 +1,187 @@
+import { Button, FileInput, PasswordInput, SegmentedControl, Stack, Text } from "@mantine/core";
+import { useForm, UseFormReturnType } from "@mantine/form";
+import { yupResolver } from "mantine-form-yup-resolver";
+import { Dispatch, FC, SetStateAction, useRef, useState } from "react";
+import * as yup from "yup";
+import { commands, VaultImportMode } from "../../../../tauri-bindings.ts";
+import { SettingRow } from "../../setting-row.tsx";
+
+/**
+ * The name of the file the vault is exported to
+ */
+const EXPORT_FILE_NAME = "storage-orchestra-vault.json";
+
+type ExportValues = {
+    password: string
+    passphrase: string
+    confirm_passphrase: string
+}
+type ExportForm = UseFormReturnType<ExportValues, (values: ExportValues) => ExportValues>
+
+type ImportValues = {
+    password: string
+    passphrase: string
+    file: File | null
+    mode: VaultImportMode
+}
+type ImportForm = UseFormReturnType<ImportValues, (values: ImportValues) => ImportValues>
+
+async function handleExport(
+    values: ExportValues,
+    setIsLoading: Dispatch<SetStateAction<boolean>>,
+    form: ExportForm,
+) {
+    setIsLoading(true);
+
+    const response = await commands.exportVault(values.password, values.passphrase);
+    setIsLoading(false);
+
+    if (response.status === "error") {
+        form.setErrors({password: response.error});
+        return;
+    }
+
+    // the bundle is sealed with the export passphrase, it can safely go through the webview
+    const url = URL.createObjectURL(new Blob([ response.data ], {type: "application/json"}));
+    const anchor = document.createElement("a");
+    anchor.href = url;
+    anchor.download = EXPORT_FILE_NAME;
+    anchor.click();
+    URL.revokeObjectURL(url);
+
+    form.reset();
+}
+
+async function handleImport(
+    values: ImportValues,
+    setIsLoading: Dispatch<SetStateAction<boolean>>,
+    setImported: Dispatch<SetStateAction<string | undefined>>,
+    form: ImportForm,
+) {
+    if (!values.file) {
+        return;
+    }
+
+    setIsLoading(true);
+
+    const response = await commands.importVault(
+        values.password,
+        await values.file.text(),
+        values.passphrase,
+        values.mode,
+    );
+    setIsLoading(false);
+
+    if (response.status === "error") {
+        form.setErrors({passphrase: response.error});
+        return;
+    }
+
+    form.reset();
+    setImported(`${ response.data.accounts.length } account(s) imported`);
+}
+
+export const ExportVault: FC = () => {
+    const ref = useRef(null);
+
+    const [ is_loading, setIsLoading ] = useState(false);
+    const export_form = useForm({
+        initialValues: {
+            password:           "",
+            passphrase:         "",
+            confirm_passphrase: "",
+        },
+        validate:      yupResolver(yup.object({
+            password:           yup.string().required("Master password is required"),
+            passphrase:         yup.string().required("Export passphrase is required"),
+            confirm_passphrase: yup.string()
+                                   .required("Confirm the export passphrase")
+                                   .oneOf([ yup.ref("passphrase") ], "Passphrases must match"),
+        })),
+    });
+
+    return (
+        <SettingRow title={ "Export vault" }
+                    description={ "Export the accounts and the settings to a file protected by an export passphrase, use it to move your vault to another device." }
+                    target={ ref }
+                    align={ "self-start" }>
+            <form onSubmit={ export_form.onSubmit((values) => handleExport(values, setIsLoading, export_form)) }>
+                <Stack w={ "100%" } maw={ "24rem" } miw={ "24rem" } ml={ "auto" }>
+                    <PasswordInput ref={ ref }
+                                   placeholder={ "Master password" }
+                                   { ...export_form.getInputProps("password") }/>
+                    <PasswordInput placeholder={ "Export passphrase" }
+                                   { ...export_form.getInputProps("passphrase") }/>
+                    <PasswordInput placeholder={ "Confirm export passphrase" }
+                                   { ...export_form.getInputProps("confirm_passphrase") }/>
+                    <Button variant={ "light" } type={ "submit" } loading={ is_loading } ml={ "auto" }>
+                        Export
+                    </Button>
+                </Stack>
+            </form>
+        </SettingRow>
+    );
+};
+
+export const ImportVault: FC = () => {
+    const ref = useRef(null);
+
+    const [ is_loading, setIsLoading ] = useState(false);
+    const [ imported, setImported ] = useState<string>();
+    const import_form = useForm<ImportValues>({
+        initialValues: {
+            password:   "",
+            passphrase: "",
+            file:       null,
+            mode:       "merge",
+        },
+        validate:      yupResolver(yup.object({
+            password:   yup.string().required("Master password is required"),
+            passphrase: yup.string().required("Export passphrase is required"),
+            file:       yup.mixed().required("Select the exported file"),
+        })),
+    });
+
+    return (
+        <SettingRow title={ "Import vault" }
+                    description={ <>
+                        <Text>
+                            Import the accounts and the settings from an exported file, the imported tokens are
+                            protected by the master password of this vault.
+                        </Text>
+                        <Text>
+                            Merging keeps the current settings, replacing overwrites the accounts and the settings.
+                        </Text>
+                    </> }
+                    target={ ref }
+                    align={ "self-start" }>
+            <form onSubmit={ import_form.onSubmit((values) => handleImport(
+                values,
+                setIsLoading,
+                setImported,
+                import_form,
+            )) }>
+                <Stack w={ "100%" } maw={ "24rem" } miw={ "24rem" } ml={ "auto" }>
+                    <FileInput ref={ ref }
+                               placeholder={ "Exported file" }
+                               accept={ "application/json" }
+                               { ...import_form.getInputProps("file") }/>
+                    <PasswordInput placeholder={ "Master password" }
+                                   { ...import_form.getInputProps("password") }/>
+                    <PasswordInput placeholder={ "Export passphrase" }
+                                   { ...import_form.getInputProps("passphrase") }/>
+                    <SegmentedControl data={ [
+                        {label: "Merge", value: "merge"},
+                        {label: "Replace", value: "replace"},
+                    ] } { ...import_form.getInputProps("mode") }/>
+                    {
+                        imported && <Text size={ "sm" } c={ "green" }>{ imported }</Text>
+                    }
+                    <Button variant={ "light" } type={ "submit" } loading={ is_loading } ml={ "auto" }>
+                        Import
+                    </Button>
+                </Stack>
+            </form>
+        </SettingRow>
+    );
+};
@@ -0,0 +1,172 @@
+import { Button, Code, Group, PasswordInput, SimpleGrid, Stack, Text, Title } from "@mantine/core";
+import { useForm, UseFormReturnType } from "@mantine/form";
+import { modals } from "@mantine/modals";
+import { yupResolver } from "mantine-form-yup-resolver";
+import { Dispatch, FC, SetStateAction, useEffect, useRef, useState } from "react";
+import * as yup from "yup";
+import { commands } from "../../../../tauri-bindings.ts";
+import { SettingRow } from "../../setting-row.tsx";
+
+type FormValues = {
+    password: string
+}
+type RecoveryKeyForm = UseFormReturnType<FormValues, (values: FormValues) => FormValues>
+
+/**
+ * Shows the recovery key, this is the only time it is displayed
+ * @param {string} recovery_key - The recovery key as a word-list mnemonic
+ */
+export function showRecoveryKey(recovery_key: string) {
+    const modal = modals.open({
+        title:               <Title order={ 3 }>Your recovery key</Title>,
+        size:                "lg",
+        withCloseButton:     false,
+        closeOnEscape:       false,
+        closeOnClickOutside: false,
+        children:            (
+                                 <Stack p={ "lg" }>
+                                     <Text>
+                                         Write down the following words in order and keep them somewhere safe, they
+                                         are the only way to regain access to your data if you forget your password.
+                                     </Text>
+                                     <Text c={ "red" } fw={ 600 }>
+                                         The recovery key will not be shown again.
+                                     </Text>
+                                     <SimpleGrid cols={ 4 }>
+                                         {
+                                             recovery_key.split(" ").map((word, index) => (
+                                                 <Code key={ index } block>
+                                                     { index + 1 }. { word }
+                                                 </Code>
+                                             ))
+                                         }
+                                     </SimpleGrid>
+                                     <Button ml={ "auto" } onClick={ () => modals.close(modal) }>
+                                         I have written it down
+                                     </Button>
+                                 </Stack>
+                             ),
+    });
+}
+
+async function handleGenerate(
+    values: FormValues,
+    setIsLoading: Dispatch<SetStateAction<boolean>>,
+    setHasRecoveryKey: Dispatch<SetStateAction<boolean>>,
+    form: RecoveryKeyForm,
+) {
+    setIsLoading(true);
+
+    const response = await commands.generateRecoveryKey(values.password);
+    setIsLoading(false);
+
+    if (response.status === "error") {
+        form.setErrors({password: response.error});
+        return;
+    }
+
+    form.reset();
+    setHasRecoveryKey(true);
+    showRecoveryKey(response.data);
+}
+
+async function handleRevoke(
+    values: FormValues,
+    setIsLoading: Dispatch<SetStateAction<boolean>>,
+    setHasRecoveryKey: Dispatch<SetStateAction<boolean>>,
+    form: RecoveryKeyForm,
+) {
+    setIsLoading(true);
+
+    const response = await commands.revokeRecoveryKey(values.password);
+    setIsLoading(false);
+
+    if (response.status === "error") {
+        form.setErrors({password: response.error});
+        return;
+    }
+
+    form.reset();
+    setHasRecoveryKey(false);
+}
+
+export const RecoveryKey: FC = () => {
+    const ref = useRef(null);
+
+    const [is_loading, setIsLoading] = useState(false);
+    const [has_recovery_key, setHasRecoveryKey] = useState(false);
+    const recovery_key_form = useForm({
+        initialValues: {
+            password: "",
+        },
+        validate:      yupResolver(yup.object({
+            password: yup.string().required("Master password is required"),
+        })),
+    });
+
+    useEffect(() => {
+        commands.hasRecoveryKey().then((response) => {
+            if (response.status === "ok") {
+                setHasRecoveryKey(response.data);
+            }
+        });
+    }, []);
+
+    return (
+        <SettingRow title={"Recovery key"}
+                    description={<>
+                        <Text>
+                            Generate a word-list recovery key to regain access to your data if you forget the master
+                            password.
+                        </Text>
+                        <Text>
+                            {
+                                has_recovery_key
+                                ? "A recovery key is configured, generating a new one invalidates the previous one."
+                                : "No recovery key is configured."
+                            }
+                        </Text>
+                    </>}
+                    target={ref}
+                    align={"self-start"}>
+            <form onSubmit={recovery_key_form.onSubmit((values) => handleGenerate(
+                values,
+                setIsLoading,
+                setHasRecoveryKey,
+                recovery_key_form,
+            ))}>
+                <Stack w={"100%"}>
+                    <PasswordInput
+                        ref={ref}
+                        placeholder={"Master password"}
+                        ml={"auto"}
+                        maw={"24rem"}
+                        miw={"24rem"}
+                        {...recovery_key_form.getInputProps("password")}
+                    />
+                    <Group ml={"auto"}>
+                        {
+                            has_recovery_key &&
+                            <Button variant={"light"}
+                                    color={"red"}
+                                    loading={is_loading}
+                                    onClick={() => recovery_key_form.onSubmit((values) => handleRevoke(
+                                        values,
+                                        setIsLoading,
+                                        setHasRecoveryKey,
+                                        recovery_key_form,
+                                    ))()}>
+                                Revoke
+                            </Button>
+                        }
+                        <Button variant={"light"}
+                                type={"submit"}
+                                loading={is_loading}>
+                            {has_recovery_key ? "Regenerate recovery key" : "Generate recovery key"}
+                        </Button>
+                    </Group>
+                </Stack>
+            </form>
+        </SettingRow>
+    );
+};
@@ -0,0 +1,154 @@
+import { Badge, Button, Group, NumberInput, PasswordInput, Stack, Table, Text } from "@mantine/core";
+import { useForm, UseFormReturnType } from "@mantine/form";
+import { yupResolver } from "mantine-form-yup-resolver";
+import { Dispatch, FC, SetStateAction, useEffect, useRef, useState } from "react";
+import * as yup from "yup";
+import { commands, UnlockAttempts as UnlockAttemptsData } from "../../../../tauri-bindings.ts";
+import { SettingRow } from "../../setting-row.tsx";
+
+type FormValues = {
+    password: string
+    lockout_threshold: number | string
+}
+type LockoutForm = UseFormReturnType<FormValues, (values: FormValues) => FormValues>
+
+async function handleSubmit(
+    values: FormValues,
+    setIsLoading: Dispatch<SetStateAction<boolean>>,
+    setAttempts: Dispatch<SetStateAction<UnlockAttemptsData | null>>,
+    form: LockoutForm,
+) {
+    setIsLoading(true);
+
+    // an empty threshold disables the lockout
+    const threshold = values.lockout_threshold === "" ? null : Number(values.lockout_threshold);
+    const response = await commands.setUnlockLockoutThreshold(values.password, threshold);
+    setIsLoading(false);
+
+    if (response.status === "error") {
+        form.setErrors({password: response.error});
+        return;
+    }
+
+    form.setFieldValue("password", "");
+    setAttempts(response.data);
+}
+
+export const UnlockAttempts: FC = () => {
+    const ref = useRef(null);
+
+    const [is_loading, setIsLoading] = useState(false);
+    const [attempts, setAttempts] = useState<UnlockAttemptsData | null>(null);
+    const lockout_form = useForm<FormValues>({
+        initialValues: {
+            password:          "",
+            lockout_threshold: "",
+        },
+        validate:      yupResolver(yup.object({
+            password: yup.string().required("Master password is required"),
+        })),
+    });
+
+    useEffect(() => {
+        commands.getUnlockAttempts().then((response) => {
+            if (response.status === "ok") {
+                setAttempts(response.data);
+                lockout_form.setFieldValue("lockout_threshold", response.data.lockout_threshold ?? "");
+            }
+        });
+    }, []);
+
+    return (
+        <Stack gap={"xl"}>
+            <SettingRow title={"Lockout"}
+                        description={<>
+                            <Text>
+                                Each failed password check doubles the delay before the next one is allowed. After
+                                the given number of consecutive failures only the recovery key can unlock the vault.
+                            </Text>
+                            <Text>
+                                Leave the threshold empty to never lock the vault out.
+                            </Text>
+                        </>}
+                        target={ref}
+                        align={"self-start"}>
+                <form onSubmit={lockout_form.onSubmit((values) => handleSubmit(
+                    values,
+                    setIsLoading,
+                    setAttempts,
+                    lockout_form,
+                ))}>
+                    <Stack w={"100%"}>
+                        <NumberInput
+                            ref={ref}
+                            placeholder={"Lockout threshold"}
+                            min={1}
+                            allowDecimal={false}
+                            allowNegative={false}
+                            ml={"auto"}
+                            maw={"24rem"}
+                            miw={"24rem"}
+                            {...lockout_form.getInputProps("lockout_threshold")}
+                        />
+                        <PasswordInput
+                            placeholder={"Master password"}
+                            ml={"auto"}
+                            maw={"24rem"}
+                            miw={"24rem"}
+                            {...lockout_form.getInputProps("password")}
+                        />
+                        <Button variant={"light"}
+                                type={"submit"}
+                                ml={"auto"}
+                                loading={is_loading}>
+                            Save
+                        </Button>
+                    </Stack>
+                </form>
+            </SettingRow>
+            <Stack gap={"xs"}>
+                <Group justify={"space-between"}>
+                    <Text fw={600}>
+                        Recent unlock attempts
+                    </Text>
+                    {
+                        attempts && attempts.failed_attempts > 0 &&
+                        <Badge color={"red"} variant={"light"}>
+                            {attempts.failed_attempts} failed since the last unlock
+                        </Badge>
+                    }
+                </Group>
+                {
+                    attempts && attempts.history.length > 0
+                    ? <Table striped>
+                        <Table.Thead>
+                            <Table.Tr>
+                                <Table.Th>Date</Table.Th>
+                                <Table.Th>Outcome</Table.Th>
+                            </Table.Tr>
+                        </Table.Thead>
+                        <Table.Tbody>
+                            {
+                                [...attempts.history].reverse().map((attempt, index) => (
+                                    <Table.Tr key={index}>
+                                        <Table.Td>
+                                            {new Date(Number(attempt.timestamp) * 1000).toLocaleString()}
+                                        </Table.Td>
+                                        <Table.Td>
+                                            <Badge color={attempt.succeeded ? "green" : "red"} variant={"light"}>
+                                                {attempt.succeeded ? "Succeeded" : "Failed"}
+                                            </Badge>
+                                        </Table.Td>
+                                    </Table.Tr>
+                                ))
+                            }
+                        </Table.Tbody>
+                    </Table>
+                    : <Text size={"sm"} c={"dimmed"}>
+                        No unlock attempt recorded yet.
+                    </Text>
+                }
+            </Stack>
+        </Stack>
+    );
+};
@@ -0,0 +1,73 @@
+import { Select, Skeleton } from "@mantine/core";
+import { FC, useRef } from "react";
+import { Settings, SettingsResult } from "../../../../tauri-bindings.ts";
+import { SettingRow } from "../../setting-row.tsx";
+
+interface AutoLockProps {
+    settings?: Settings;
+    updateSettings: (updated_value: SettingsResult, refreshSettings: () => Promise<void>) => Promise<void>;
+    refreshSettings: () => Promise<void>;
+}
+
+const auto_lock_options = [
+    {label: "Never", value: "0"},
+    {label: "After 5 minutes", value: "5"},
+    {label: "After 15 minutes", value: "15"},
+    {label: "After 30 minutes", value: "30"},
+    {label: "After 1 hour", value: "60"},
+];
+
+async function handleOnChange(
+    value: string | null,
+    settings: Settings,
+    updateSettings: (
+        updated_value: SettingsResult,
+        refreshSettings: () => Promise<void>,
+    ) => Promise<void>,
+    refreshSettings: () => Promise<void>,
+) {
+    if (!value) {
+        return;
+    }
+
+    await updateSettings(
+        {
+            security: {
+                ...settings.security,
+                idle_lock_minutes: Number(value),
+            },
+        },
+        refreshSettings,
+    );
+}
+
+export const AutoLock: FC<AutoLockProps> = ({settings, updateSettings, refreshSettings}) => {
+    const ref = useRef(null);
+
+    return (
+        <SettingRow title={ "Auto-lock" }
+                    description={ "Lock the vault when the application has not been used for a while" }
+                    target={ ref }>
+            {
+                !settings && <Skeleton height={ 40 }
+                                       animate
+                                       maw={ "24rem" }
+                                       ml={ "auto" }/>
+            }
+            {
+                settings && (
+                             <Select
+                                 ref={ ref }
+                                 placeholder={ "Auto-lock" }
+                                 data={ auto_lock_options }
+                                 ml={ "auto" }
+                                 maw={ "24rem" }
+                                 allowDeselect={ false }
+                                 value={ settings.security.idle_lock_minutes.toString() }
+                                 onChange={ (value) => handleOnChange(value, settings, updateSettings, refreshSettings) }
+                             />
+                         )
+            }
+        </SettingRow>
+    );
+};
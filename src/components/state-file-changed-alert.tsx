@@ -0,0 +1,64 @@
+import { Alert, Button, Group, Stack, Text } from "@mantine/core";
+import { IconAlertTriangle } from "@tabler/icons-react";
+import { FC, useState } from "react";
+import { commands } from "../tauri-bindings.ts";
+
+interface StateFileChangedAlertProps {
+    reloadable: boolean;
+    onResolved: () => void;
+}
+
+export const StateFileChangedAlert: FC<StateFileChangedAlertProps> = ({reloadable, onResolved}) => {
+    const [ is_loading, setIsLoading ] = useState(false);
+    const [ error, setError ] = useState<string | null>(null);
+
+    const resolve = async (reload: boolean) => {
+        setIsLoading(true);
+        const result = reload ? await commands.reloadStateFile() : await commands.overwriteStateFile();
+        setIsLoading(false);
+
+        if (result.status === "error") {
+            setError(result.error);
+            return;
+        }
+
+        onResolved();
+    };
+
+    return (
+        <Alert color={ "orange" }
+               title={ "The vault file was changed outside of the application" }
+               icon={ <IconAlertTriangle/> }
+               mb={ "md" }>
+            <Stack gap={ "sm" }>
+                <Text size={ "sm" }>
+                    {
+                        reloadable
+                        ? "Another program replaced the vault file while your changes were not saved yet. Reload it to " +
+                          "use its content, or keep your version to overwrite it."
+                        : "The new vault file cannot be opened with the key of this vault, it may be damaged or " +
+                          "belong to another vault. Keep your version to overwrite it."
+                    }
+                </Text>
+                {
+                    error &&
+                    <Text size={ "sm" } c={ "red" }>
+                        { error }
+                    </Text>
+                }
+                <Group justify={ "flex-end" }>
+                    {
+                        reloadable &&
+                        <Button variant={ "light" } color={ "orange" } loading={ is_loading }
+                                onClick={ () => resolve(true) }>
+                            Reload from disk
+                        </Button>
+                    }
+                    <Button variant={ "light" } loading={ is_loading } onClick={ () => resolve(false) }>
+                        Keep my version
+                    </Button>
+                </Group>
+            </Stack>
+        </Alert>
+    );
+};
@@ -0,0 +1,29 @@
+import { Alert, Button, Stack, Text } from "@mantine/core";
+import { IconAlertTriangle } from "@tabler/icons-react";
+import { FC } from "react";
+
+interface StateRollbackAlertProps {
+    onOverride: () => void;
+    loading?: boolean;
+}
+
+export const StateRollbackAlert: FC<StateRollbackAlertProps> = ({onOverride, loading}) => {
+    return (
+        <Alert color={ "orange" }
+               title={ "This vault is older than expected" }
+               icon={ <IconAlertTriangle/> }>
+            <Stack gap={ "sm" }>
+                <Text size={ "sm" }>
+                    The vault file is older than the last one saved on this device. An old copy may have been
+                    restored to bring back removed accounts or disabled protections.
+                </Text>
+                <Text size={ "sm" }>
+                    Only continue if you restored this copy yourself, the changes made since then will be lost.
+                </Text>
+                <Button color={ "orange" } variant={ "light" } onClick={ onOverride } loading={ loading }>
+                    Open the older vault anyway
+                </Button>
+            </Stack>
+        </Alert>
+    );
+};
@@ -0,0 +1,116 @@
+import { Anchor, Button, Group, Select, Stack, TextInput, Title } from "@mantine/core";
+import { useForm } from "@mantine/form";
+import { modals } from "@mantine/modals";
+import { yupResolver } from "mantine-form-yup-resolver";
+import { FC, useEffect, useState } from "react";
+import { NavigateFunction, useNavigate } from "react-router";
+import * as yup from "yup";
+import { commands, VaultSummary } from "../tauri-bindings.ts";
+
+/**
+ * Select a vault, vaults that have not been set up yet are onboarded
+ * @param {VaultSummary} vault - The vault to select
+ * @param {NavigateFunction} navigate - The navigate function
+ * @returns {Promise<string | undefined>} The error, if any
+ */
+async function selectVault(vault: VaultSummary, navigate: NavigateFunction): Promise<string | undefined> {
+    const response = await commands.selectVault(vault.id);
+    if (response.status === "error") {
+        return response.error;
+    }
+
+    if (!vault.initialized) {
+        navigate("/onboard");
+    }
+}
+
+const ModalCreateVault: FC<{ navigate: NavigateFunction, close: () => void }> = ({navigate, close}) => {
+    const [ loading, setLoading ] = useState(false);
+    const form = useForm({
+        initialValues: {
+            name: "",
+        },
+        validate:      yupResolver(yup.object({
+            name: yup.string().trim().required("Vault name is required"),
+        })),
+    });
+
+    return (
+        <form onSubmit={ form.onSubmit(async (values) => {
+            setLoading(true);
+            const response = await commands.createVault(values.name);
+            if (response.status === "error") {
+                form.setErrors({name: response.error});
+                setLoading(false);
+                return;
+            }
+
+            const error = await selectVault(response.data, navigate);
+            setLoading(false);
+            if (error) {
+                form.setErrors({name: error});
+                return;
+            }
+
+            close();
+        }) }>
+            <Stack p={ "lg" }>
+                <TextInput label={ "Vault name" }
+                           description={ "Each vault has its own password, providers and settings" }
+                           { ...form.getInputProps("name") }/>
+                <Group ml={ "auto" }>
+                    <Button variant={ "light" } onClick={ close }>Cancel</Button>
+                    <Button type={ "submit" } loading={ loading }>Create</Button>
+                </Group>
+            </Stack>
+        </form>
+    );
+};
+
+/**
+ * Lists the vaults and allows to switch between them or to create a new one
+ */
+export const VaultPicker: FC = () => {
+    const navigate = useNavigate();
+    const [ vaults, setVaults ] = useState<VaultSummary[]>([]);
+    const [ error, setError ] = useState<string>();
+
+    const refresh = async () => {
+        const response = await commands.listVaults();
+        if (response.status === "ok") {
+            setVaults(response.data);
+        }
+    };
+
+    useEffect(() => {
+        refresh();
+    }, []);
+
+    const openCreateVault = () => {
+        const modal = modals.open({
+            title:    <Title order={ 4 }>New vault</Title>,
+            children: <ModalCreateVault navigate={ navigate } close={ () => modals.close(modal) }/>,
+        });
+    };
+
+    return (
+        <Stack gap={ "xs" }>
+            <Select data={ vaults.map((vault) => ({label: vault.name, value: vault.id})) }
+                    value={ vaults.find((vault) => vault.selected)?.id ?? null }
+                    allowDeselect={ false }
+                    error={ error }
+                    onChange={ async (value) => {
+                        const vault = vaults.find((vault) => vault.id === value);
+                        if (!vault) {
+                            return;
+                        }
+
+                        setError(await selectVault(vault, navigate));
+                        await refresh();
+                    } }/>
+            <Anchor component={ "button" } type={ "button" } size={ "sm" } ml={ "auto" } onClick={ openCreateVault }>
+                New vault
+            </Anchor>
+        </Stack>
+    );
+};
@@ -10,7 +10,6 @@ import {
     Text,
     Tooltip,
 } from "@mantine/core";
-import { fetch } from "@tauri-apps/plugin-http";
 import querystring from "query-string";
 import { all, unique } from "radash";
 import { Dispatch, FC, SetStateAction, useEffect, useState } from "react";
@@ -19,9 +18,10 @@ import { PageHeader } from "../components/page-header.tsx";
 import { ProviderIcon } from "../components/provider-icon.tsx";
 import { useProviders } from "../hooks/use-providers.ts";
 import { GoogleProvider } from "../providers/google-provider.tsx";
-import { commands, ProviderData, StorageProvider } from "../tauri-bindings.ts";
-import { dayjs } from "../utility/dayjs.ts";
+import { AccountView, commands, StorageProvider } from "../tauri-bindings.ts";
+import { authorizedFetch } from "../utility/authorized-fetch.ts";
 import { formatByteSize } from "../utility/format-bytesize.ts";
+import { isCredentialExpired } from "../utility/is-credential-expired.ts";
 
 interface ProviderStats {
     /**
@@ -98,10 +98,8 @@ interface ProviderStats {
     };
 }
 
-async function fetchGoogleStats(provider: ProviderData): Promise<ProviderStats | undefined> {
-    const now = dayjs.utc().unix();
-
-    if (provider.expiry <= now) {
+async function fetchGoogleStats(provider: AccountView): Promise<ProviderStats | undefined> {
+    if (isCredentialExpired(provider)) {
         const google_oauth = await GoogleProvider.init();
         const updated_provider = await google_oauth.refresh(provider);
         if (updated_provider) {
@@ -114,19 +112,12 @@ async function fetchGoogleStats(provider: ProviderData): Promise<ProviderStats |
         }
     }
 
-    const access_token = await commands.cryptDataGetRawDataAsString(provider.access_token);
-    if (access_token.status === "error") {
-        console.error("Failed to decrypt Google OAuth access token for provider", provider.owner);
-        return;
-    }
-
-    const response = await fetch("https://www.googleapis.com/drive/v3/about?" + querystring.stringify({
-        fields: "storageQuota",
-    }), {
-        headers: {
-            Authorization: `Bearer ${ access_token.data }`,
-        },
-    });
+    const response = await authorizedFetch(
+        {id: provider.id},
+        "https://www.googleapis.com/drive/v3/about?" + querystring.stringify({
+            fields: "storageQuota",
+        }),
+    );
 
     if (!response.ok) {
         console.error(
@@ -144,6 +135,14 @@ async function fetchGoogleStats(provider: ProviderData): Promise<ProviderStats |
 
     const available = BigInt(data.storageQuota.limit as string);
     const used = BigInt(data.storageQuota.usageInDrive as string);
+
+    // keep the last known quota with the account, the sizes fit in a number for the ipc
+    commands.recordAccountQuota(provider.id, Number(used) as unknown as bigint, Number(available) as unknown as bigint)
+            .then((result) => {
+                if (result.status === "error") {
+                    console.error("Failed to record the quota of provider", provider.owner, result.error);
+                }
+            });
     const free = available - used;
     const used_for_other = available - (
         free + used
@@ -192,7 +191,7 @@ async function fetchGoogleStats(provider: ProviderData): Promise<ProviderStats |
     return result;
 }
 
-async function fetchStats(provider: ProviderData): Promise<ProviderStats | undefined> {
+async function fetchStats(provider: AccountView): Promise<ProviderStats | undefined> {
     switch (provider.provider) {
         case "google":
             return fetchGoogleStats(provider);
@@ -200,7 +199,7 @@ async function fetchStats(provider: ProviderData): Promise<ProviderStats | undef
 }
 
 function fetchAllStats(
-    providers: ProviderData[],
+    providers: AccountView[],
     setStats: Dispatch<SetStateAction<ProviderStats[]>>,
     setLoading: Dispatch<SetStateAction<boolean>>,
 ) {
@@ -1,4 +1,5 @@
 import {
+    Alert,
     AppShell,
     AppShellMain,
     AppShellNavbar,
@@ -9,19 +10,37 @@ import {
     ScrollArea,
     Text,
 } from "@mantine/core";
-import { IconChartCohort, IconLayoutDashboard, IconServer, IconSettings } from "@tabler/icons-react";
+import {
+    IconAlertTriangle,
+    IconChartCohort,
+    IconLayoutDashboard,
+    IconLock,
+    IconServer,
+    IconSettings,
+} from "@tabler/icons-react";
 import { getVersion } from "@tauri-apps/api/app";
-import { useEffect, useState } from "react";
+import { useEffect, useRef, useState } from "react";
 import { Link, Outlet, useLocation, useNavigate } from "react-router";
 import { ProviderIcon } from "../components/provider-icon.tsx";
+import { StateFileChangedAlert } from "../components/state-file-changed-alert.tsx";
 import { useProviders } from "../hooks/use-providers.ts";
+import { commands, events, StateFileChanged } from "../tauri-bindings.ts";
 import { ensureIsAuthenticated } from "../utility/ensure-is-autenticated.ts";
+import { State } from "../utility/state.ts";
+
+/**
+ * The minimum delay between two activity reports, in milliseconds
+ */
+const ACTIVITY_REPORT_INTERVAL = 30_000;
 
 export default function Layout() {
     const navigate = useNavigate();
     const location = useLocation();
     const providers = useProviders();
     const [ version, setVersion ] = useState<string | null>(null);
+    const [ saveError, setSaveError ] = useState<string | null>(null);
+    const [ stateFileChange, setStateFileChange ] = useState<StateFileChanged | null>(null);
+    const last_activity_report = useRef(0);
 
     useEffect(() => {
         ensureIsAuthenticated(navigate);
@@ -31,6 +50,59 @@ export default function Layout() {
         getVersion().then((v) => setVersion(v));
     }, []);
 
+    useEffect(() => {
+        // the vault can be locked by another window
+        const unlisten = events.vaultLocked.listen(() => {
+            State.forget();
+            navigate("/login");
+        });
+
+        return () => {
+            unlisten.then((fn) => fn());
+        };
+    }, []);
+
+    useEffect(() => {
+        // the vault is locked after a period of inactivity, the reports are throttled
+        const reportActivity = () => {
+            const now = Date.now();
+            if (now - last_activity_report.current < ACTIVITY_REPORT_INTERVAL) {
+                return;
+            }
+
+            last_activity_report.current = now;
+            commands.reportActivity();
+        };
+
+        const activity_events = [ "mousemove", "mousedown", "keydown", "wheel", "touchstart" ];
+        activity_events.forEach((event) => window.addEventListener(event, reportActivity, {passive: true}));
+
+        return () => {
+            activity_events.forEach((event) => window.removeEventListener(event, reportActivity));
+        };
+    }, []);
+
+    useEffect(() => {
+        const unlisten = events.stateSaveFailed.listen((event) => {
+            setSaveError(event.payload.error);
+        });
+
+        return () => {
+            unlisten.then((fn) => fn());
+        };
+    }, []);
+
+    useEffect(() => {
+        // the changes that cannot be reloaded automatically are left to the user
+        const unlisten = events.stateFileChanged.listen((event) => {
+            setStateFileChange(event.payload);
+        });
+
+        return () => {
+            unlisten.then((fn) => fn());
+        };
+    }, []);
+
     return (
         <AppShell
             header={ {height: 0} }
@@ -86,6 +158,9 @@ export default function Layout() {
                              component={ Link }/>
                 </AppShellSection>
                 <AppShellSection>
+                    <NavLink label={ "Lock vault" }
+                             leftSection={ <IconLock size={ 20 } stroke={ 1.5 }/> }
+                             onClick={ () => State.lock().then(() => navigate("/login")) }/>
                     <Center>
                         <Text size={ "sm" } c={ "dimmed" }>
                             Storage Orchestra v{ version }
@@ -94,6 +169,22 @@ export default function Layout() {
                 </AppShellSection>
             </AppShellNavbar>
             <AppShellMain>
+                {
+                    saveError &&
+                    <Alert color={ "red" }
+                           title={ "Your changes could not be saved" }
+                           icon={ <IconAlertTriangle/> }
+                           mb={ "md" }
+                           withCloseButton
+                           onClose={ () => setSaveError(null) }>
+                        The changes are kept until the application is closed: { saveError }
+                    </Alert>
+                }
+                {
+                    stateFileChange &&
+                    <StateFileChangedAlert reloadable={ stateFileChange.reloadable }
+                                           onResolved={ () => setStateFileChange(null) }/>
+                }
                 <Outlet/>
             </AppShellMain>
         </AppShell>
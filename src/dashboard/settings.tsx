@@ -9,6 +9,10 @@ import { DefaultToNativeApp } from "../components/settings/general/general-behav
 import { DefaultToWebEditor } from "../components/settings/general/general-behaviour/default-to-web-editor.tsx";
 import { FontSize } from "../components/settings/general/theme/font-size.tsx";
 import { Theme } from "../components/settings/general/theme/theme.tsx";
+import { ExportVault, ImportVault } from "../components/settings/security/backup/vault-backup.tsx";
+import { RecoveryKey } from "../components/settings/security/passwords/recovery-key.tsx";
+import { UnlockAttempts } from "../components/settings/security/passwords/unlock-attempts.tsx";
+import { AutoLock } from "../components/settings/security/session/auto-lock.tsx";
 import { UpdatePassword } from "../components/settings/security/passwords/update-password.tsx";
 import { useSettings } from "../hooks/use-settings.ts";
 import { commands, SettingsResult } from "../tauri-bindings.ts";
@@ -122,6 +126,36 @@ export default function Settings() {
                         </Title>
                         <Stack m={"lg"} gap={"xl"}>
                             <UpdatePassword/>
+                            <Divider/>
+                            <RecoveryKey/>
+                        </Stack>
+                    </Card>
+                    <Card withBorder p={"xl"} mt={"lg"}>
+                        <Title order={4}>
+                            Session
+                        </Title>
+                        <Stack m={"lg"} gap={"xl"}>
+                            <AutoLock settings={ settings }
+                                      refreshSettings={ refreshSettings }
+                                      updateSettings={ updateSettings }/>
+                        </Stack>
+                    </Card>
+                    <Card withBorder p={"xl"} mt={"lg"}>
+                        <Title order={4}>
+                            Unlock attempts
+                        </Title>
+                        <Stack m={"lg"} gap={"xl"}>
+                            <UnlockAttempts/>
+                        </Stack>
+                    </Card>
+                    <Card withBorder p={"xl"} mt={"lg"}>
+                        <Title order={4}>
+                            Backup
+                        </Title>
+                        <Stack m={"lg"} gap={"xl"}>
+                            <ExportVault/>
+                            <Divider/>
+                            <ImportVault/>
                         </Stack>
                     </Card>
                     {
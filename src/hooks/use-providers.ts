@@ -4,21 +4,22 @@ import {
 } from "react";
 import {useNavigate} from "react-router";
 import {
+    AccountView,
     commands,
-    ProviderData,
+    events,
 } from "../tauri-bindings.ts";
 
 /**
  * Fetches the providers from the state and returns them, sorted by provider name.
  * If an error occurs, the user is navigated to the settings providers page.
- * @returns {ProviderData[]}
+ * @returns {AccountView[]}
  */
 export function useProviders() {
     const navigate = useNavigate();
-    const [providers, setProviders] = useState<ProviderData[]>([]);
+    const [providers, setProviders] = useState<AccountView[]>([]);
 
     useEffect(() => {
-        commands.getFromState("providers").then((providers) => {
+        commands.listAccounts().then((providers) => {
             if (providers.status === "error") {
                 console.error(providers.error);
 
@@ -27,10 +28,17 @@ export function useProviders() {
                 return;
             }
 
-            if ("providers" in providers.data) {
-                setProviders(providers.data.providers.sort((a, b) => a.owner.localeCompare(b.provider)));
-            }
+            setProviders(providers.data.sort((a, b) => a.owner.localeCompare(b.provider)));
+        });
+
+        // keep the providers in sync with the changes made elsewhere
+        const unlisten = events.providersChanged.listen((event) => {
+            setProviders(event.payload.value.sort((a, b) => a.owner.localeCompare(b.provider)));
         });
+
+        return () => {
+            unlisten.then((fn) => fn());
+        };
     }, []);
 
     return providers;
@@ -1,7 +1,7 @@
 import { useSignals } from "@preact/signals-react/runtime";
 import { Dispatch, SetStateAction, useCallback, useEffect, useState } from "react";
 import { NavigateFunction, useNavigate } from "react-router";
-import { commands, Settings } from "../tauri-bindings.ts";
+import { commands, events, Settings } from "../tauri-bindings.ts";
 
 function makeRefreshSettings(
     navigate: NavigateFunction,
@@ -45,5 +45,14 @@ export function useSettings() {
         }
     }, []);
 
+    useEffect(() => {
+        // keep the settings in sync with the changes made elsewhere
+        const unlisten = events.settingsChanged.listen((event) => setSettings(event.payload.value));
+
+        return () => {
+            unlisten.then((fn) => fn());
+        };
+    }, []);
+
     return {settings, refreshSettings};
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
-import { commands, ProviderData } from "../tauri-bindings.ts";
-import { dayjs } from "../utility/dayjs.ts";
+import { AccountView } from "../tauri-bindings.ts";
+import { isCredentialExpired } from "../utility/is-credential-expired.ts";
 
 export abstract class OAuthProvider {
     protected _port: number = 0;
@@ -26,21 +26,19 @@ export abstract class OAuthProvider {
 
     /**
      * Refresh the provider data
-     * @param {ProviderData} data - The provider data to refresh
-     * @returns {Promise<ProviderData | undefined>}
+     * @param {AccountView} data - The provider data to refresh
+     * @returns {Promise<AccountView | undefined>}
      */
-    public abstract refresh(data: ProviderData): Promise<ProviderData | undefined>;
+    public abstract refresh(data: AccountView): Promise<AccountView | undefined>;
 
     /**
      * Refresh a provider if it is stale
-     * @param {ProviderData} provider - The provider to refresh
-     * @returns {Promise<ProviderData | undefined>}
+     * @param {AccountView} provider - The provider to refresh
+     * @returns {Promise<AccountView | undefined>}
      */
-    protected async refreshProviderIfStale(provider: ProviderData): Promise<ProviderData | undefined> {
-        const now = dayjs.utc().unix();
-
+    protected async refreshProviderIfStale(provider: AccountView): Promise<AccountView | undefined> {
         // If the provider is expired, refresh it
-        if (provider.expiry <= now) {
+        if (isCredentialExpired(provider)) {
             const updated_provider = await this.refresh(provider);
 
             if (updated_provider) {
@@ -57,21 +55,6 @@ export abstract class OAuthProvider {
         return provider;
     }
 
-    /**
-     * Unpack the access token from a provider
-     * @param {ProviderData} provider
-     * @returns {Promise<string | undefined>}
-     */
-    protected async unpackAccessToken(provider: ProviderData): Promise<string | undefined> {
-        const access_token = await commands.cryptDataGetRawDataAsString(provider.access_token);
-        if (access_token.status === "error") {
-            console.error("Failed to decrypt OAuth access token for provider", provider.owner);
-            return;
-        }
-
-        return access_token.data;
-    }
-
     /**
      * Handle OAuth errors
      * @param {string} error - The error that occurred
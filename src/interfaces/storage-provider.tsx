@@ -1,11 +1,10 @@
 import { FileWithPath } from "@mantine/dropzone";
 import { modals } from "@mantine/modals";
-import { fetch } from "@tauri-apps/plugin-http";
 import { Dispatch, SetStateAction } from "react";
 import { ModalOpenWithNativeApp } from "../components/modal-open-with-native-app.tsx";
 import { ExtendedGoogleFile } from "../providers/google-provider.tsx";
-import { ProviderData, StorageProvider } from "../tauri-bindings.ts";
-import { State } from "../utility/state.ts";
+import { AccountView, ProviderAccount, StorageProvider } from "../tauri-bindings.ts";
+import { authorizedFetch } from "../utility/authorized-fetch.ts";
 import { DriveFile } from "./drive-file.ts";
 import { OAuthProvider } from "./oauth-provider.ts";
 import { TrackableModalInfo } from "./trackable-modal-info.ts";
@@ -13,15 +12,15 @@ import { TrackableModalInfo } from "./trackable-modal-info.ts";
 export abstract class Provider extends OAuthProvider {
     protected static _instance?: Provider;
 
-    protected constructor(protected _provider_name: StorageProvider, protected _providers: ProviderData[] = []) {
+    protected constructor(protected _provider_name: StorageProvider, protected _providers: AccountView[] = []) {
         super();
     }
 
     /**
      * Get GoogleOAuth providers
-     * @returns {ProviderData[]}
+     * @returns {AccountView[]}
      */
-    public get providers(): ProviderData[] {
+    public get providers(): AccountView[] {
         return this._providers;
     }
 
@@ -112,7 +111,7 @@ export abstract class Provider extends OAuthProvider {
     public abstract listFiles(owner: string, folder: string, page?: string): Promise<unknown>;
 
     /**
-     * Get the instance of the provider and access token, refreshing if necessary
+     * Get the instance of the provider and the account authorizing its requests, refreshing if necessary
      * @param {string} owner - The owner of the provider to get
      * @protected
      */
@@ -120,8 +119,8 @@ export abstract class Provider extends OAuthProvider {
         owner: string,
     ): Promise<
         {
-            provider: ProviderData;
-            accessToken: string
+            provider: AccountView;
+            account: ProviderAccount
         } | null
     > {
         let provider = this._providers.find((p) => p.owner === owner);
@@ -134,9 +133,12 @@ export abstract class Provider extends OAuthProvider {
             return null;
         }
 
-        const accessToken = await this.unpackAccessToken(provider);
-
-        return accessToken ? {provider, accessToken} : null;
+        return {
+            provider,
+            account: {
+                id: provider.id,
+            },
+        };
     }
 
     /**
@@ -175,22 +177,19 @@ export abstract class Provider extends OAuthProvider {
     }
 
     /**
-     * Creates a fetch request with the given access token, forwarding all other options
+     * Creates a fetch request authorized by the given account, forwarding all other options
      * @param {string} url - The URL to fetch
-     * @param {string} accessToken - The access token to use
+     * @param {ProviderAccount} account - The account whose access token is used
      * @param {RequestInit} options - The fetch options
      * @returns {Promise<Response>}
      * @protected
      */
     protected async authorizedFetch(
         url: string,
-        accessToken: string,
+        account: ProviderAccount,
         options?: RequestInit,
     ): Promise<Response> {
-        const headers = new Headers(options?.headers || {});
-        headers.set("Authorization", `Bearer ${ accessToken }`);
-
-        return await fetch(url, {...options, headers});
+        return await authorizedFetch(account, url, options);
     }
 
     /**
@@ -225,21 +224,4 @@ export abstract class Provider extends OAuthProvider {
     protected async wait(ms: number): Promise<void> {
         return new Promise((resolve) => setTimeout(resolve, ms));
     }
-
-    /**
-     * Replace all providers with the currently loaded ones
-     * @returns {Promise<void>}
-     */
-    protected async replaceAllProviders(): Promise<void> {
-        // get all providers
-        const storage = await State.init("");
-        const all_providers = await storage.get("providers");
-
-        if ("providers" in all_providers) {
-            // Remove all non-internal providers and add the new providers
-            const non_internal_providers = all_providers.providers.filter((provider) => provider.provider !==
-                                                                                        this._provider_name);
-            await storage.insert({providers: [ ...non_internal_providers, ...this._providers ]});
-        }
-    }
 }
\ No newline at end of file
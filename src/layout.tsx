@@ -1,13 +1,12 @@
 import "./assets/tailwind.css";
 import { localStorageColorSchemeManager, MantineProvider } from "@mantine/core";
 import { ModalsProvider } from "@mantine/modals";
-import { BaseDirectory, exists } from "@tauri-apps/plugin-fs";
 import { ContextMenuProvider } from "mantine-contextmenu";
 import { useEffect } from "react";
 import { NavigateFunction, Outlet, useNavigate } from "react-router";
 import { ModalError } from "./components/modal-error.tsx";
 import { useSimpleThemeContext } from "./hooks/use-theme.tsx";
-import { VAULT_NAME } from "./utility/state.ts";
+import { commands } from "./tauri-bindings.ts";
 
 const modals = {
     error: ModalError,
@@ -46,8 +45,9 @@ export default function Layout() {
  * @param navigate The navigate function
  */
 async function shouldOnboard(navigate: NavigateFunction) {
-    // Check if vault exists
-    const vault_exists = await exists(VAULT_NAME, {baseDir: BaseDirectory.AppLocalData});
+    // Check if the selected vault exists
+    const vaults = await commands.listVaults();
+    const vault_exists = vaults.status === "ok" && vaults.data.some((vault) => vault.selected && vault.initialized);
 
     // If vault does not exist, navigate to onboard page
     if (!vault_exists) {
@@ -1,11 +1,13 @@
-import { Button, Card, Center, Image, PasswordInput, Stack, Text, Title } from "@mantine/core";
+import { Anchor, Button, Card, Center, Image, PasswordInput, Stack, Text, TextInput, Title } from "@mantine/core";
 import { useForm, UseFormReturnType } from "@mantine/form";
 import { yupResolver } from "mantine-form-yup-resolver";
-import { createRef, useEffect } from "react";
-import { NavigateFunction, useNavigate } from "react-router";
+import { createRef, useEffect, useState } from "react";
+import { Link, NavigateFunction, useNavigate } from "react-router";
 import * as yup from "yup";
+import { StateRollbackAlert } from "./components/state-rollback-alert.tsx";
+import { VaultPicker } from "./components/vault-picker.tsx";
 import { ExtendedThemeContextType, useThemeContext } from "./hooks/use-theme.tsx";
-import { commands } from "./tauri-bindings.ts";
+import { commands, STATE_ROLLBACK_ERROR } from "./tauri-bindings.ts";
 import { computeDefaultPage } from "./utility/compute-default-page.ts";
 import { ensureIsAuthenticated } from "./utility/ensure-is-autenticated.ts";
 import { loadTheme } from "./utility/load-theme.ts";
@@ -13,8 +15,11 @@ import { State } from "./utility/state.ts";
 
 type LoginValues = {
     password: string;
+    totp_code: string;
 }
 
+const TWO_FACTOR_REQUIRED_ERROR = "Two factor authentication code required";
+
 type LoginForm = UseFormReturnType<LoginValues, (values: LoginValues) => LoginValues>;
 
 async function login(
@@ -22,15 +27,36 @@ async function login(
     form: LoginForm,
     navigate: NavigateFunction,
     theme_ctx: ExtendedThemeContextType,
+    setRequiresTotp: (requires_totp: boolean) => void,
+    setRollbackDetected: (rollback_detected: boolean) => void,
+    allow_rollback: boolean,
 ) {
     try {
-        await State.init(values.password);
+        await State.init(
+            values.password,
+            false,
+            values.totp_code !== "" ? values.totp_code : undefined,
+            allow_rollback,
+        );
         const settings = await commands.loadSettings();
         loadTheme(theme_ctx, settings);
-        navigate(computeDefaultPage(settings));
+        navigate(await computeDefaultPage(settings));
     }
     catch (e) {
         if (e instanceof Error) {
+            if (e.message === TWO_FACTOR_REQUIRED_ERROR) {
+                setRequiresTotp(true);
+                form.setErrors({
+                    totp_code: e.message,
+                });
+                return;
+            }
+
+            if (e.message === STATE_ROLLBACK_ERROR) {
+                setRollbackDetected(true);
+                return;
+            }
+
             form.setErrors({
                 password: e.message,
             });
@@ -42,10 +68,13 @@ export default function Login() {
     const theme_ctx = useThemeContext();
     const navigate = useNavigate();
     const ref = createRef<HTMLInputElement>();
+    const [ requires_totp, setRequiresTotp ] = useState(false);
+    const [ rollback_detected, setRollbackDetected ] = useState(false);
 
     const login_form = useForm({
         initialValues: {
-            password: ""
+            password: "",
+            totp_code: "",
         },
         validate: yupResolver(yup.object({
             password: yup.string().required("Password is required")
@@ -60,19 +89,43 @@ export default function Login() {
         ref.current?.focus();
     }, []);
 
+    const submit = (allow_rollback: boolean) => login_form.onSubmit((values) => login(
+        values,
+        login_form,
+        navigate,
+        theme_ctx,
+        setRequiresTotp,
+        setRollbackDetected,
+        allow_rollback,
+    ));
+
     return (
         <Center h={ "100svh" } className={ "bg-indigo-200" }>
             <Card shadow={ "xs" } padding={ "xl" } className={ "bg-white" } miw={ "32rem" } maw={ "32rem" }>
-                <form onSubmit={ login_form.onSubmit((values) => login(values, login_form, navigate, theme_ctx)) }>
+                <form onSubmit={ submit(false) }>
                     <Stack>
                         <Image src={ "/logo.svg" } alt={ "Storage Orchestra" } mah={ "4rem" } fit={ "contain" }/>
                         <div>
                             <Title className={ "text-center" }>Login</Title>
                             <Text className={ "text-center !font-semibold" } c={"dark.4"}>Sign in to your account</Text>
                         </div>
+                        <VaultPicker/>
                         <PasswordInput placeholder={ "Password" } { ...login_form.getInputProps("password") }
                                        ref={ ref }/>
+                        {
+                            requires_totp &&
+                            <TextInput placeholder={ "Authentication or recovery code" }
+                                       autoComplete={ "one-time-code" }
+                                       { ...login_form.getInputProps("totp_code") }/>
+                        }
+                        {
+                            rollback_detected &&
+                            <StateRollbackAlert onOverride={ () => submit(true)() }/>
+                        }
                         <Button type={"submit"} fullWidth>Sign in</Button>
+                        <Anchor component={ Link } to={ "/recover" } size={ "sm" } className={ "text-center" }>
+                            Forgot your password?
+                        </Anchor>
                     </Stack>
                 </form>
             </Card>
@@ -11,6 +11,7 @@ import { ThemeProvider } from "./hooks/use-theme.tsx";
 import Layout from "./layout.tsx";
 import Login from "./login.tsx";
 import Onboard from "./onboard.tsx";
+import Recover from "./recover.tsx";
 import { requestNotificationPermission } from "./utility/notification.ts";
 import "./utility/dayjs.ts";
 
@@ -26,6 +27,7 @@ ReactDOM.createRoot(root).render(
                     <Route path={ "/" } element={ <Layout/> }>
                         <Route path={ "login" } element={ <Login/> }/>
                         <Route path={ "onboard" } element={ <Onboard/> }/>
+                        <Route path={ "recover" } element={ <Recover/> }/>
                         <Route path={ "error" } element={ <Error/> }/>
 
                         <Route path={ "dashboard" } element={ <DashboardLayout/> }>
@@ -20,14 +20,15 @@ import { useForm, UseFormReturnType } from "@mantine/form";
 import { IconArrowRightDashed, IconBrandGoogleDrive, IconCloud, IconTrash } from "@tabler/icons-react";
 import { EmblaCarouselType } from "embla-carousel-react";
 import { yupResolver } from "mantine-form-yup-resolver";
-import { all, title } from "radash";
+import { title } from "radash";
 import { Dispatch, SetStateAction, useEffect, useState } from "react";
 import { NavigateFunction, useNavigate } from "react-router";
 import * as yup from "yup";
 import classes from "./assets/carousel.module.css";
 import { GoogleDriveSignIn } from "./components/google-drive-sign-in.tsx";
+import { showRecoveryKey } from "./components/settings/security/passwords/recovery-key.tsx";
 import { GoogleProvider } from "./providers/google-provider.tsx";
-import { ProviderData } from "./tauri-bindings.ts";
+import { AccountView, commands } from "./tauri-bindings.ts";
 import { State } from "./utility/state.ts";
 
 type StrongholdFormValues = {
@@ -60,6 +61,12 @@ async function setupState(
     try {
         await State.init(values.password);
 
+        // the recovery key is the only way to regain access to the data if the password is forgotten
+        const recovery_key = await commands.generateRecoveryKey(values.password);
+        if (recovery_key.status === "ok") {
+            showRecoveryKey(recovery_key.data);
+        }
+
         set_status(false);
         embla?.scrollNext();
     }
@@ -77,10 +84,10 @@ async function setupState(
 
 /**
  * Updates the providers list
- * @param {React.Dispatch<React.SetStateAction<ProviderData[]>>} setProviders
+ * @param {React.Dispatch<React.SetStateAction<AccountView[]>>} setProviders
  * @returns {number}
  */
-function updateProviders(setProviders: Dispatch<SetStateAction<ProviderData[]>>) {
+function updateProviders(setProviders: Dispatch<SetStateAction<AccountView[]>>) {
     return setInterval(async () => {
         const google = await GoogleProvider.init();
 
@@ -97,30 +104,29 @@ function updateProviders(setProviders: Dispatch<SetStateAction<ProviderData[]>>)
 
 /**
  * Drops a provider
- * @param {ProviderData} provider - The provider to drop
+ * @param {AccountView} provider - The provider to drop
  * @returns {Promise<void>}
  */
-async function dropProvider(provider: ProviderData): Promise<void> {
+async function dropProvider(provider: AccountView): Promise<void> {
     console.log("Dropping provider", provider);
-    const state = await State.init("");
-
-    const providers = await state.get("providers");
-    if ("providers" in providers) {
-        const new_providers = providers.providers.filter((p: ProviderData) => p.owner !== provider.owner);
-        await all([
-            state.insert({providers: new_providers}),
-            GoogleProvider.init().then((google) => google.dropProvider(provider.owner)),
-        ]);
 
-        console.log("Provider dropped");
+    const result = await commands.removeAccount(provider.id);
+    if (result.status === "error") {
+        console.error("Error dropping provider:", result.error);
+        return;
     }
+
+    const google = await GoogleProvider.init();
+    google.dropProvider(provider.owner);
+
+    console.log("Provider dropped");
 }
 
 function emblaSlideSpy(embla: EmblaCarouselType, setCurrentSlide: Dispatch<SetStateAction<number>>) {
     setCurrentSlide(embla.selectedScrollSnap());
 }
 
-function completeSetup(providers: ProviderData[], navigate: NavigateFunction) {
+function completeSetup(providers: AccountView[], navigate: NavigateFunction) {
     if (providers.length === 0) {
         return;
     }
@@ -134,7 +140,7 @@ export default function Onboard() {
     const [ embla, setEmbla ] = useState<Embla | null>(null);
     const [ current_slide, setCurrentSlide ] = useState<number>(0);
 
-    const [ providers, setProviders ] = useState<ProviderData[]>([]);
+    const [ providers, setProviders ] = useState<AccountView[]>([]);
 
     const [ is_initiating_stronghold, setInitiatingStronghold ] = useState<boolean>(false);
     const stronghold_form = useForm({
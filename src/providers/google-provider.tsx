@@ -1,14 +1,13 @@
 import { FileWithPath } from "@mantine/dropzone";
+import { Channel } from "@tauri-apps/api/core";
 import * as path from "@tauri-apps/api/path";
-import { BaseDirectory, FileHandle, open, size } from "@tauri-apps/plugin-fs";
-import { download } from "@tauri-apps/plugin-upload";
+import { BaseDirectory, FileHandle, open, remove, size } from "@tauri-apps/plugin-fs";
 import { Dispatch, SetStateAction } from "react";
 import { FILE_UPLOAD_CHUNK_SIZE } from "../constants.ts";
 import { DriveFile } from "../interfaces/drive-file.ts";
 import { TrackableModalInfo } from "../interfaces/trackable-modal-info.ts";
-import { ProviderData } from "../tauri-bindings.ts";
+import { AccountView, commands, DownloadProgress, ProviderAccount } from "../tauri-bindings.ts";
 import { DualSidedCache } from "../utility/cache.ts";
-import { State } from "../utility/state.ts";
 import { GoogleOAuth } from "./oauth/google.ts";
 
 export interface GoogleFileListing {
@@ -73,7 +72,7 @@ const ERROR_LISTING: GoogleFileListing = {
 class GoogleProvider extends GoogleOAuth {
     private _cached_folders = new DualSidedCache<string>();
 
-    protected constructor(providers: ProviderData[] = []) {
+    protected constructor(providers: AccountView[] = []) {
         super(providers);
     }
 
@@ -85,19 +84,17 @@ class GoogleProvider extends GoogleOAuth {
 
         console.log("Initializing GoogleProvider...");
 
-        // Load GoogleOAuthData records from stronghold
-        const storage = await State.init("");
-
-        let records = await storage.get("providers");
-        if ("providers" in records) {
+        // Load the accounts from the state, their credentials stay in the backend
+        const records = await commands.listAccounts();
+        if (records.status === "ok") {
             // Create instance and return it
             GoogleProvider._instance = new GoogleProvider(
-                records.providers.filter((provider) => provider.provider === "google"),
+                records.data.filter((provider) => provider.provider === "google"),
             );
             return GoogleProvider._instance as GoogleProvider;
         }
 
-        throw new Error("No providers found in state");
+        throw new Error(records.error);
     }
 
     public async listFiles(owner: string, folder: string = "root", page?: string): Promise<GoogleFileListing> {
@@ -105,7 +102,7 @@ class GoogleProvider extends GoogleOAuth {
         if (!valid) {
             return ERROR_LISTING;
         }
-        const {accessToken} = valid;
+        const {account} = valid;
 
         const url = `https://www.googleapis.com/drive/v3/files?${ new URLSearchParams({
             includeItemsFromAllDrives: "true",
@@ -116,7 +113,7 @@ class GoogleProvider extends GoogleOAuth {
             corpora:                   "user",
             q:                         `trashed = false and '${ folder }' in parents`,
         }) }`;
-        const response = await this.authorizedFetch(url, accessToken);
+        const response = await this.authorizedFetch(url, account);
 
         if (response.ok) {
             return (
@@ -134,12 +131,12 @@ class GoogleProvider extends GoogleOAuth {
         if (!valid) {
             return;
         }
-        const {accessToken} = valid;
+        const {account} = valid;
 
         const url = `https://www.googleapis.com/drive/v3/files/${ file.id }?${ new URLSearchParams({
             fields: "id, name, size, mimeType, kind, parents, thumbnailLink, shared, lastModifyingUser, owners, sharingUser, createdTime, modifiedTime",
         }) }`;
-        const response = await this.authorizedFetch(url, accessToken);
+        const response = await this.authorizedFetch(url, account);
 
         if (response.ok) {
             return (
@@ -161,14 +158,14 @@ class GoogleProvider extends GoogleOAuth {
         if (!valid) {
             return;
         }
-        const {accessToken} = valid;
+        const {account} = valid;
 
         const url = `https://www.googleapis.com/drive/v3/files/${ file.id }/download`;
-        const response = await this.authorizedFetch(url, accessToken, {method: "POST"});
+        const response = await this.authorizedFetch(url, account, {method: "POST"});
 
         if (response.ok) {
             let operation: GoogleDriveDownloadResponse = await response.json();
-            operation = await this.waitForOperation(operation, accessToken);
+            operation = await this.waitForOperation(operation, account);
 
             if (operation.error) {
                 console.error("Error downloading Google Drive file:", operation.error.message);
@@ -198,17 +195,29 @@ class GoogleProvider extends GoogleOAuth {
             }
             const download_path = await path.join(save_path, file.name.concat(extension));
 
-            const headers = new Map<string, string>();
-            headers.set("Authorization", `Bearer ${ accessToken }`);
-            await download(
+            const on_progress = new Channel<DownloadProgress>();
+            on_progress.onmessage = ({progress_total}) => {
+                modal.manual_override.path = download_path;
+                this.updateModalProgress(modal.progress.total, Number(progress_total), modal);
+            };
+
+            const downloaded = await commands.authorizedDownload(
+                account,
                 operation.response!.downloadUri,
                 download_path,
-                ({progressTotal}) => {
-                    modal.manual_override.path = download_path;
-                    this.updateModalProgress(modal.progress.total, progressTotal, modal);
-                },
-                headers,
+                on_progress,
             );
+            if (downloaded.status === "error") {
+                console.error("Error downloading Google Drive file:", downloaded.error);
+                return;
+            }
+
+            // files compressed before the upload are recognized and decompressed transparently
+            const finalized = await commands.finalizeDownload(download_path);
+            if (finalized.status === "error") {
+                console.error("Error decompressing Google Drive file:", finalized.error);
+                return;
+            }
 
             return download_path;
         }
@@ -229,13 +238,13 @@ class GoogleProvider extends GoogleOAuth {
         if (!valid) {
             return;
         }
-        const {accessToken} = valid;
+        const {account} = valid;
 
         // Extract path fragments and filename
         const {file: filename, folders: path_folders} = this.makePathFragments(file.path!);
 
         // Search for folders along the file path
-        const folders = await this.searchFilesDeeply(accessToken, folder, path_folders);
+        const folders = await this.searchFilesDeeply(account, folder, path_folders);
         console.log("folders before loop", {folders});
         console.log("path_folders before loop", {path_folders});
 
@@ -247,7 +256,7 @@ class GoogleProvider extends GoogleOAuth {
             const folder_name = path_folders[index];
             const parent_folder = folders[index - 1] as GoogleFile | undefined;
 
-            const new_folder = await this.mkdir(accessToken, parent_folder?.id ?? folder, folder_name);
+            const new_folder = await this.mkdir(account, parent_folder?.id ?? folder, folder_name);
             folders.push(new_folder);
         }
         console.log("folders after loop", {folders});
@@ -257,7 +266,7 @@ class GoogleProvider extends GoogleOAuth {
 
         // Check if the file already exists
         const folders_with_file = await this.searchFilesDeeply(
-            accessToken,
+            account,
             immediate_parent,
             [ filename ],
         );
@@ -267,7 +276,7 @@ class GoogleProvider extends GoogleOAuth {
         if (folders_with_file.length > 0) {
             // Update the file
             location = await this.makeUploadUrl(
-                accessToken,
+                account,
                 folders_with_file.at(-1).id,
                 undefined,
                 undefined,
@@ -276,7 +285,7 @@ class GoogleProvider extends GoogleOAuth {
         }
         else {
             // Create the file
-            location = await this.makeUploadUrl(accessToken, undefined, undefined, filename, immediate_parent);
+            location = await this.makeUploadUrl(account, undefined, undefined, filename, immediate_parent);
         }
 
         if (!location) {
@@ -291,7 +300,7 @@ class GoogleProvider extends GoogleOAuth {
                 range_upload_upper,
                 file.size,
                 location,
-                accessToken,
+                account,
             );
 
             while (!completed && range_upper !== range_upload_upper) {
@@ -302,7 +311,7 @@ class GoogleProvider extends GoogleOAuth {
                     range_upload_upper,
                     file.size,
                     location,
-                    accessToken,
+                    account,
                 );
 
                 range_upper = result.range_upper;
@@ -333,9 +342,16 @@ class GoogleProvider extends GoogleOAuth {
         if (!valid) {
             return;
         }
-        const {accessToken} = valid;
+        const {account} = valid;
+
+        // compress the file if the provider has the file compression enabled
+        const prepared = await commands.prepareUpload("google", file_path);
+        if (prepared.status === "error") {
+            throw new Error(prepared.error);
+        }
+        const {path: upload_path, compressed} = prepared.data;
 
-        const file_size = await size(file_path);
+        const file_size = await size(upload_path);
         this.updateModalProgress(0, file_size, modal);
 
         // get the filename from the filepath in order to include the default extension
@@ -344,12 +360,14 @@ class GoogleProvider extends GoogleOAuth {
 
         // mime types are not always accurate (especially with derived types such as docx (aka zip with specialized
         // structure inside)), so we will use the file extension to determine the mime type
-        const location = await this.makeUploadUrl(accessToken, file?.id, undefined, filename, undefined);
+        const location = await this.makeUploadUrl(account, file?.id, undefined, filename, undefined);
         if (!location) {
             return;
         }
 
-        const file_handle = await open(filename, {read: true, baseDir: BaseDirectory.Temp});
+        const file_handle = compressed
+                            ? await open(upload_path, {read: true})
+                            : await open(filename, {read: true, baseDir: BaseDirectory.Temp});
 
         const full_chunks = Math.floor(file_size / FILE_UPLOAD_CHUNK_SIZE);
         const last_chunk = file_size % FILE_UPLOAD_CHUNK_SIZE;
@@ -365,7 +383,7 @@ class GoogleProvider extends GoogleOAuth {
                 range_upload_lower,
                 range_upload_upper,
                 location,
-                accessToken,
+                account,
                 file_size,
             );
 
@@ -376,7 +394,7 @@ class GoogleProvider extends GoogleOAuth {
                     range_lower,
                     range_upper,
                     location,
-                    accessToken,
+                    account,
                     file_size,
                 );
 
@@ -395,7 +413,7 @@ class GoogleProvider extends GoogleOAuth {
                 range_upload_lower,
                 range_upload_upper,
                 location,
-                accessToken,
+                account,
                 file_size,
             );
 
@@ -406,7 +424,7 @@ class GoogleProvider extends GoogleOAuth {
                     range_lower,
                     range_upper,
                     location,
-                    accessToken,
+                    account,
                     file_size,
                 );
 
@@ -416,6 +434,12 @@ class GoogleProvider extends GoogleOAuth {
 
             this.updateModalProgress(file_size, file_size, modal);
         }
+
+        // the compressed copy is only needed for the upload
+        if (compressed) {
+            await file_handle.close();
+            await remove(upload_path);
+        }
     }
 
     public async createFolder(owner: string, parent: string, folder_name: string): Promise<DriveFile | undefined> {
@@ -423,15 +447,15 @@ class GoogleProvider extends GoogleOAuth {
         if (!valid) {
             return;
         }
-        const {accessToken} = valid;
+        const {account} = valid;
 
-        return await this.mkdir(accessToken, parent, folder_name);
+        return await this.mkdir(account, parent, folder_name);
     }
 
     /**
      * Generates an upload URL for Google Drive. Based on the presence of the ID, the method will either create a new
      * file or update an existing one.
-     * @param {string} accessToken - The access token to use for the upload.
+     * @param {ProviderAccount} account - The account authorizing the upload.
      * @param {string} id - The ID of the file to update.
      * @param {string} mime - The MIME type of the file to upload.
      * @param {string} filename - The name of the file to upload.
@@ -439,7 +463,7 @@ class GoogleProvider extends GoogleOAuth {
      * @private
      */
     private async makeUploadUrl(
-        accessToken: string,
+        account: ProviderAccount,
         id?: string,
         mime?: string,
         filename?: string,
@@ -447,7 +471,7 @@ class GoogleProvider extends GoogleOAuth {
     ): Promise<string | null> {
         const response = await this.authorizedFetch(
             `https://www.googleapis.com/upload/drive/v3/files${ id ? `/${ id }` : "" }?uploadType=resumable`,
-            accessToken,
+            account,
             {
                 method: id ? "PATCH" : "POST",
                 body:   JSON.stringify({
@@ -477,7 +501,7 @@ class GoogleProvider extends GoogleOAuth {
      */
     private async waitForOperation(
         operation: GoogleDriveDownloadResponse,
-        accessToken: string,
+        account: ProviderAccount,
     ): Promise<GoogleDriveDownloadResponse> {
         const operationUrl = `https://www.googleapis.com/drive/v3/operations/${ operation.name }`;
         const backoff = 1.5;
@@ -494,7 +518,7 @@ class GoogleProvider extends GoogleOAuth {
             await this.wait(delay);
             console.log("Checking operation status...");
 
-            const opResponse = await this.authorizedFetch(operationUrl, accessToken);
+            const opResponse = await this.authorizedFetch(operationUrl, account);
             if (opResponse.ok) {
                 operation = await opResponse.json();
             }
@@ -509,14 +533,14 @@ class GoogleProvider extends GoogleOAuth {
 
     /**
      * Searches for files in Google Drive recursively.
-     * @param {string} accessToken - The access token to use for the search.
+     * @param {ProviderAccount} account - The account authorizing the search.
      * @param {string} parentFolderId - The ID of the parent folder to search in.
      * @param {string[]} folders - The folders to search for.
      * @returns {Promise<any[]>}
      * @private
      */
     private async searchFilesDeeply(
-        accessToken: string,
+        account: ProviderAccount,
         parentFolderId: string,
         folders: string[],
     ): Promise<any[]> {
@@ -550,7 +574,7 @@ class GoogleProvider extends GoogleOAuth {
                 includeItemsFromAllDrives: "true",
             }) }`;
 
-            const response = await _this.authorizedFetch(url, accessToken);
+            const response = await _this.authorizedFetch(url, account);
 
             if (!response.ok) {
                 throw new Error(`Failed to fetch files: ${ response.statusText }`);
@@ -575,17 +599,17 @@ class GoogleProvider extends GoogleOAuth {
 
     /**
      * Creates a folder in Google Drive.
-     * @param {string} access_token - The access token to use for the upload.
+     * @param {ProviderAccount} account - The account authorizing the upload.
      * @param {string} parent_folder - The parent folder to create the new folder in.
      * @param {string} folder_name - The name of the new folder.
      * @private
      */
     private async mkdir(
-        access_token: string,
+        account: ProviderAccount,
         parent_folder: string,
         folder_name: string,
     ): Promise<DriveFile | undefined> {
-        const response = await this.authorizedFetch("https://www.googleapis.com/drive/v3/files", access_token, {
+        const response = await this.authorizedFetch("https://www.googleapis.com/drive/v3/files", account, {
             method:  "POST",
             headers: {
                 "Content-Type": "application/json",
@@ -627,7 +651,7 @@ class GoogleProvider extends GoogleOAuth {
      * @param {number} range_upper - The upper range of the file to upload.
      * @param {number} file_size - The size of the file to upload.
      * @param {string} location - The location to upload the file to.
-     * @param {string} access_token - The access token to use for the upload.
+     * @param {ProviderAccount} account - The account authorizing the upload.
      * @private
      */
     private async uploadToGoogleDrive(
@@ -636,9 +660,9 @@ class GoogleProvider extends GoogleOAuth {
         range_upper: number,
         file_size: number,
         location: string,
-        access_token: string,
+        account: ProviderAccount,
     ) {
-        const response = await this.authorizedFetch(location, access_token, {
+        const response = await this.authorizedFetch(location, account, {
             method:  "PUT",
             headers: {
                 "Content-Length": `${ data.byteLength }`,
@@ -677,7 +701,7 @@ class GoogleProvider extends GoogleOAuth {
      * @param {number} range_lower - The lower range of the file to upload.
      * @param {number} range_upper - The upper range of the file to upload.
      * @param {string} location - The location to upload the file to.
-     * @param {string} access_token - The access token to use for the upload.
+     * @param {ProviderAccount} account - The account authorizing the upload.
      * @param {number} file_size - The size of the file to upload.
      * @private
      */
@@ -686,7 +710,7 @@ class GoogleProvider extends GoogleOAuth {
         range_lower: number,
         range_upper: number,
         location: string,
-        access_token: string,
+        account: ProviderAccount,
         file_size: number,
     ) {
         // Determine if this is the final chunk (adjust the range accordingly)
@@ -700,7 +724,7 @@ class GoogleProvider extends GoogleOAuth {
             range_upper -= 1;
         }
 
-        return await this.uploadToGoogleDrive(buffer, range_lower, range_upper, file_size, location, access_token);
+        return await this.uploadToGoogleDrive(buffer, range_lower, range_upper, file_size, location, account);
     }
 }
 
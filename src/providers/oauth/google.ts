@@ -4,12 +4,12 @@ import { sendNotification } from "@tauri-apps/plugin-notification";
 import { openUrl } from "@tauri-apps/plugin-opener";
 import querystring from "query-string";
 import { Provider } from "../../interfaces/storage-provider.tsx";
-import { commands, ProviderData } from "../../tauri-bindings.ts";
+import { AccountView, commands, ProviderData } from "../../tauri-bindings.ts";
 import { dayjs } from "../../utility/dayjs.ts";
 import { StateMarker } from "../../utility/state.ts";
 
 export abstract class GoogleOAuth extends Provider {
-    protected constructor(providers: ProviderData[] = []) {
+    protected constructor(providers: AccountView[] = []) {
         super("google", providers);
     }
 
@@ -48,60 +48,30 @@ export abstract class GoogleOAuth extends Provider {
         }
     }
 
-    public async refresh(data: ProviderData) {
-        const refresh_token = await commands.cryptDataGetRawDataAsString(data.refresh_token);
+    public async refresh(data: AccountView) {
+        // the token is refreshed by the backend, the refresh token never reaches the webview
+        const result = await commands.refreshProvider({
+            id: data.id,
+        });
 
-        if (refresh_token.status === "error") {
-            console.error("Error fetching refresh token:", refresh_token.error);
+        if (result.status === "error") {
+            console.error("Error refreshing OAuth token:", result.error);
             return;
         }
 
-        const response = await fetch("https://oauth2.googleapis.com/token", {
-            method:  "POST",
-            headers: {
-                "Content-Type": "application/x-www-form-urlencoded",
-            },
-            body:    querystring.stringify({
-                refresh_token: refresh_token.data,
-                client_id:     "243418232258-pi0e0sa9g3ol72c212hg7k496g51k765.apps.googleusercontent.com",
-                client_secret: "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",
-                grant_type:    "refresh_token",
-            }),
-        });
-
-        if (response.ok) {
-            const json = await response.json();
-
-            const now = dayjs.utc().unix();
-            const expiry = now + json.expires_in;
-            const owner = data.owner;
-
-            // Encrypt access token
-            const access_token = await commands.makeCryptDataFromQualifiedString(StateMarker.asSecret(json.access_token as string));
-            if (access_token.status === "error") {
-                console.error("Error encrypting access token:", access_token.error);
-                return;
-            }
-
-            const new_data = {
-                access_token:  access_token.data,
-                refresh_token: data.refresh_token,
-                expiry,
-                owner,
-                provider:      "google",
-            } as ProviderData;
-
-            // Update record
-            const index = this._providers.findIndex((provider) => provider.owner === owner);
-            this._providers[index] = new_data;
+        // Reload the refreshed record
+        const refreshed = await commands.getAccount(data.id);
+        if (refreshed.status === "error") {
+            console.error("Error reloading the refreshed account:", refreshed.error);
+            return;
+        }
+        const new_data = refreshed.data;
 
-            await this.replaceAllProviders();
+        // Update record
+        const index = this._providers.findIndex((provider) => provider.owner === data.owner);
+        this._providers[index] = new_data;
 
-            return new_data;
-        }
-        else {
-            console.error("Error refreshing OAuth token:", response.statusText);
-        }
+        return new_data;
     }
 
     protected async handleOAuthError(error: string) {
@@ -138,7 +108,8 @@ export abstract class GoogleOAuth extends Provider {
 
             const now = dayjs.utc().unix();
             const expiry = now + json.expires_in;
-            const owner = JSON.parse(atob(json.id_token.split(".")[1])).email as string;
+            const claims = JSON.parse(atob(json.id_token.split(".")[1]));
+            const owner = claims.email as string;
 
             // Encrypt access token
             const access_token = await commands.makeCryptDataFromQualifiedString(StateMarker.asSecret(json.access_token as string));
@@ -155,17 +126,38 @@ export abstract class GoogleOAuth extends Provider {
             }
 
             const data = {
-                access_token:  access_token.data,
-                refresh_token: refresh_token.data,
-                expiry,
+                id:           "",
+                credential:   {
+                    kind:          "oauth",
+                    access_token:  access_token.data,
+                    refresh_token: refresh_token.data,
+                    expiry,
+                    token_type:    json.token_type ?? "Bearer",
+                    scopes:        (json.scope as string ?? "").split(" ").filter((scope) => scope.length > 0),
+                },
                 owner,
-                provider:      "google",
+                provider:     "google",
+                display_name: claims.name ?? null,
+                avatar_url:   claims.picture ?? null,
+                quota:        null,
             } as ProviderData;
 
-            // Add new record
-            this._providers.push(data);
+            // Signing in again to a known account updates it, the id is assigned by the backend
+            const index = this._providers.findIndex((provider) => provider.owner === owner);
+            const result = index === -1
+                           ? await commands.addAccount(data)
+                           : await commands.updateAccount(this._providers[index].id, data);
+            if (result.status === "error") {
+                console.error("Error saving the account:", result.error);
+                return;
+            }
 
-            await this.replaceAllProviders();
+            if (index === -1) {
+                this._providers.push(result.data);
+            }
+            else {
+                this._providers[index] = result.data;
+            }
         }
         else {
             console.error("Error fetching OAuth token:", response.statusText);
@@ -0,0 +1,149 @@
+import { Anchor, Button, Card, Center, Image, PasswordInput, Stack, Text, Textarea, TextInput, Title } from "@mantine/core";
+import { useForm, UseFormReturnType } from "@mantine/form";
+import { Channel } from "@tauri-apps/api/core";
+import { yupResolver } from "mantine-form-yup-resolver";
+import { useState } from "react";
+import { Link, NavigateFunction, useNavigate } from "react-router";
+import * as yup from "yup";
+import { StateRollbackAlert } from "./components/state-rollback-alert.tsx";
+import { ExtendedThemeContextType, useThemeContext } from "./hooks/use-theme.tsx";
+import { commands, PasswordUpdateEvent, STATE_ROLLBACK_ERROR } from "./tauri-bindings.ts";
+import { computeDefaultPage } from "./utility/compute-default-page.ts";
+import { loadTheme } from "./utility/load-theme.ts";
+import { State } from "./utility/state.ts";
+
+type RecoverValues = {
+    recovery_key: string;
+    new_password: string;
+    confirm_password: string;
+    totp_code: string;
+}
+
+type RecoverForm = UseFormReturnType<RecoverValues, (values: RecoverValues) => RecoverValues>;
+
+const TWO_FACTOR_REQUIRED_ERROR = "Two factor authentication code required";
+
+async function recover(
+    values: RecoverValues,
+    form: RecoverForm,
+    navigate: NavigateFunction,
+    theme_ctx: ExtendedThemeContextType,
+    setRequiresTotp: (requires_totp: boolean) => void,
+    setIsRecovering: (is_recovering: boolean) => void,
+    setRollbackDetected: (rollback_detected: boolean) => void,
+    allow_rollback: boolean,
+) {
+    setIsRecovering(true);
+
+    const channel = new Channel<PasswordUpdateEvent>();
+    const result = await commands.recoverState(
+        values.recovery_key,
+        values.new_password,
+        values.totp_code !== "" ? values.totp_code : null,
+        allow_rollback,
+        channel,
+    );
+
+    if (result.status === "error") {
+        setIsRecovering(false);
+
+        if (result.error === TWO_FACTOR_REQUIRED_ERROR) {
+            setRequiresTotp(true);
+            form.setErrors({
+                totp_code: result.error,
+            });
+            return;
+        }
+
+        if (result.error === STATE_ROLLBACK_ERROR) {
+            setRollbackDetected(true);
+            return;
+        }
+
+        form.setErrors({
+            recovery_key: result.error,
+        });
+        return;
+    }
+
+    // the rust state is already unlocked with the new password
+    await State.init(values.new_password, true);
+    const settings = await commands.loadSettings();
+    loadTheme(theme_ctx, settings);
+    navigate(await computeDefaultPage(settings));
+}
+
+export default function Recover() {
+    const theme_ctx = useThemeContext();
+    const navigate = useNavigate();
+    const [ requires_totp, setRequiresTotp ] = useState(false);
+    const [ is_recovering, setIsRecovering ] = useState(false);
+    const [ rollback_detected, setRollbackDetected ] = useState(false);
+
+    const recover_form = useForm({
+        initialValues: {
+            recovery_key:     "",
+            new_password:     "",
+            confirm_password: "",
+            totp_code:        "",
+        },
+        validate:      yupResolver(yup.object({
+            recovery_key:     yup.string().required("Recovery key is required"),
+            new_password:     yup.string().min(8).required("New password is required"),
+            confirm_password: yup.string()
+                              .required("Confirm password is required")
+                              .oneOf([ yup.ref("new_password") ], "Passwords do not match"),
+        })),
+    });
+
+    const submit = (allow_rollback: boolean) => recover_form.onSubmit((values) => recover(
+        values,
+        recover_form,
+        navigate,
+        theme_ctx,
+        setRequiresTotp,
+        setIsRecovering,
+        setRollbackDetected,
+        allow_rollback,
+    ));
+
+    return (
+        <Center h={ "100svh" } className={ "bg-indigo-200" }>
+            <Card shadow={ "xs" } padding={ "xl" } className={ "bg-white" } miw={ "32rem" } maw={ "32rem" }>
+                <form onSubmit={ submit(false) }>
+                    <Stack>
+                        <Image src={ "/logo.svg" } alt={ "Storage Orchestra" } mah={ "4rem" } fit={ "contain" }/>
+                        <div>
+                            <Title className={ "text-center" }>Recover access</Title>
+                            <Text className={ "text-center !font-semibold" } c={ "dark.4" }>
+                                Use your recovery key to set a new password
+                            </Text>
+                        </div>
+                        <Textarea placeholder={ "Recovery key words" }
+                                  autosize
+                                  minRows={ 3 }
+                                  { ...recover_form.getInputProps("recovery_key") }/>
+                        <PasswordInput placeholder={ "New master password" }
+                                       { ...recover_form.getInputProps("new_password") }/>
+                        <PasswordInput placeholder={ "Confirm new master password" }
+                                       { ...recover_form.getInputProps("confirm_password") }/>
+                        {
+                            requires_totp &&
+                            <TextInput placeholder={ "Authentication or recovery code" }
+                                       autoComplete={ "one-time-code" }
+                                       { ...recover_form.getInputProps("totp_code") }/>
+                        }
+                        {
+                            rollback_detected &&
+                            <StateRollbackAlert onOverride={ () => submit(true)() } loading={ is_recovering }/>
+                        }
+                        <Button type={ "submit" } fullWidth loading={ is_recovering }>Recover</Button>
+                        <Anchor component={ Link } to={ "/login" } size={ "sm" } className={ "text-center" }>
+                            Back to login
+                        </Anchor>
+                    </Stack>
+                </form>
+            </Card>
+        </Center>
+    );
+}
@@ -19,14 +19,43 @@ async startServer() : Promise<Result<number, string>> {
  * # Arguments
  * 
  * * `password` - The password to set.
+ * * `totp_code` - The two factor authentication code (or a recovery code), required only if two
+ * factor authentication is enabled.
+ * * `allow_rollback` - Whether to open a state file older than the last one saved on this
+ * device or one of its backups, the unlock fails with `STATE_ROLLBACK_ERROR` otherwise.
  * 
  * # Returns
  * 
  * Nothing.
  */
-async initState(password: string) : Promise<Result<null, string>> {
+async initState(password: string, totpCode: string | null, allowRollback: boolean) : Promise<Result<null, string>> {
     try {
-    return { status: "ok", data: await TAURI_INVOKE("init_state", { password }) };
+    return { status: "ok", data: await TAURI_INVOKE("init_state", { password, totpCode, allowRollback }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Unlocks the application secure storage using the recovery key and sets a new password.
+ * 
+ * # Arguments
+ * 
+ * * `recovery_key` - The recovery key as a word-list mnemonic.
+ * * `new_password` - The new password.
+ * * `totp_code` - The two factor authentication code (or a recovery code), required only if two
+ * factor authentication is enabled.
+ * * `allow_rollback` - Whether to recover a state file older than the last one saved on this
+ * device or one of its backups, the recovery fails with `STATE_ROLLBACK_ERROR` otherwise.
+ * * `ev` - The event channel.
+ * 
+ * # Returns
+ * 
+ * Nothing.
+ */
+async recoverState(recoveryKey: string, newPassword: string, totpCode: string | null, allowRollback: boolean, ev: TAURI_CHANNEL<PasswordUpdateEvent>) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("recover_state", { recoveryKey, newPassword, totpCode, allowRollback, ev }) };
 } catch (e) {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
@@ -92,6 +121,73 @@ async insertInState(value: AppStateDeepResult) : Promise<Result<null, string>> {
     else return { status: "error", error: e  as any };
 }
 },
+/**
+ * Applies a patch to the state.
+ * 
+ * The operations are applied in order under the write lock, either all of them are applied or
+ * none is.
+ * 
+ * # Arguments
+ * 
+ * * `state` - The state to patch.
+ * * `operations` - The patch operations.
+ * 
+ * # Returns
+ * 
+ * Nothing.
+ */
+async patchState(operations: StatePatchOperation[]) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("patch_state", { operations }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Replaces the state in memory with the state file, used when the state file was changed outside
+ * of the application.
+ * 
+ * The changes waiting to be saved are dropped.
+ * 
+ * # Arguments
+ * 
+ * * `app` - The application handle.
+ * * `state` - The application state.
+ * 
+ * # Returns
+ * 
+ * Nothing.
+ */
+async reloadStateFile() : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("reload_state_file") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Overwrites the state file with the state in memory, used when the state file was changed
+ * outside of the application.
+ * 
+ * # Arguments
+ * 
+ * * `app` - The application handle.
+ * * `state` - The application state.
+ * 
+ * # Returns
+ * 
+ * Nothing.
+ */
+async overwriteStateFile() : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("overwrite_state_file") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
  * Checks if the user is authenticated.
  * 
@@ -103,25 +199,52 @@ async isAuthenticated() : Promise<boolean> {
     return await TAURI_INVOKE("is_authenticated");
 },
 /**
- * Gets the password for the application secure storage if already loaded in memory.
+ * Records that the user is active, this postpones the idle lock of the vault.
+ * 
+ * # Returns
+ * 
+ * Nothing.
+ */
+async reportActivity() : Promise<void> {
+    await TAURI_INVOKE("report_activity");
+},
+/**
+ * Locks the vault wiping the secrets and the decrypted state from memory.
+ * 
+ * # Arguments
+ * 
+ * * `state` - The application state.
  * 
- * # Why
+ * # Returns
+ * 
+ * Nothing.
+ */
+async lockVault() : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("lock_vault") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Unlocks a previously locked vault.
  * 
- * This function is useful when the frontend needs to initialize the State class or some other
- * singleton but the password was not provided at application startup.
- * This is most commonly a development issue (commonly derived from hot-reloading) but implementing
- * a safe reboot of singleton in case the password is missing can be useful to improve the user
- * experience.
+ * # Arguments
  * 
- * **Note**: This function DOES NOT expose the password to other applications or the networks.
+ * * `password` - The password.
+ * * `totp_code` - The two factor authentication code (or a recovery code), required only if two
+ * factor authentication is enabled.
+ * * `allow_rollback` - Whether to open a state file older than the last one saved on this
+ * device.
  * 
  * # Returns
  * 
- * The password.
+ * Nothing.
  */
-async getPassword() : Promise<Result<string, string>> {
+async unlockVault(password: string, totpCode: string | null, allowRollback: boolean) : Promise<Result<null, string>> {
     try {
-    return { status: "ok", data: await TAURI_INVOKE("get_password") };
+    return { status: "ok", data: await TAURI_INVOKE("unlock_vault", { password, totpCode, allowRollback }) };
 } catch (e) {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
@@ -169,8 +292,12 @@ async updateSettings(value: SettingsResult) : Promise<Result<null, string>> {
 /**
  * Check that the password is correct
  * 
+ * The failed checks delay the next ones and count towards the lockout threshold of the vault, a
+ * successful check allows the sensitive commands to run for a short time.
+ * 
  * # Arguments
  * 
+ * - `app` - The application handle
  * - `state` - The application state
  * - `password` - The password to check
  * 
@@ -189,7 +316,9 @@ async checkPassword(password: string) : Promise<Result<null, string>> {
 /**
  * Update the password
  * 
- * Note that this triggers the update of all encrypted data too as the password is used to encrypt them.
+ * Only the password hash and the password key slot are updated, the secrets are encrypted with
+ * the data key that the password key slot wraps. The rotation is built off to the side and
+ * journaled before being applied, the journal is removed once the rotated state is on disk.
  * 
  * # Arguments
  * 
@@ -211,162 +340,930 @@ async updatePassword(currentPassword: string, newPassword: string, ev: TAURI_CHA
 }
 },
 /**
- * Get the raw data as a string
+ * Start the enrollment of a TOTP secret
+ * 
+ * The secret is stored but two factor authentication is only enabled once a code generated by
+ * the authenticator application is confirmed with `confirm_totp`.
  * 
  * # Arguments
  * 
- * * `state` - The state to get the data from
- * * `data` - The data to get
+ * - `state` - The application state
+ * - `password` - The password
  * 
  * # Returns
  * 
- * The raw data as a string
+ * Returns the data needed to register the secret in an authenticator application
  */
-async cryptDataGetRawDataAsString(data: CryptData) : Promise<Result<string, string>> {
+async enrollTotp(password: string) : Promise<Result<TotpEnrollment, string>> {
     try {
-    return { status: "ok", data: await TAURI_INVOKE("crypt_data_get_raw_data_as_string", { data }) };
+    return { status: "ok", data: await TAURI_INVOKE("enroll_totp", { password }) };
 } catch (e) {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
 }
 },
 /**
- * Get the raw data
+ * Confirm the enrollment of the TOTP secret and enable two factor authentication
  * 
  * # Arguments
  * 
- * * `state` - The state to get the data from
- * * `data` - The data to get
+ * - `state` - The application state
+ * - `code` - A code generated by the authenticator application
  * 
  * # Returns
  * 
- * The raw data
+ * Returns the single-use recovery codes, they are shown only once
  */
-async cryptDataGetRawData(data: CryptData) : Promise<Result<number[], string>> {
+async confirmTotp(code: string) : Promise<Result<string[], string>> {
     try {
-    return { status: "ok", data: await TAURI_INVOKE("crypt_data_get_raw_data", { data }) };
+    return { status: "ok", data: await TAURI_INVOKE("confirm_totp", { code }) };
 } catch (e) {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
 }
 },
 /**
- * Create a new CryptData struct using a fully qualified string
+ * Verify a code generated by the authenticator application
  * 
  * # Arguments
  * 
- * * `data` - The fully qualified string
+ * - `state` - The application state
+ * - `code` - The code to verify
  * 
  * # Returns
  * 
- * The CryptData struct
+ * Returns `Ok(())` if the code is valid, otherwise returns `Err(String)`
+ */
+async verifyTotp(code: string) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("verify_totp", { code }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Regenerate the recovery codes, invalidating the previous ones
  * 
- * # Example
+ * # Arguments
  * 
- * ```typescript
- * const data = "example string";
- * const qualified_data = StateMarker.asSecret(data);
+ * - `state` - The application state
+ * - `code` - A code generated by the authenticator application
  * 
- * const crypt_data = await invoke("make_crypt_data_from_qualified_string", {data: qualified_data});
- * ```
+ * # Returns
+ * 
+ * Returns the new single-use recovery codes, they are shown only once
  */
-async makeCryptDataFromQualifiedString(data: string) : Promise<Result<CryptData, string>> {
+async regenerateRecoveryCodes(code: string) : Promise<Result<string[], string>> {
     try {
-    return { status: "ok", data: await TAURI_INVOKE("make_crypt_data_from_qualified_string", { data }) };
+    return { status: "ok", data: await TAURI_INVOKE("regenerate_recovery_codes", { code }) };
 } catch (e) {
     if(e instanceof Error) throw e;
     else return { status: "error", error: e  as any };
 }
 },
-    /**
-     * Watch a file for the default application to open it and return its content when the application
-     * closes
-     *
-     * # Arguments
-     *
-     * * `app` - The Tauri app handle.
-     * * `file_path` - The path to the file to watch.
-     *
-     * # Returns
-     *
-     * A `Result` containing the file path, or an error message if the file could not be opened.
-     */
-    async watchNativeOpen(filePath: string, event: TAURI_CHANNEL<WatchProcessEvent>): Promise<Result<string, string>> {
-        try {
-            return {status: "ok", data: await TAURI_INVOKE("watch_native_open", {filePath, event})};
-        }
-        catch (e) {
-            if (e instanceof Error) {
-                throw e;
-            }
-            else {
-                return {status: "error", error: e as any};
-            }
-        }
-    },
-    /**
-     * Cancel the watch_native_open command if it is running
-     *
-     * # Arguments
-     *
-     * * `state` - The Tauri app state.
-     *
-     * # Returns
-     *
-     * A `Result` containing `Ok(())` if the command was cancelled, or an error message if the command
-     * was not running.
-     */
-    async cancelWatchNativeOpen(): Promise<Result<null, string>> {
-        try {
-            return {status: "ok", data: await TAURI_INVOKE("cancel_watch_native_open")};
-        }
-        catch (e) {
-            if (e instanceof Error) {
-                throw e;
-            }
-            else {
-                return {status: "error", error: e as any};
-            }
-        }
-    }
-}
-
-/** user-defined events **/
-
-
-
-/** user-defined constants **/
-
-export const STATE_FILE = "state.json" as const;
-
-/** user-defined types **/
-
-export type AppStateDeepKeys = "debounced_saver" | "cancellation_tokens" | "password" | "providers" | "settings"
-export type AppStateDeepResult = 
-/**
- * The password to access the secure storage
- */
-{ password: CryptData } | 
 /**
- * The list of providers
+ * Disable two factor authentication removing the secret and the recovery codes
+ * 
+ * # Arguments
+ * 
+ * - `state` - The application state
+ * - `password` - The password
+ * - `code` - A code generated by the authenticator application or a recovery code
+ * 
+ * # Returns
+ * 
+ * Returns `Ok(())` if two factor authentication was disabled, otherwise returns `Err(String)`
  */
-{ providers: ProviderData[] } | 
+async disableTotp(password: string, code: string) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("disable_totp", { password, code }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * The settings of the application
+ * Generate a new recovery key, replacing the previous one if any
+ * 
+ * The recovery key wraps the data key in its own key slot, it is never stored and is shown
+ * only once.
+ * 
+ * # Arguments
+ * 
+ * - `state` - The application state
+ * - `password` - The password
+ * 
+ * # Returns
+ * 
+ * Returns the recovery key as a word-list mnemonic
  */
-{ settings: Settings }
+async generateRecoveryKey(password: string) : Promise<Result<string, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("generate_recovery_key", { password }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * Represent some data that have been managed cryptographically
+ * Revoke the recovery key
+ * 
+ * # Arguments
+ * 
+ * - `state` - The application state
+ * - `password` - The password
+ * 
+ * # Returns
+ * 
+ * Returns `Ok(())` if the recovery key was revoked, otherwise returns `Err(String)`
  */
-export type CryptData = { 
+async revokeRecoveryKey(password: string) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("revoke_recovery_key", { password }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * The cryptographically modified data
+ * Check whether a recovery key is configured
+ * 
+ * # Arguments
+ * 
+ * - `state` - The application state
+ * 
+ * # Returns
+ * 
+ * Returns `true` if a recovery key is configured
  */
-data: number[]; 
+async hasRecoveryKey() : Promise<Result<boolean, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("has_recovery_key") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 /**
- * The raw data, never stored on disk (this field is never serialized)
+ * Get the password checks of the vault
+ * 
+ * # Arguments
+ * 
+ * - `app` - The application handle
+ * 
+ * # Returns
+ * 
+ * Returns the failed attempts counter, the lockout threshold and the latest attempts
  */
-raw_data: number[] | null; 
+async getUnlockAttempts() : Promise<Result<UnlockAttempts, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_unlock_attempts") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Set the number of consecutive failed attempts after which only the recovery key can unlock the
+ * vault
+ * 
+ * # Arguments
+ * 
+ * - `app` - The application handle
+ * - `state` - The application state
+ * - `password` - The password
+ * - `threshold` - The lockout threshold, `None` to never lock the vault out
+ * 
+ * # Returns
+ * 
+ * Returns the updated unlock attempts
+ */
+async setUnlockLockoutThreshold(password: string, threshold: number | null) : Promise<Result<UnlockAttempts, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_unlock_lockout_threshold", { password, threshold }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * List the vaults
+ * 
+ * # Arguments
+ * 
+ * * `app` - The application handle
+ * 
+ * # Returns
+ * 
+ * The vaults
+ */
+async listVaults() : Promise<Result<VaultSummary[], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("list_vaults") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Create a new vault, the vault is set up with its own password once selected
+ * 
+ * # Arguments
+ * 
+ * * `app` - The application handle
+ * * `name` - The name of the vault
+ * 
+ * # Returns
+ * 
+ * The new vault
+ */
+async createVault(name: string) : Promise<Result<VaultSummary, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("create_vault", { name }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Rename a vault
+ * 
+ * # Arguments
+ * 
+ * * `app` - The application handle
+ * * `id` - The id of the vault
+ * * `name` - The new name of the vault
+ * 
+ * # Returns
+ * 
+ * Nothing
+ */
+async renameVault(id: string, name: string) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("rename_vault", { id, name }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Delete a vault along with its state file and backups, the selected vault cannot be deleted
+ * 
+ * # Arguments
+ * 
+ * * `app` - The application handle
+ * * `id` - The id of the vault
+ * * `password` - The password of the vault, ignored if the vault has not been set up
+ * 
+ * # Returns
+ * 
+ * Nothing
+ */
+async deleteVault(id: string, password: string) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("delete_vault", { id, password }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Select the vault used by the application, the current vault is locked
+ * 
+ * # Arguments
+ * 
+ * * `app` - The application handle
+ * * `state` - The application state
+ * * `id` - The id of the vault
+ * 
+ * # Returns
+ * 
+ * Nothing
+ */
+async selectVault(id: string) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("select_vault", { id }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Export the accounts and the settings of the vault as a bundle sealed with an export passphrase
+ * 
+ * # Arguments
+ * 
+ * * `app` - The application handle
+ * * `state` - The application state
+ * * `password` - The password of the vault
+ * * `passphrase` - The export passphrase, required to import the bundle
+ * 
+ * # Returns
+ * 
+ * The bundle file content
+ */
+async exportVault(password: string, passphrase: string) : Promise<Result<string, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("export_vault", { password, passphrase }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Import a bundle in the vault, the imported tokens are encrypted with the data key of the vault
+ * 
+ * # Arguments
+ * 
+ * * `app` - The application handle
+ * * `state` - The application state
+ * * `password` - The password of the vault
+ * * `bundle` - The bundle file content
+ * * `passphrase` - The export passphrase of the bundle
+ * * `mode` - How the bundle is applied to the vault
+ * 
+ * # Returns
+ * 
+ * The manifest of the imported bundle
+ */
+async importVault(password: string, bundle: string, passphrase: string, mode: VaultImportMode) : Promise<Result<VaultBundleManifest, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("import_vault", { password, bundle, passphrase, mode }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * List the storage provider accounts, their credentials are not exposed
+ * 
+ * # Arguments
+ * 
+ * * `state` - The application state
+ * 
+ * # Returns
+ * 
+ * The accounts
+ */
+async listAccounts() : Promise<Result<AccountView[], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("list_accounts") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Get a storage provider account, its credential is not exposed
+ * 
+ * # Arguments
+ * 
+ * * `state` - The application state
+ * * `id` - The id of the account
+ * 
+ * # Returns
+ * 
+ * The account
+ */
+async getAccount(id: string) : Promise<Result<AccountView, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_account", { id }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Add a storage provider account, an account can only be added once per provider
+ * 
+ * # Arguments
+ * 
+ * * `app` - The application handle
+ * * `state` - The application state
+ * * `account` - The account to add, its id is assigned by the registry
+ * 
+ * # Returns
+ * 
+ * The added account, its credential is not exposed
+ */
+async addAccount(account: ProviderData) : Promise<Result<AccountView, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("add_account", { account }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Update a storage provider account, used when the account is signed in again
+ * 
+ * # Arguments
+ * 
+ * * `app` - The application handle
+ * * `state` - The application state
+ * * `id` - The id of the account
+ * * `account` - The new data of the account
+ * 
+ * # Returns
+ * 
+ * The updated account, its credential is not exposed
+ */
+async updateAccount(id: string, account: ProviderData) : Promise<Result<AccountView, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("update_account", { id, account }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Remove a storage provider account, the default page is reset if it shows the account
+ * 
+ * # Arguments
+ * 
+ * * `app` - The application handle
+ * * `state` - The application state
+ * * `id` - The id of the account
+ * 
+ * # Returns
+ * 
+ * Nothing
+ */
+async removeAccount(id: string) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("remove_account", { id }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Record the storage quota of a storage provider account
+ * 
+ * The snapshot is informational, no change event is emitted so that the views showing the quota
+ * are not refreshed by their own snapshot.
+ * 
+ * # Arguments
+ * 
+ * * `app` - The application handle
+ * * `state` - The application state
+ * * `id` - The id of the account
+ * * `used` - The used storage in bytes
+ * * `total` - The total storage in bytes, `None` if the storage is unlimited
+ * 
+ * # Returns
+ * 
+ * Nothing
+ */
+async recordAccountQuota(id: string, used: bigint, total: bigint | null) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("record_account_quota", { id, used, total }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Create a new CryptData struct using a fully qualified string
+ * 
+ * # Arguments
+ * 
+ * * `data` - The fully qualified string
+ * 
+ * # Returns
+ * 
+ * The CryptData struct
+ * 
+ * # Example
+ * 
+ * ```typescript
+ * const data = "example string";
+ * const qualified_data = StateMarker.asSecret(data);
+ * 
+ * const crypt_data = await invoke("make_crypt_data_from_qualified_string", {data: qualified_data});
+ * ```
+ */
+async makeCryptDataFromQualifiedString(data: string) : Promise<Result<CryptData, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("make_crypt_data_from_qualified_string", { data }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+    /**
+     * Watch a file for the default application to open it and return its content when the application
+     * closes
+     *
+     * # Arguments
+     *
+     * * `app` - The Tauri app handle.
+     * * `file_path` - The path to the file to watch.
+     *
+     * # Returns
+     *
+     * A `Result` containing the file path, or an error message if the file could not be opened.
+     */
+    async watchNativeOpen(filePath: string, event: TAURI_CHANNEL<WatchProcessEvent>): Promise<Result<string, string>> {
+        try {
+            return {status: "ok", data: await TAURI_INVOKE("watch_native_open", {filePath, event})};
+        }
+        catch (e) {
+            if (e instanceof Error) {
+                throw e;
+            }
+            else {
+                return {status: "error", error: e as any};
+            }
+        }
+    },
+    /**
+     * Cancel the watch_native_open command if it is running
+     *
+     * # Arguments
+     *
+     * * `state` - The Tauri app state.
+     *
+     * # Returns
+     *
+     * A `Result` containing `Ok(())` if the command was cancelled, or an error message if the command
+     * was not running.
+     */
+    async cancelWatchNativeOpen(): Promise<Result<null, string>> {
+        try {
+            return {status: "ok", data: await TAURI_INVOKE("cancel_watch_native_open")};
+        }
+        catch (e) {
+            if (e instanceof Error) {
+                throw e;
+            }
+            else {
+                return {status: "error", error: e as any};
+            }
+        }
+    },
+/**
+ * Prepare a file for the upload to a storage provider, compressing it if the provider has the
+ * file compression enabled
+ * 
+ * # Arguments
+ * 
+ * * `app` - The Tauri app handle.
+ * * `state` - The application state.
+ * * `provider` - The storage provider the file will be uploaded to.
+ * * `file_path` - The path of the file to upload.
+ * 
+ * # Returns
+ * 
+ * The file to upload
+ */
+async prepareUpload(provider: StorageProvider, filePath: string) : Promise<Result<PreparedUpload, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("prepare_upload", { provider, filePath }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Decompress in place a downloaded file if it was compressed before the upload
+ * 
+ * Only the files the downloads can be saved to are rewritten, and the decompressed file is
+ * limited to `MAX_DECOMPRESSED_FILE_SIZE`.
+ * 
+ * # Arguments
+ * 
+ * * `app` - The Tauri app handle.
+ * * `file_path` - The path of the downloaded file.
+ * 
+ * # Returns
+ * 
+ * Whether the file was decompressed
+ */
+async finalizeDownload(filePath: string) : Promise<Result<boolean, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("finalize_download", { filePath }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Send a request to a storage provider API using the access token of an account
+ * 
+ * The access token never leaves the backend, it is refreshed if expired and attached to the
+ * request only if the url belongs to the provider.
+ * 
+ * # Arguments
+ * 
+ * * `app` - The Tauri app handle.
+ * * `state` - The application state.
+ * * `account` - The account whose access token authorizes the request.
+ * * `url` - The url of the request.
+ * * `request` - The request.
+ * 
+ * # Returns
+ * 
+ * The response
+ */
+async authorizedFetch(account: ProviderAccount, url: string, request: AuthorizedRequest) : Promise<Result<AuthorizedResponse, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("authorized_fetch", { account, url, request }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Download a file from a storage provider using the access token of an account
+ * 
+ * # Arguments
+ * 
+ * * `app` - The Tauri app handle.
+ * * `state` - The application state.
+ * * `account` - The account whose access token authorizes the download.
+ * * `url` - The url of the file.
+ * * `file_path` - The path to save the file to.
+ * * `on_progress` - The channel receiving the download progress.
+ * 
+ * # Returns
+ * 
+ * Nothing
+ */
+async authorizedDownload(account: ProviderAccount, url: string, filePath: string, onProgress: TAURI_CHANNEL<DownloadProgress>) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("authorized_download", { account, url, filePath, onProgress }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Refresh the access token of an account
+ * 
+ * # Arguments
+ * 
+ * * `app` - The Tauri app handle.
+ * * `state` - The application state.
+ * * `account` - The account to refresh the access token of.
+ * 
+ * # Returns
+ * 
+ * Nothing
+ */
+async refreshProvider(account: ProviderAccount) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("refresh_provider", { account }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+}
+}
+
+/** user-defined events **/
+
+
+export const events = __makeEvents__<{
+credentialsChanged: CredentialsChanged,
+providersChanged: ProvidersChanged,
+settingsChanged: SettingsChanged,
+stateFileChanged: StateFileChanged,
+stateSaveFailed: StateSaveFailed,
+vaultLocked: VaultLocked
+}>({
+credentialsChanged: "credentials-changed",
+providersChanged: "providers-changed",
+settingsChanged: "settings-changed",
+stateFileChanged: "state-file-changed",
+stateSaveFailed: "state-save-failed",
+vaultLocked: "vault-locked"
+})
+
+/** user-defined constants **/
+
+export const STATE_FILE = "state.json" as const;
+export const STATE_ROLLBACK_ERROR = "The state file is older than the last one saved on this device" as const;
+
+/** user-defined types **/
+
+/**
+ * The credential of a storage provider account, the secrets are encrypted with the data key
+ */
+export type AccountCredential = 
+/**
+ * OAuth tokens
+ */
+{ kind: "oauth"; 
+/**
+ * The access token
+ */
+access_token: CryptData; 
+/**
+ * The refresh token
+ */
+refresh_token: CryptData; 
+/**
+ * The expiry date of the access token (utc unix timestamp)
+ */
+expiry: bigint; 
+/**
+ * The type of the access token (usually `Bearer`)
+ */
+token_type: string; 
+/**
+ * The scopes granted to the application
+ */
+scopes: string[] } | 
+/**
+ * A static key pair (S3 style access key and secret)
+ */
+{ kind: "key_pair"; 
+/**
+ * The access key id
+ */
+access_key_id: string; 
+/**
+ * The secret access key
+ */
+secret_access_key: CryptData } | 
+/**
+ * A username and a password (WebDAV or SFTP)
+ */
+{ kind: "password"; 
+/**
+ * The username
+ */
+username: string; 
+/**
+ * The password
+ */
+password: CryptData; 
+/**
+ * Whether the password is an application specific password
+ */
+app_password: boolean }
+/**
+ * A storage provider account as shown to the webview, the credential never leaves the backend
+ */
+export type AccountView = { 
+/**
+ * The stable identifier of the account
+ */
+id: string; 
+/**
+ * The provider of the account
+ */
+provider: StorageProvider; 
+/**
+ * The owner of the account (email or username)
+ */
+owner: string; 
+/**
+ * The kind of the credential of the account
+ */
+kind: CredentialKind; 
+/**
+ * The name of the account as shown by the provider
+ */
+display_name: string | null; 
+/**
+ * The url of the avatar of the account
+ */
+avatar_url: string | null; 
+/**
+ * The scopes granted to the application, empty if the credential has no scopes
+ */
+scopes: string[]; 
+/**
+ * When the access token expires (utc unix timestamp), `None` if the credential does not
+ * expire
+ */
+expiry: bigint | null; 
+/**
+ * The last known storage quota of the account
+ */
+quota: QuotaSnapshot | null }
+/**
+ * The authenticated encryption algorithms that can be used to encrypt data
+ */
+export type AeadAlgorithm = 
+/**
+ * XChaCha20-Poly1305 with a random 24 bytes nonce prepended to the ciphertext
+ */
+"xchacha20-poly1305"
+export type AppStateDeepKeys = "debounced_saver" | "cancellation_tokens" | "schema_version" | "generation" | "password" | "key_slots" | "providers" | "settings"
+export type AppStateDeepResult = 
+/**
+ * The version of the state schema, used to migrate the states written by older releases
+ */
+{ schema_version: number } | 
+/**
+ * The number of times the state was saved, mirrored outside of the vault directory so that
+ * an older copy of the state file cannot be restored unnoticed
+ */
+{ generation: bigint } | 
+/**
+ * The password to access the secure storage
+ */
+{ password: CryptData } | 
+/**
+ * The data key wrapped by each of the available unlock methods
+ */
+{ key_slots: KeySlot[] } | 
+/**
+ * The list of providers
+ */
+{ providers: ProviderData[] } | 
+/**
+ * The settings of the application
+ */
+{ settings: Settings }
+/**
+ * A request to a storage provider API
+ */
+export type AuthorizedRequest = { 
+/**
+ * The http method, defaults to `GET`
+ */
+method: string | null; 
+/**
+ * The request headers, the authorization header is set automatically
+ */
+headers: { [key in string]: string }; 
+/**
+ * The request body
+ */
+body: number[] | null }
+/**
+ * The response of a storage provider API
+ */
+export type AuthorizedResponse = { 
+/**
+ * The http status code
+ */
+status: number; 
+/**
+ * The http status text
+ */
+status_text: string; 
+/**
+ * The response headers
+ */
+headers: { [key in string]: string }; 
+/**
+ * The response body
+ */
+body: number[] }
+/**
+ * An account listed in the manifest of a bundle
+ */
+export type BundledAccount = { 
+/**
+ * The provider of the account
+ */
+provider: StorageProvider; 
+/**
+ * The owner of the account (email or username)
+ */
+owner: string }
+/**
+ * The kind of the credential of an account
+ */
+export type CredentialKind = 
+/**
+ * OAuth tokens
+ */
+"oauth" | 
+/**
+ * A static key pair (S3 style access key and secret)
+ */
+"key_pair" | 
+/**
+ * A username and a password (WebDAV or SFTP)
+ */
+"password"
+/**
+ * Emitted when the password or the key slots change, the new value is never sent
+ */
+export type CredentialsChanged = { 
+/**
+ * The key of the state that changed
+ */
+key: AppStateDeepKeys }
+/**
+ * Represent some data that have been managed cryptographically
+ */
+export type CryptData = { 
+/**
+ * The cryptographically modified data
+ */
+data: number[]; 
+/**
+ * The raw data, never stored on disk (this field is never serialized)
+ */
+raw_data: number[] | null; 
 /**
  * The working mode of the data
  */
@@ -375,6 +1272,15 @@ mode: number;
  * The salt applied when deriving the encryption key
  */
 salt: number[] | null; 
+/**
+ * The key derivation function (and its cost parameters) used to derive the encryption key or
+ * the password hash
+ */
+kdf: KdfAlgorithm; 
+/**
+ * The authenticated encryption algorithm used to encrypt the data
+ */
+aead: AeadAlgorithm; 
 /**
  * The list of related keys in the parent struct, this is used to understand which values are
  * required to (re-)compute the hash
@@ -405,6 +1311,22 @@ export type DefaultPageGroups =
  * The provider default page
  */
 { providers: ProviderPage }
+/**
+ * The progress of a download
+ */
+export type DownloadProgress = { 
+/**
+ * The bytes received since the last progress event
+ */
+progress: bigint; 
+/**
+ * The bytes received so far
+ */
+progress_total: bigint; 
+/**
+ * The size of the file, 0 if unknown
+ */
+total: bigint }
 /**
  * The encryption settings
  */
@@ -449,43 +1371,154 @@ default_to_web_editor: boolean;
  * use your files.
  */
 compress_files: Partial<{ [key in StorageProvider]: boolean }> }
+export type JsonValue = null | boolean | number | string | JsonValue[] | { [key in string]: JsonValue }
+/**
+ * The key derivation functions that can be used to derive a key
+ */
+export type KdfAlgorithm = 
+/**
+ * A single HKDF-SHA3-512 expand, only suitable for high entropy keys
+ */
+{ id: "hkdf-sha3-512" } | 
+/**
+ * The memory-hard Argon2id derivation, used for keys derived from a password
+ */
+{ id: "argon2id"; params: KdfParams }
+/**
+ * The cost parameters of the Argon2id password key derivation function.
+ * 
+ * Defaults follow the OWASP recommendation for Argon2id (19 MiB of memory, 2 iterations, 1 degree
+ * of parallelism).
+ */
+export type KdfParams = { 
+/**
+ * The memory cost in KiB
+ */
+memory_kib: number; 
+/**
+ * The number of iterations (time cost)
+ */
+iterations: number; 
+/**
+ * The degree of parallelism
+ */
+parallelism: number }
+/**
+ * A copy of the data key wrapped by one of the unlock methods.
+ * 
+ * All the secrets in the state are encrypted using the random data key, the data key is stored
+ * encrypted once per unlock method so that changing the password only requires re-wrapping it.
+ */
+export type KeySlot = { 
+/**
+ * The unlock method protecting the data key
+ */
+kind: KeySlotKind; 
+/**
+ * The data key encrypted with the key derived from the unlock secret
+ */
+wrapped_key: CryptData }
+/**
+ * The unlock methods that can wrap the data key
+ */
+export type KeySlotKind = 
+/**
+ * The data key is wrapped by a key derived from the user password
+ */
+"password" | 
+/**
+ * The data key is wrapped by a key derived from the offline recovery key
+ */
+"recovery"
 export type PasswordUpdateEvent = { event: "initialized"; data: { steps: number } } | { event: "step_completed" } | { event: "completed" }
 /**
- * The data of a storage provider
+ * The file to upload to a storage provider
  */
-export type ProviderData = { 
+export type PreparedUpload = { 
 /**
- * The access token
+ * The path of the file to upload
  */
-access_token: CryptData; 
+path: string; 
 /**
- * The refresh token
+ * Whether the file was compressed, if so it must be removed once uploaded
  */
-refresh_token: CryptData; 
+compressed: boolean }
 /**
- * The expiry date of the token (utc unix timestamp)
+ * The handle of a storage provider account, used to look up its tokens
  */
-expiry: bigint; 
+export type ProviderAccount = { 
+/**
+ * The stable identifier of the account
+ */
+id: string }
+/**
+ * The data of a storage provider account
+ */
+export type ProviderData = { 
+/**
+ * The stable identifier of the account
+ */
+id: string; 
 /**
- * The owner of the token (email)
+ * The credential used to access the account
+ */
+credential: AccountCredential; 
+/**
+ * The owner of the account (email or username)
  */
 owner: string; 
 /**
- * The provider of the token
+ * The provider of the account
+ */
+provider: StorageProvider; 
+/**
+ * The name of the account as shown by the provider
+ */
+display_name: string | null; 
+/**
+ * The url of the avatar of the account
+ */
+avatar_url: string | null; 
+/**
+ * The last known storage quota of the account
  */
-provider: StorageProvider }
+quota: QuotaSnapshot | null }
 /**
  * A provider page
  */
 export type ProviderPage = { 
 /**
- * The storage provider
+ * The id of the account shown by the page
  */
-provider: StorageProvider; 
+account_id: string }
 /**
- * The owner of the provider
+ * Emitted when the storage providers change
  */
-owner: string }
+export type ProvidersChanged = { 
+/**
+ * The key of the state that changed
+ */
+key: AppStateDeepKeys; 
+/**
+ * The new storage provider accounts, their credentials are not exposed
+ */
+value: AccountView[] }
+/**
+ * A snapshot of the storage quota of an account
+ */
+export type QuotaSnapshot = { 
+/**
+ * The used storage in bytes
+ */
+used: bigint; 
+/**
+ * The total storage in bytes, `None` if the storage is unlimited
+ */
+total: bigint | null; 
+/**
+ * The date of the snapshot (utc unix timestamp)
+ */
+checked_at: bigint }
 export type Security = { 
 /**
  * The encryption settings
@@ -498,7 +1531,11 @@ two_factor_authentication: TwoFactorAuthentication;
 /**
  * The state file signature, this is used to verify the integrity of the state file.
  */
-signature: CryptData }
+signature: CryptData; 
+/**
+ * The idle time after which the vault is locked, in minutes, 0 to never lock it
+ */
+idle_lock_minutes: number }
 /**
  * The settings of the application
  */
@@ -515,6 +1552,18 @@ general_behaviour: GeneralBehaviour;
  * The security settings
  */
 security: Security }
+/**
+ * Emitted when the settings change
+ */
+export type SettingsChanged = { 
+/**
+ * The key of the state that changed
+ */
+key: AppStateDeepKeys; 
+/**
+ * The new settings
+ */
+value: Settings }
 export type SettingsResult = 
 /**
  * The theme settings
@@ -528,6 +1577,48 @@ export type SettingsResult =
  * The security settings
  */
 { security: Security }
+/**
+ * An operation of a state patch, paths are dot-separated (e.g. `settings.theme.theme` or
+ * `providers.0.owner`)
+ */
+export type StatePatchOperation = 
+/**
+ * Set the value at the path, `-` as the last segment of an array path appends the value
+ */
+{ op: "set"; 
+/**
+ * The path of the value
+ */
+path: string; 
+/**
+ * The new value
+ */
+value: JsonValue } | 
+/**
+ * Remove an element of an array
+ */
+{ op: "remove"; 
+/**
+ * The path of the element
+ */
+path: string }
+/**
+ * Emitted when the state file was changed outside of the application and the change could not be
+ * applied automatically
+ */
+export type StateFileChanged = { 
+/**
+ * Whether the changed state file is validly signed and can replace the state in memory
+ */
+reloadable: boolean }
+/**
+ * Emitted when the state could not be saved to disk, the changes are only kept in memory
+ */
+export type StateSaveFailed = { 
+/**
+ * The error of the last save attempt
+ */
+error: string }
 /**
  * The available storage providers
  */
@@ -545,6 +1636,18 @@ font_size: number;
  * The application theme
  */
 theme: Theme }
+/**
+ * The data needed to register the TOTP secret in an authenticator application
+ */
+export type TotpEnrollment = { 
+/**
+ * The `otpauth://` URI of the secret
+ */
+uri: string; 
+/**
+ * The QR code of the URI as a base64 encoded PNG image
+ */
+qr_code: string }
 /**
  * The two factor authentication settings
  */
@@ -556,7 +1659,114 @@ enabled: boolean;
 /**
  * The two factor authentication secret
  */
-secret: CryptData | null }
+secret: CryptData | null; 
+/**
+ * The hashes of the single-use recovery codes
+ */
+recovery_codes: CryptData[] }
+/**
+ * A password check
+ */
+export type UnlockAttempt = { 
+/**
+ * When the attempt was made (utc unix timestamp)
+ */
+timestamp: bigint; 
+/**
+ * Whether the password was correct
+ */
+succeeded: boolean }
+/**
+ * The password checks of a vault.
+ * 
+ * The attempts are stored in clear next to the state file as they must be read before the vault
+ * is unlocked, for the same reason the lockout threshold lives here instead of in the settings.
+ */
+export type UnlockAttempts = { 
+/**
+ * The number of failed attempts since the last successful one
+ */
+failed_attempts: number; 
+/**
+ * The number of consecutive failed attempts after which only the recovery key can unlock
+ * the vault, `None` to never lock the vault out
+ */
+lockout_threshold: number | null; 
+/**
+ * The latest attempts, the newest last
+ */
+history: UnlockAttempt[]; 
+/**
+ * The number of wrong or missing second factor codes since the last accepted one, they
+ * impose their own delay as a correct password does not prove the code
+ */
+failed_second_factor_attempts: number; 
+/**
+ * When the last second factor code was refused (utc unix timestamp)
+ */
+last_second_factor_failure: bigint | null; 
+/**
+ * Whether a code was asked for since the last accepted one, a check without a code after it
+ * counts as a failed attempt
+ */
+second_factor_requested: boolean }
+/**
+ * The description of the content of a bundle
+ */
+export type VaultBundleManifest = { 
+/**
+ * The version of the bundle format
+ */
+format_version: number; 
+/**
+ * The version of the application that exported the bundle
+ */
+app_version: string; 
+/**
+ * The export date (utc unix timestamp)
+ */
+created_at: bigint; 
+/**
+ * The accounts in the bundle
+ */
+accounts: BundledAccount[] }
+/**
+ * How an imported bundle is applied to the vault
+ */
+export type VaultImportMode = 
+/**
+ * The imported accounts are added to the vault, accounts present in both keep the tokens
+ * expiring last, the local settings are kept
+ */
+"merge" | 
+/**
+ * The accounts and the settings of the vault are replaced by the imported ones
+ */
+"replace"
+/**
+ * Emitted when the vault is locked and the decrypted state is wiped from memory
+ */
+export type VaultLocked = null
+/**
+ * A vault as listed to the user
+ */
+export type VaultSummary = { 
+/**
+ * The unique identifier of the vault
+ */
+id: string; 
+/**
+ * The name of the vault
+ */
+name: string; 
+/**
+ * Whether the vault is the selected one
+ */
+selected: boolean; 
+/**
+ * Whether the vault has been set up with a password
+ */
+initialized: boolean }
 /**
  * Events that can be sent by the watch process event channel.
  */
@@ -0,0 +1,69 @@
+import { AuthorizedRequest, commands, ProviderAccount } from "../tauri-bindings.ts";
+
+/**
+ * Statuses whose responses cannot have a body
+ */
+const NULL_BODY_STATUSES = [ 101, 204, 205, 304 ];
+
+/**
+ * Convert a request body to the bytes sent by the backend
+ * @param {BodyInit | null} body - The request body
+ * @returns {Promise<number[] | null>}
+ */
+async function makeBody(body?: BodyInit | null): Promise<number[] | null> {
+    if (body === undefined || body === null) {
+        return null;
+    }
+
+    if (typeof body === "string") {
+        return Array.from(new TextEncoder().encode(body));
+    }
+
+    if (body instanceof ArrayBuffer) {
+        return Array.from(new Uint8Array(body));
+    }
+
+    if (ArrayBuffer.isView(body)) {
+        return Array.from(new Uint8Array(body.buffer, body.byteOffset, body.byteLength));
+    }
+
+    return Array.from(new Uint8Array(await new Response(body).arrayBuffer()));
+}
+
+/**
+ * Send a request to a storage provider API, the access token of the account is attached by the backend and never
+ * reaches the webview
+ * @param {ProviderAccount} account - The account whose access token authorizes the request
+ * @param {string} url - The URL to fetch
+ * @param {RequestInit} options - The fetch options
+ * @returns {Promise<Response>}
+ * @throws {Error}
+ */
+export async function authorizedFetch(
+    account: ProviderAccount,
+    url: string,
+    options?: RequestInit,
+): Promise<Response> {
+    const headers: { [key in string]: string } = {};
+    new Headers(options?.headers || {}).forEach((value, name) => {
+        headers[name] = value;
+    });
+
+    const request: AuthorizedRequest = {
+        method: options?.method ?? null,
+        headers,
+        body:   await makeBody(options?.body),
+    };
+
+    const result = await commands.authorizedFetch(account, url, request);
+    if (result.status === "error") {
+        throw new Error(result.error);
+    }
+
+    const {status, status_text, body} = result.data;
+    return new Response(NULL_BODY_STATUSES.includes(status) ? null : new Uint8Array(body), {
+        status,
+        statusText: status_text,
+        headers:    new Headers(result.data.headers),
+    });
+}
@@ -1,11 +1,11 @@
-import { Result, Settings } from "../tauri-bindings.ts";
+import { commands, Result, Settings } from "../tauri-bindings.ts";
 
 /**
  * Compute the default page to redirect to after login.
  * @param {Result<Settings, string>} settings
- * @returns {string}
+ * @returns {Promise<string>}
  */
-export function computeDefaultPage(settings: Result<Settings, string>): string {
+export async function computeDefaultPage(settings: Result<Settings, string>): Promise<string> {
     if (settings.status === "error") {
         return "/error?message=" + encodeURIComponent(settings.error);
     }
@@ -23,6 +23,12 @@ export function computeDefaultPage(settings: Result<Settings, string>): string {
         }
     }
     else {
-        return `/dashboard/drives/${ default_page.providers.provider }/${ default_page.providers.owner }`;
+        // the page refers to the account by its id, the account may have been removed since
+        const account = await commands.getAccount(default_page.providers.account_id);
+        if (account.status === "error") {
+            return "/dashboard";
+        }
+
+        return `/dashboard/drives/${ account.data.provider }/${ account.data.owner }`;
     }
 }
\ No newline at end of file
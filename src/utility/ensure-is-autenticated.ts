@@ -33,7 +33,7 @@ export async function ensureIsAuthenticated(
             loadTheme(theme_ctx, settings);
         }
 
-        redirect(computeDefaultPage(settings));
+        redirect(await computeDefaultPage(settings));
     }
 
     return true;
@@ -0,0 +1,12 @@
+import { AccountView } from "../tauri-bindings.ts";
+import { dayjs } from "./dayjs.ts";
+
+/**
+ * Check whether the credential of an account must be refreshed before use.
+ * Only the OAuth tokens expire, the key pairs and the passwords are used as-is.
+ * @param {AccountView} provider
+ * @returns {boolean}
+ */
+export function isCredentialExpired(provider: AccountView): boolean {
+    return provider.expiry !== null && provider.expiry <= dayjs.utc().unix();
+}
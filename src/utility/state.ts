@@ -1,6 +1,4 @@
-import { AppStateDeepKeys, AppStateDeepResult, commands, STATE_FILE } from "../tauri-bindings.ts";
-
-const VAULT_NAME = STATE_FILE;
+import { AppStateDeepKeys, AppStateDeepResult, StatePatchOperation, commands } from "../tauri-bindings.ts";
 
 class StateMarker {
     private constructor() {}
@@ -41,29 +39,37 @@ class State {
     /**
      * Get state instance
      * @param {string} password - The password to use for the state decryption
+     * @param {boolean} skip_password_initialization - Whether the rust state is already initialized
+     * @param {string} totp_code - The two factor authentication code, if enabled
+     * @param {boolean} allow_rollback - Whether to open a vault file older than the last one saved on this device
      * @returns {Promise<State>}
      * @throws {Error}
      */
-    public static async init(password: string, skip_password_initialization?: boolean): Promise<State> {
+    public static async init(
+        password: string,
+        skip_password_initialization?: boolean,
+        totp_code?: string,
+        allow_rollback?: boolean,
+    ): Promise<State> {
         // Check if stronghold is already initialized
         if (State._instance) {
             return State._instance;
         }
 
         if (!password || password === "") {
-            // if no password is provided, try to get it from the rust state
-            const password = await commands.getPassword();
-            if (password.status === "error") {
+            // if no password is provided, the rust state must already be unlocked
+            const is_authenticated = await commands.isAuthenticated();
+            if (!is_authenticated) {
                 throw new Error("Password is required to initialize state");
             }
 
-            // reboot the state with the password
-            return State.init(password.data, true);
+            State._instance = new State();
+            return State._instance;
         }
 
         if (!skip_password_initialization) {
             // Initialize state with password
-            const result = await commands.initState(password);
+            const result = await commands.initState(password, totp_code ?? null, allow_rollback ?? false);
 
             // If an error occurred, throw it
             if (result.status === "error") {
@@ -75,6 +81,29 @@ class State {
         return State._instance;
     }
 
+    /**
+     * Lock the vault, the state must be initialized again to unlock it
+     * @returns {Promise<void>}
+     * @throws {Error}
+     */
+    public static async lock(): Promise<void> {
+        const result = await commands.lockVault();
+
+        // If an error occurred, throw it
+        if (result.status === "error") {
+            throw new Error(result.error);
+        }
+
+        State.forget();
+    }
+
+    /**
+     * Forget the state instance once the vault has been locked
+     */
+    public static forget() {
+        State._instance = undefined;
+    }
+
     /**
      * Insert a record into store
      * @param value The value of the record
@@ -89,6 +118,20 @@ class State {
         }
     }
 
+    /**
+     * Apply a patch to the store, either all the operations are applied or none is
+     * @param operations - The operations to apply, in order
+     * @returns Promise<void>
+     * @throws {Error}
+     */
+    public async patch(operations: StatePatchOperation[]) {
+        const result = await commands.patchState(operations);
+
+        if (result.status === "error") {
+            throw new Error(result.error);
+        }
+    }
+
     /**
      * Get a record from store
      * @param key - The key of the record to get
@@ -121,5 +164,4 @@ class State {
 export {
     StateMarker,
     State,
-    VAULT_NAME,
 };
\ No newline at end of file